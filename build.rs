@@ -0,0 +1,19 @@
+//! Check that the metadata of the runtimes enabled with cargo features is present, see
+//! `src/runtime.rs`. A missing file sets the `missing_<runtime>_metadata` cfg, which turns into a
+//! `compile_error!` naming the command to fetch it, instead of the errors of the subxt macro.
+use std::path::Path;
+
+// Runtimes behind a feature of the same name
+const RUNTIMES: [&str; 2] = ["polkadot", "kusama"];
+
+fn main() {
+    for runtime in RUNTIMES {
+        let file = format!("{runtime}_metadata.scale");
+        println!("cargo:rerun-if-changed={file}");
+        println!("cargo:rustc-check-cfg=cfg(missing_{runtime}_metadata)");
+        let enabled = std::env::var_os(format!("CARGO_FEATURE_{}", runtime.to_uppercase()));
+        if enabled.is_some() && !Path::new(&file).exists() {
+            println!("cargo:rustc-cfg=missing_{runtime}_metadata");
+        }
+    }
+}
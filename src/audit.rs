@@ -0,0 +1,34 @@
+//! Append-only audit log of the submitted extrinsics, a record of the governance actions performed
+//! from this machine.
+//!
+//! Each line is a JSON object with the time, the chain genesis hash, the call, the signer and its
+//! nonce, the extrinsic and block hashes and the decoded events. The log is written to
+//! `audit.jsonl` in the config dir, unless another path is set with `--audit-log` or the
+//! `audit_log` profile setting.
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde_json::Value as Json;
+
+use crate::config::Config;
+
+/// Default location of the audit log
+pub fn default_path() -> Option<PathBuf> {
+    Some(Config::dir()?.join("audit.jsonl"))
+}
+
+/// Append an entry to the audit log, creating it if needed
+pub fn append(path: &Path, entry: &Json) -> Result<()> {
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("failed to create {}", dir.display()))?;
+    }
+
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("failed to open {}", path.display()))?;
+    writeln!(file, "{entry}").with_context(|| format!("failed to write {}", path.display()))
+}
@@ -0,0 +1,332 @@
+//! Daemon voting on the referenda as they start, following the rules of the config file.
+//!
+//! The rules are the `[[auto_vote]]` tables of the config file, the first rule matching a new
+//! referendum decides the vote:
+//!
+//! ```toml
+//! [[auto_vote]]
+//! proposers = ["eve", "5HGjWAeFDfFCWPsjFQdVV2Msvz2XtMktvgocEZcCj68kUMaw"]
+//! vote = "nay"
+//! balance = "1 UNIT"
+//!
+//! [[auto_vote]]
+//! track = "staking_admin"
+//! vote = "aye"
+//! conviction = "1x"
+//! balance = "10 UNIT"
+//! ```
+//!
+//! A rule without `track` or `proposers` matches every referendum. The democracy referenda have
+//! neither a track nor a known proposer, only such rules apply to them. Each decision is written
+//! to the audit log, next to the vote extrinsics, and the votes are notified with the `notify`
+//! command of the profile.
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use serde_json::json;
+use subxt::blocks::ExtrinsicEvents;
+use subxt::ext::futures::StreamExt;
+use subxt::utils::{AccountId32, H256};
+
+use crate::chain::ChainConfig;
+use crate::config::Config;
+use crate::error::CliError;
+use crate::kitchensink::runtime_types::pallet_referenda::types::ReferendumInfo;
+use crate::units::{parse_account, Balance, Conviction};
+use crate::{
+    audit, create_cv_vote, create_vote, dynamic, kitchensink, print, shutdown, warning, Governance,
+    Program,
+};
+
+/// Rule of the config file, as written
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Rule {
+    /// Name or id of the track of the referendum
+    pub track: Option<String>,
+    /// Addresses or dev user names, one of which submitted the referendum
+    #[serde(default)]
+    pub proposers: Vec<String>,
+    pub vote: Direction,
+    /// Conviction of the vote [default: none]
+    pub conviction: Option<String>,
+    /// Balance locked by the vote, in planck or in token units
+    pub balance: String,
+}
+
+/// Direction of the vote of a rule
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    Aye,
+    Nay,
+}
+
+// Rule resolved against the connected chain
+struct Matcher {
+    track: Option<u16>,
+    proposers: Vec<AccountId32>,
+    aye: bool,
+    conviction: Conviction,
+    balance: u128,
+}
+
+// Referendum that just started
+struct Started {
+    index: u32,
+    track: Option<u16>,
+    proposer: Option<AccountId32>,
+}
+
+impl Matcher {
+    fn matches(&self, referendum: &Started) -> bool {
+        let track = self
+            .track
+            .is_none_or(|track| referendum.track == Some(track));
+        let proposer = self.proposers.is_empty()
+            || referendum
+                .proposer
+                .as_ref()
+                .is_some_and(|proposer| self.proposers.contains(proposer));
+        track && proposer
+    }
+}
+
+/// Vote on the referenda starting from now on, until interrupted
+pub async fn run<C: ChainConfig>(program: &Program<C>) -> Result<()> {
+    let rules = Config::load()?.auto_vote;
+    if rules.is_empty() {
+        Err(CliError::Validation {
+            what: "auto_vote".into(),
+            source: anyhow!("no [[auto_vote]] rule in the config file"),
+        })?;
+    }
+    let governance = program.governance()?;
+    let matchers = rules
+        .iter()
+        .zip(1..)
+        .map(|(rule, number)| {
+            resolve(program, rule).map_err(|source| CliError::Validation {
+                what: format!("auto_vote rule {number}"),
+                source,
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let max_lock = matchers
+        .iter()
+        .map(|rule| rule.balance)
+        .max()
+        .unwrap_or_default();
+    warning!(
+        program,
+        "voting as {} on the referenda matching {} rules, locking up to {} per vote",
+        program.user,
+        matchers.len(),
+        program.balance(max_lock)
+    );
+    if !program.confirm("continue?")? {
+        Err(anyhow!("aborted"))?;
+    }
+
+    let mut blocks = program.finalized_blocks().boxed();
+    loop {
+        let block = tokio::select! {
+            biased;
+            () = shutdown::requested() => break,
+            block = blocks.next() => match block {
+                Some(block) => block?,
+                None => break,
+            },
+        };
+        program.unpin();
+
+        for referendum in started(program, governance, block.hash, &block.events).await? {
+            let Some((number, rule)) = (1..)
+                .zip(&matchers)
+                .find(|(_, rule)| rule.matches(&referendum))
+            else {
+                tracing::debug!(index = referendum.index, "no rule matches the referendum");
+                continue;
+            };
+
+            let direction = if rule.aye { "aye" } else { "nay" };
+            print!(
+                program,
+                "#{} referendum {} matches rule {number}, voting {direction} with {} conviction",
+                block.number,
+                referendum.index,
+                rule.conviction
+            );
+            let result = vote(program, governance, referendum.index, rule).await;
+            let outcome = match &result {
+                Ok(events) => json!({ "extrinsic_hash": events.extrinsic_hash() }),
+                Err(err) => json!({ "error": format!("{err:#}") }),
+            };
+            if let Err(err) = log(program, referendum.index, number, rule, outcome) {
+                warning!(program, "failed to write the audit log: {err:#}");
+            }
+            match result {
+                Ok(events) => {
+                    program.output.record(
+                        "auto-vote",
+                        json!({
+                            "block": block.number,
+                            "index": referendum.index,
+                            "rule": number,
+                            "vote": direction,
+                            "extrinsic_hash": events.extrinsic_hash(),
+                        }),
+                        format!(
+                            "voted {direction} on referendum {} {:?}",
+                            referendum.index,
+                            events.extrinsic_hash()
+                        ),
+                    );
+                    program
+                        .notify(&format!(
+                            "voted {direction} on referendum {} (rule {number})",
+                            referendum.index
+                        ))
+                        .await;
+                }
+                Err(err) => {
+                    warning!(
+                        program,
+                        "failed to vote on referendum {}: {err:#}",
+                        referendum.index
+                    );
+                    program
+                        .notify(&format!(
+                            "failed to vote on referendum {}: {err:#}",
+                            referendum.index
+                        ))
+                        .await;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+// Resolve the track name, the proposers and the amounts of a rule
+fn resolve<C: ChainConfig>(program: &Program<C>, rule: &Rule) -> Result<Matcher> {
+    let track = match &rule.track {
+        Some(track) => Some(program.track_id(track)?),
+        None => None,
+    };
+    let proposers = rule
+        .proposers
+        .iter()
+        .map(|proposer| parse_account(proposer))
+        .collect::<Result<_>>()?;
+    let balance = Balance::parse(&rule.balance)?;
+
+    Ok(Matcher {
+        track,
+        proposers,
+        aye: rule.vote == Direction::Aye,
+        conviction: Conviction::parse_or_default(rule.conviction.as_deref())?,
+        balance: program.planck(&balance)?,
+    })
+}
+
+// Referenda started in a block, with their track and proposer when known
+async fn started<C: ChainConfig>(
+    program: &Program<C>,
+    governance: Governance,
+    block: H256,
+    events: &subxt::events::Events<C>,
+) -> Result<Vec<Started>> {
+    let mut referenda = Vec::new();
+    match governance {
+        Governance::OpenGov => {
+            for submitted in events.find::<kitchensink::referenda::events::Submitted>() {
+                let submitted = submitted?;
+                let query = kitchensink::storage()
+                    .referenda()
+                    .referendum_info_for(submitted.index);
+                let info = program.api.storage().at(block).fetch(&query).await?;
+                let proposer = match info {
+                    Some(ReferendumInfo::Ongoing(status)) => Some(status.submission_deposit.who),
+                    _ => None,
+                };
+                referenda.push(Started {
+                    index: submitted.index,
+                    track: Some(submitted.track),
+                    proposer,
+                });
+            }
+        }
+        Governance::Democracy => {
+            for started in events.find::<kitchensink::democracy::events::Started>() {
+                referenda.push(Started {
+                    index: started?.ref_index,
+                    track: None,
+                    proposer: None,
+                });
+            }
+        }
+    }
+    Ok(referenda)
+}
+
+// Submit the vote of a rule without asking, the rules were confirmed on start
+async fn vote<C: ChainConfig>(
+    program: &Program<C>,
+    governance: Governance,
+    index: u32,
+    rule: &Matcher,
+) -> Result<ExtrinsicEvents<C>> {
+    let Matcher {
+        aye,
+        conviction,
+        balance,
+        ..
+    } = *rule;
+    let lock = Some(("lock", balance));
+    match governance {
+        Governance::OpenGov => {
+            let tx = create_cv_vote(index, aye, conviction, balance);
+            program.check_balance(&tx, lock).await?;
+            program.submit_and_watch(&tx).await
+        }
+        Governance::Democracy if program.dynamic => {
+            let tx = dynamic::vote(index, aye, conviction, balance);
+            program.check_balance(&tx, lock).await?;
+            program.submit_and_watch(&tx).await
+        }
+        Governance::Democracy => {
+            let tx = create_vote(index, aye, conviction, balance);
+            program.check_balance(&tx, lock).await?;
+            program.submit_and_watch(&tx).await
+        }
+    }
+}
+
+// Record the decision on a referendum in the audit log
+fn log<C: ChainConfig>(
+    program: &Program<C>,
+    index: u32,
+    number: usize,
+    rule: &Matcher,
+    outcome: serde_json::Value,
+) -> Result<()> {
+    let Some(path) = &program.audit_log else {
+        return Ok(());
+    };
+
+    let entry = json!({
+        "time": chrono::Utc::now().to_rfc3339(),
+        "genesis_hash": program.api.genesis_hash(),
+        "auto_vote": {
+            "referendum": index,
+            "rule": number,
+            "aye": rule.aye,
+            "conviction": rule.conviction.to_string(),
+            "balance": rule.balance,
+        },
+        "signer": program.address(&program.account_id()),
+        "outcome": outcome,
+    });
+    audit::append(path, &entry)
+}
@@ -0,0 +1,158 @@
+//! In-process bus sharing a single finalized block subscription between the consumers of a
+//! program, such as the spinner, the event matchers and the dashboard.
+//!
+//! The subscription is opened by the first consumer, in a background task fetching the events of
+//! each block once and broadcasting them, and closed when the last consumer is dropped. A
+//! consumer falling more than [`CAPACITY`] blocks behind the others gets an error rather than
+//! silently missing blocks.
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use subxt::backend::rpc::RpcClient;
+use subxt::events::Events;
+use subxt::ext::futures::{stream, Stream, StreamExt};
+use subxt::utils::H256;
+use subxt::OnlineClient;
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use crate::chain::ChainConfig;
+use crate::error::CliError;
+use crate::subscription::{self, FinalizedBlock};
+
+/// Number of blocks kept for the slowest consumer
+pub const CAPACITY: usize = 64;
+
+/// Finalized block with its events, as broadcast to the consumers
+pub struct FinalizedEvents<C: ChainConfig> {
+    pub number: u32,
+    pub hash: H256,
+    pub events: Events<C>,
+}
+
+// Broadcast item, the errors are shared as they end the stream of every consumer
+type Item<C> = Result<Arc<FinalizedEvents<C>>, Arc<anyhow::Error>>;
+
+/// Bus of the finalized blocks, cloned handles share the same subscription
+pub struct EventBus<C: ChainConfig> {
+    api: OnlineClient<C>,
+    rpc: RpcClient,
+    /// Sender of the running subscription task, if any
+    sender: Arc<Mutex<Option<broadcast::Sender<Item<C>>>>>,
+}
+
+impl<C: ChainConfig> Clone for EventBus<C> {
+    fn clone(&self) -> Self {
+        Self {
+            api: self.api.clone(),
+            rpc: self.rpc.clone(),
+            sender: self.sender.clone(),
+        }
+    }
+}
+
+impl<C: ChainConfig> EventBus<C> {
+    pub fn new(api: &OnlineClient<C>, rpc: &RpcClient) -> Self {
+        Self {
+            api: api.clone(),
+            rpc: rpc.clone(),
+            sender: Arc::default(),
+        }
+    }
+
+    /// Stream of the blocks finalized from now on, starting the subscription if no other
+    /// consumer is running
+    pub fn subscribe(
+        &self,
+    ) -> impl Stream<Item = Result<Arc<FinalizedEvents<C>>>> + Send + 'static {
+        let receiver = {
+            let mut slot = self.sender.lock().unwrap();
+            match slot.as_ref() {
+                Some(sender) => sender.subscribe(),
+                None => {
+                    let (sender, receiver) = broadcast::channel(CAPACITY);
+                    *slot = Some(sender.clone());
+                    tokio::spawn(publish(
+                        self.api.clone(),
+                        self.rpc.clone(),
+                        sender,
+                        self.sender.clone(),
+                    ));
+                    receiver
+                }
+            }
+        };
+
+        stream::unfold(Some(receiver), |receiver| async move {
+            let mut receiver = receiver?;
+            match receiver.recv().await {
+                Ok(Ok(block)) => Some((Ok(block), Some(receiver))),
+                Ok(Err(err)) => Some((
+                    Err(CliError::Connection(anyhow::anyhow!("{err:#}")).into()),
+                    None,
+                )),
+                Err(RecvError::Lagged(missed)) => {
+                    let err =
+                        anyhow::anyhow!("missed {missed} finalized blocks, processing too slowly");
+                    Some((Err(err), None))
+                }
+                Err(RecvError::Closed) => None,
+            }
+        })
+    }
+}
+
+// Broadcast the finalized blocks until no consumer is left or the subscription fails
+async fn publish<C: ChainConfig>(
+    api: OnlineClient<C>,
+    rpc: RpcClient,
+    sender: broadcast::Sender<Item<C>>,
+    slot: Arc<Mutex<Option<broadcast::Sender<Item<C>>>>>,
+) {
+    let mut blocks = subscription::finalized_blocks(&api, &rpc, None).boxed();
+    loop {
+        let item = match blocks.next().await {
+            Some(Ok(block)) => with_events(&api, block)
+                .await
+                .map(Arc::new)
+                .map_err(Arc::new),
+            Some(Err(err)) => Err(Arc::new(err)),
+            None => return forget(&mut slot.lock().unwrap(), &sender),
+        };
+
+        if item.is_err() {
+            forget(&mut slot.lock().unwrap(), &sender);
+            let _ = sender.send(item);
+            return;
+        }
+        if sender.send(item).is_err() {
+            // a consumer may have subscribed since the send failed
+            let mut slot = slot.lock().unwrap();
+            if sender.receiver_count() == 0 {
+                tracing::debug!("no consumer left, closing the block subscription");
+                return forget(&mut slot, &sender);
+            }
+        }
+    }
+}
+
+/// Fetch the events of a finalized block, only the `System::Events` storage of the block
+pub async fn with_events<C: ChainConfig>(
+    api: &OnlineClient<C>,
+    block: FinalizedBlock,
+) -> Result<FinalizedEvents<C>> {
+    Ok(FinalizedEvents {
+        number: block.number,
+        hash: block.hash,
+        events: api.events().at(block.hash).await?,
+    })
+}
+
+// Forget a stopped task, so that the next consumer starts another one
+fn forget<T>(slot: &mut Option<broadcast::Sender<T>>, sender: &broadcast::Sender<T>) {
+    if slot
+        .as_ref()
+        .is_some_and(|current| current.same_channel(sender))
+    {
+        *slot = None;
+    }
+}
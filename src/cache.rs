@@ -0,0 +1,245 @@
+//! Local sqlite cache of the referenda and votes, built with the `cache` feature.
+//!
+//! The storage maps of [`MAPS`] are saved each time they are read in full from the chain, e.g. by
+//! the dashboard on each finalized block. The list and report commands then read them from the
+//! cache instead of scanning the chain storage again, `--refresh` reads them from the chain.
+//!
+//! The cache of a chain is stored as `cache/<genesis hash>.sqlite` in the config dir, holding the
+//! raw entries of each map along with the block and the runtime version they were read at. The
+//! maps saved by another runtime version are read from the chain again, their types may differ,
+//! as are the maps saved more than a few blocks before the block a command reads.
+//!
+//! The `proposals`, `referenda` and `votes` tables are kept by the `index` command from the
+//! governance events, see [`crate::indexer`].
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use anyhow::{Context, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+use subxt::utils::{AccountId32, H256};
+
+use crate::config::Config;
+use crate::Governance;
+
+/// Storage maps kept in the cache: the referenda with their tally, and the votes
+pub const MAPS: &[&str] = &[
+    "Referenda::ReferendumInfoFor",
+    "ConvictionVoting::VotingFor",
+    "Democracy::ReferendumInfoOf",
+    "Democracy::VotingOf",
+];
+
+// Block and runtime version each map was read at, the entries of the maps, and the tables of the
+// indexer. The balances are stored as text, they don't fit the sqlite integers
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS maps (
+    name TEXT PRIMARY KEY,
+    block INTEGER NOT NULL,
+    spec_version INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS entries (
+    map TEXT NOT NULL,
+    key BLOB NOT NULL,
+    value BLOB NOT NULL,
+    PRIMARY KEY (map, key)
+);
+CREATE TABLE IF NOT EXISTS proposals (
+    idx INTEGER PRIMARY KEY,
+    deposit TEXT NOT NULL,
+    proposed INTEGER NOT NULL,
+    tabled INTEGER
+);
+CREATE TABLE IF NOT EXISTS referenda (
+    governance TEXT NOT NULL,
+    idx INTEGER NOT NULL,
+    track INTEGER,
+    started INTEGER NOT NULL,
+    outcome TEXT,
+    concluded INTEGER,
+    PRIMARY KEY (governance, idx)
+);
+CREATE TABLE IF NOT EXISTS votes (
+    governance TEXT NOT NULL,
+    idx INTEGER NOT NULL,
+    voter BLOB NOT NULL,
+    vote TEXT NOT NULL,
+    block INTEGER NOT NULL,
+    PRIMARY KEY (governance, idx, voter)
+);
+";
+
+/// Governance fact of an indexed block, see [`crate::indexer`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Record {
+    /// Public proposal of the democracy pallet, with its deposit
+    Proposed { index: u32, deposit: u128 },
+    /// Public proposal turned into a referendum
+    Tabled { index: u32 },
+    /// Referendum started, on a track with OpenGov
+    Started {
+        governance: Governance,
+        index: u32,
+        track: Option<u16>,
+    },
+    /// Referendum concluded, e.g. `approved` or `timed out`
+    Concluded {
+        governance: Governance,
+        index: u32,
+        outcome: &'static str,
+    },
+    /// Vote cast on a referendum, as debug text
+    Voted {
+        governance: Governance,
+        index: u32,
+        voter: AccountId32,
+        vote: String,
+    },
+}
+
+/// Raw keys and values of a storage map
+pub type Entries = Vec<(Vec<u8>, Vec<u8>)>;
+
+/// Location of the cache of a chain
+pub fn path(genesis_hash: H256) -> Option<PathBuf> {
+    Some(
+        Config::dir()?
+            .join("cache")
+            .join(format!("{genesis_hash:?}.sqlite")),
+    )
+}
+
+/// Whether a storage map, named `Pallet::Entry`, is kept in the cache
+pub fn is_cached(name: &str) -> bool {
+    MAPS.contains(&name)
+}
+
+/// Cache of the chain the program is connected to
+pub struct Cache {
+    connection: Mutex<Connection>,
+    spec_version: u32,
+    refresh: bool,
+}
+
+impl Cache {
+    /// Open the cache of a chain running the runtime `spec_version`, creating it when missing.
+    /// `None` when there is no config dir
+    pub fn open(genesis_hash: H256, spec_version: u32, refresh: bool) -> Result<Option<Self>> {
+        let Some(path) = path(genesis_hash) else {
+            return Ok(None);
+        };
+
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("failed to create {}", dir.display()))?;
+        }
+        let connection = Connection::open(&path)
+            .with_context(|| format!("failed to open {}", path.display()))?;
+        connection
+            .execute_batch(SCHEMA)
+            .with_context(|| format!("failed to create the tables of {}", path.display()))?;
+        tracing::debug!(path = %path.display(), "opened the cache");
+        Ok(Some(Self {
+            connection: Mutex::new(connection),
+            spec_version,
+            refresh,
+        }))
+    }
+
+    /// Whether the cached maps are read from the chain again instead of from the cache
+    pub fn refresh(&self) -> bool {
+        self.refresh
+    }
+
+    /// Entries of a map, with the block they were read at. `None` when the map wasn't read by the
+    /// running runtime version
+    pub fn entries(&self, map: &str) -> Result<Option<(u32, Entries)>> {
+        let connection = self.connection.lock().unwrap();
+        let block = connection
+            .query_row(
+                "SELECT block FROM maps WHERE name = ?1 AND spec_version = ?2",
+                params![map, self.spec_version],
+                |row| row.get(0),
+            )
+            .optional()?;
+        let Some(block) = block else {
+            return Ok(None);
+        };
+
+        let mut statement =
+            connection.prepare("SELECT key, value FROM entries WHERE map = ?1 ORDER BY key")?;
+        let entries = statement
+            .query_map(params![map], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(Some((block, entries)))
+    }
+
+    /// Replace the entries of a map with the ones read at a block
+    pub fn store(&self, map: &str, block: u32, entries: &Entries) -> Result<()> {
+        let mut connection = self.connection.lock().unwrap();
+        let transaction = connection.transaction()?;
+        transaction.execute("DELETE FROM entries WHERE map = ?1", params![map])?;
+        {
+            let mut insert =
+                transaction.prepare("INSERT INTO entries (map, key, value) VALUES (?1, ?2, ?3)")?;
+            for (key, value) in entries {
+                insert.execute(params![map, key, value])?;
+            }
+        }
+        transaction.execute(
+            "INSERT OR REPLACE INTO maps (name, block, spec_version) VALUES (?1, ?2, ?3)",
+            params![map, block, self.spec_version],
+        )?;
+        transaction.commit()?;
+
+        tracing::debug!(block, entries = entries.len(), "cached {map}");
+        Ok(())
+    }
+
+    /// Save the records of an indexed block, indexing a block again leaves the tables unchanged
+    pub fn index_block(&self, block: u32, records: &[Record]) -> Result<()> {
+        let mut connection = self.connection.lock().unwrap();
+        let transaction = connection.transaction()?;
+        for record in records {
+            match record {
+                Record::Proposed { index, deposit } => transaction.execute(
+                    "INSERT OR REPLACE INTO proposals (idx, deposit, proposed) VALUES (?1, ?2, ?3)",
+                    params![index, deposit.to_string(), block],
+                )?,
+                Record::Tabled { index } => transaction.execute(
+                    "UPDATE proposals SET tabled = ?2 WHERE idx = ?1",
+                    params![index, block],
+                )?,
+                Record::Started {
+                    governance,
+                    index,
+                    track,
+                } => transaction.execute(
+                    "INSERT OR REPLACE INTO referenda (governance, idx, track, started) \
+                     VALUES (?1, ?2, ?3, ?4)",
+                    params![governance.to_string(), index, track, block],
+                )?,
+                Record::Concluded {
+                    governance,
+                    index,
+                    outcome,
+                } => transaction.execute(
+                    "UPDATE referenda SET outcome = ?3, concluded = ?4 \
+                     WHERE governance = ?1 AND idx = ?2",
+                    params![governance.to_string(), index, outcome, block],
+                )?,
+                Record::Voted {
+                    governance,
+                    index,
+                    voter,
+                    vote,
+                } => transaction.execute(
+                    "INSERT OR REPLACE INTO votes (governance, idx, voter, vote, block) \
+                     VALUES (?1, ?2, ?3, ?4, ?5)",
+                    params![governance.to_string(), index, &voter.0[..], vote, block],
+                )?,
+            };
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+}
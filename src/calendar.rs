@@ -0,0 +1,139 @@
+//! Calendar of the upcoming governance deadlines: the next launch period boundary of the democracy
+//! pallet, the end of the voting or decision period of each ongoing referendum and the calls
+//! scheduled in the scheduler's agenda, sorted by block with their estimated date.
+use anyhow::Result;
+use serde_json::json;
+
+use crate::chain::ChainConfig;
+use crate::kitchensink;
+use crate::kitchensink::runtime_types::frame_support::traits::preimages::Bounded;
+use crate::kitchensink::runtime_types::pallet_democracy::types::ReferendumInfo as DemocracyReferendumInfo;
+use crate::kitchensink::runtime_types::pallet_referenda::types::DecidingStatus;
+use crate::output::Table;
+use crate::{storage_key_index, Governance, Program};
+
+// Deadline of the calendar
+struct Deadline {
+    block: u32,
+    kind: &'static str,
+    description: String,
+}
+
+/// Print the upcoming governance deadlines
+pub async fn run<C: ChainConfig>(program: &Program<C>) -> Result<()> {
+    let now = program.snapshot().await?.number;
+    let mut deadlines = match program.governance()? {
+        Governance::Democracy => democracy(program, now).await?,
+        Governance::OpenGov => open_gov(program).await?,
+    };
+    if program.api.metadata().pallet_by_name("Scheduler").is_some() {
+        deadlines.extend(agenda(program).await?);
+    }
+    deadlines.retain(|deadline| deadline.block >= now);
+    deadlines.sort_by_key(|deadline| deadline.block);
+
+    let mut table = Table::new(&["when", "kind", "deadline"]);
+    for Deadline {
+        block,
+        kind,
+        description,
+    } in deadlines
+    {
+        program.output.record_json(
+            "deadline",
+            json!({ "block": block, "kind": kind, "description": description }),
+        );
+        table.add_row(vec![
+            program.describe_block(block, now),
+            kind.to_string(),
+            description,
+        ]);
+    }
+    program.output.table(&table)
+}
+
+// Next launch period boundary and the end of the ongoing democracy referenda
+async fn democracy<C: ChainConfig>(program: &Program<C>, now: u32) -> Result<Vec<Deadline>> {
+    let launch_period = program
+        .api
+        .constants()
+        .at(&kitchensink::constants().democracy().launch_period())?;
+    // proposals are tabled on the blocks that are multiples of the launch period
+    let mut deadlines = vec![Deadline {
+        block: (now / launch_period + 1).saturating_mul(launch_period),
+        kind: "launch",
+        description: "next public proposal or external proposal tabled".to_string(),
+    }];
+
+    let query = kitchensink::storage().democracy().referendum_info_of_iter();
+    for (key, info) in program.iter_storage(&query).await? {
+        if let DemocracyReferendumInfo::Ongoing(status) = info {
+            deadlines.push(Deadline {
+                block: status.end,
+                kind: "referendum",
+                description: format!("voting on referendum #{} ends", storage_key_index(&key)),
+            });
+        }
+    }
+    Ok(deadlines)
+}
+
+// End of the current period of the ongoing OpenGov referenda
+async fn open_gov<C: ChainConfig>(program: &Program<C>) -> Result<Vec<Deadline>> {
+    let mut deadlines = Vec::new();
+    for (track_id, referenda) in program.ongoing_referenda().await? {
+        let track = program.track(track_id)?;
+        for (index, status) in referenda {
+            let (block, period) = match status.deciding {
+                None if status.in_queue => continue,
+                None => (
+                    status.submitted.saturating_add(track.prepare_period),
+                    "prepare",
+                ),
+                Some(DecidingStatus {
+                    confirming: None,
+                    since,
+                }) => (since.saturating_add(track.decision_period), "decision"),
+                Some(DecidingStatus {
+                    confirming: Some(end),
+                    ..
+                }) => (end, "confirm"),
+            };
+            deadlines.push(Deadline {
+                block,
+                kind: "referendum",
+                description: format!(
+                    "{period} period of referendum #{index} on track {} ends",
+                    track.name
+                ),
+            });
+        }
+    }
+    Ok(deadlines)
+}
+
+// Calls of the scheduler's agenda, such as the enactment of the approved referenda
+async fn agenda<C: ChainConfig>(program: &Program<C>) -> Result<Vec<Deadline>> {
+    let mut deadlines = Vec::new();
+    let query = kitchensink::storage().scheduler().agenda_iter();
+    for (key, agenda) in program.iter_storage(&query).await? {
+        let block = storage_key_index(&key);
+        for scheduled in agenda.0.into_iter().flatten() {
+            let call = match &scheduled.call {
+                Bounded::Inline(call) => program.call_name(&call.0),
+                Bounded::Lookup { hash, .. } | Bounded::Legacy { hash } => format!("{hash:?}"),
+                Bounded::__Ignore(_) => "unknown call".to_string(),
+            };
+            let id = scheduled
+                .maybe_id
+                .map(|id| format!(" ({})", hex::encode(id)))
+                .unwrap_or_default();
+            deadlines.push(Deadline {
+                block,
+                kind: "scheduled",
+                description: format!("{call} dispatched{id}"),
+            });
+        }
+    }
+    Ok(deadlines)
+}
@@ -0,0 +1,104 @@
+//! Runtime calls described as JSON, encoded through subxt's dynamic tx API.
+//!
+//! A call description looks like:
+//!
+//! ```json
+//! { "pallet": "System", "call": "remark", "args": { "remark": "0x1234" } }
+//! ```
+//!
+//! Arguments map to SCALE values as follows:
+//! - objects are named composites, arrays are unnamed composites
+//! - `{ "variant": "Name", "fields": ... }` is an enum variant
+//! - `0x` prefixed strings are byte sequences
+//! - numbers, and numeric strings for values that overflow JSON numbers, are integers
+//! - other strings are strings, booleans are booleans
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_json::Value as Json;
+use subxt::dynamic::Value;
+use subxt::ext::scale_value::Composite;
+use subxt::tx::DynamicPayload;
+
+/// A runtime call described by its pallet, call name and arguments
+#[derive(Deserialize, Debug)]
+pub struct CallDescription {
+    pub pallet: String,
+    pub call: String,
+    #[serde(default)]
+    pub args: Json,
+}
+
+impl CallDescription {
+    /// Read a call description from a JSON file
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("invalid call description in {}", path.display()))
+    }
+
+    /// Read a list of call descriptions from a YAML file
+    pub fn list_from_yaml(path: &Path) -> Result<Vec<Self>> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        serde_yaml::from_str(&content)
+            .with_context(|| format!("invalid call descriptions in {}", path.display()))
+    }
+
+    /// Build the dynamic payload for this call
+    pub fn payload(&self) -> Result<DynamicPayload> {
+        let args = to_composite(&self.args)
+            .with_context(|| format!("invalid arguments for {}.{}", self.pallet, self.call))?;
+        Ok(subxt::dynamic::tx(&self.pallet, &self.call, args))
+    }
+}
+
+// Convert JSON call arguments (or variant fields) into a SCALE composite
+fn to_composite(json: &Json) -> Result<Composite<()>> {
+    match json {
+        Json::Null => Ok(Composite::unnamed(vec![])),
+        Json::Array(values) => Ok(Composite::unnamed(
+            values.iter().map(to_value).collect::<Result<Vec<_>>>()?,
+        )),
+        Json::Object(fields) => Ok(Composite::named(
+            fields
+                .iter()
+                .map(|(name, value)| Ok((name.clone(), to_value(value)?)))
+                .collect::<Result<Vec<_>>>()?,
+        )),
+        other => anyhow::bail!("expected an object or an array, got {other}"),
+    }
+}
+
+// Convert a JSON value into a SCALE value
+fn to_value(json: &Json) -> Result<Value> {
+    let value = match json {
+        Json::Null => Value::unnamed_composite(vec![]),
+        Json::Bool(b) => Value::bool(*b),
+        Json::Number(n) => match (n.as_u64(), n.as_i64()) {
+            (Some(n), _) => Value::u128(n as u128),
+            (None, Some(n)) => Value::i128(n as i128),
+            _ => anyhow::bail!("unsupported number {n}, use a string for large integers"),
+        },
+        Json::String(s) => match s.strip_prefix("0x") {
+            Some(hex) => Value::from_bytes(hex::decode(hex).context("invalid hex string")?),
+            // large integers don't fit in a JSON number, accept them as strings
+            None => match s.parse::<u128>() {
+                Ok(n) => Value::u128(n),
+                Err(_) => Value::string(s),
+            },
+        },
+        Json::Array(_) => Value::without_context(to_composite(json)?.into()),
+        Json::Object(fields) => match fields.get("variant") {
+            Some(Json::String(name)) => Value::variant(
+                name,
+                to_composite(fields.get("fields").unwrap_or(&Json::Null))?,
+            ),
+            _ => Value::without_context(to_composite(json)?.into()),
+        },
+    };
+
+    Ok(value)
+}
@@ -0,0 +1,105 @@
+//! Interactive call builder, prompting for a pallet, a call and its arguments from the runtime
+//! metadata. Arguments use the `scale_value` syntax (e.g. `Some(10)`, `{ a: 1 }`), with hex
+//! strings for bytes and ss58 strings for accounts.
+use std::io::{BufRead, Write};
+
+use anyhow::{Context, Result};
+use subxt::dynamic::Value;
+use subxt::ext::scale_encode::EncodeAsType;
+use subxt::ext::scale_value::stringify::{self, custom_parsers};
+use subxt::ext::scale_value::Composite;
+use subxt::tx::DynamicPayload;
+use subxt::Metadata;
+
+/// Interactively build a call from the metadata
+pub fn build_call(metadata: &Metadata) -> Result<DynamicPayload> {
+    let mut pallets = metadata
+        .pallets()
+        .filter(|pallet| pallet.call_variants().is_some())
+        .collect::<Vec<_>>();
+    pallets.sort_by_key(|pallet| pallet.name());
+    let names = pallets
+        .iter()
+        .map(|pallet| pallet.name())
+        .collect::<Vec<_>>();
+    let pallet = pallets[choose("pallet", &names)?];
+
+    let calls = pallet.call_variants().unwrap_or_default();
+    let names = calls
+        .iter()
+        .map(|call| call.name.as_str())
+        .collect::<Vec<_>>();
+    let call = &calls[choose("call", &names)?];
+
+    let mut args = Vec::new();
+    for (i, field) in call.fields.iter().enumerate() {
+        let name = field.name.clone().unwrap_or_else(|| format!("arg{i}"));
+        let type_name = field.type_name.as_deref().unwrap_or("?");
+        let value = loop {
+            let input = prompt(&format!("{name} ({type_name})"))?;
+            match parse_arg(&input, field.ty.id, metadata) {
+                Ok(value) => break value,
+                Err(err) => eprintln!("invalid value: {err:#}"),
+            }
+        };
+        args.push((name, value));
+    }
+
+    Ok(subxt::dynamic::tx(
+        pallet.name(),
+        &call.name,
+        Composite::named(args),
+    ))
+}
+
+// Parse an argument and check that it encodes to the expected type
+fn parse_arg(input: &str, type_id: u32, metadata: &Metadata) -> Result<Value> {
+    let (value, rest) = stringify::from_str_custom()
+        .add_custom_parser(custom_parsers::parse_hex)
+        .add_custom_parser(custom_parsers::parse_ss58)
+        .parse(input);
+
+    // accept unquoted strings for plain text arguments
+    let value = match value {
+        Ok(value) if rest.trim().is_empty() => value,
+        _ => Value::string(input),
+    };
+
+    let mut encoded = Vec::new();
+    value
+        .encode_as_type_to(type_id, metadata.types(), &mut encoded)
+        .context("value doesn't match the argument type")?;
+    Ok(value)
+}
+
+// Let the user pick an option by index or name
+fn choose(what: &str, options: &[&str]) -> Result<usize> {
+    for (i, option) in options.iter().enumerate() {
+        eprintln!("{i:>3}: {option}");
+    }
+
+    loop {
+        let input = prompt(&format!("select a {what}"))?;
+        let choice = input
+            .parse::<usize>()
+            .ok()
+            .filter(|i| *i < options.len())
+            .or_else(|| options.iter().position(|option| *option == input));
+        match choice {
+            Some(i) => return Ok(i),
+            None => eprintln!("unknown {what} {input}"),
+        }
+    }
+}
+
+// Prompt the user for a line of input
+fn prompt(label: &str) -> Result<String> {
+    eprint!("{label}: ");
+    std::io::stderr().flush()?;
+
+    let mut line = String::new();
+    if std::io::stdin().lock().read_line(&mut line)? == 0 {
+        anyhow::bail!("unexpected end of input");
+    }
+    Ok(line.trim().to_string())
+}
@@ -0,0 +1,176 @@
+//! Well-known chains, with their public RPC endpoints and formatting properties.
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use sp_core::crypto::{Ss58AddressFormat, Ss58Codec};
+use subxt::config::substrate::{BlakeTwo256, SubstrateHeader};
+use subxt::config::ExtrinsicParams;
+use subxt::utils::{AccountId32, MultiSignature, H256};
+use subxt::{Config, PolkadotConfig, SubstrateConfig};
+use subxt_signer::sr25519::PublicKey;
+
+use crate::units::parse_h256;
+
+/// subxt configurations the [`crate::Program`] runs with. The account, hash and header types are
+/// those of the generated runtime types, the address format and the signed extensions vary
+/// between chains.
+pub trait ChainConfig:
+    Config<
+    Hash = H256,
+    AccountId = AccountId32,
+    Address: From<PublicKey>,
+    Signature = MultiSignature,
+    Hasher = BlakeTwo256,
+    Header = SubstrateHeader<u32, BlakeTwo256>,
+    ExtrinsicParams: ExtrinsicParams<Self, OtherParams: Default>,
+>
+{
+}
+
+impl ChainConfig for SubstrateConfig {}
+impl ChainConfig for PolkadotConfig {}
+
+/// Chains with built-in presets
+#[derive(PartialEq, Debug, Clone, Copy, clap::ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Chain {
+    Polkadot,
+    Kusama,
+    Westend,
+    Rococo,
+    Local,
+}
+
+/// Most decimals of a token, those of a `u128` balance
+const MAX_DECIMALS: u8 = 38;
+
+/// Formatting properties of a chain
+#[derive(Debug, Clone)]
+pub struct ChainProperties {
+    pub ss58_prefix: u16,
+    pub decimals: u8,
+    pub symbol: String,
+}
+
+impl ChainProperties {
+    /// Override the properties with the ones reported by the node's `system_properties`
+    pub fn update_from(&mut self, properties: &Map<String, Value>) {
+        // multi-token chains report lists, the first entry is the native token
+        let first = |key: &str| match properties.get(key) {
+            Some(Value::Array(values)) => values.first(),
+            value => value,
+        };
+
+        if let Some(prefix) = first("ss58Format").and_then(|value| value.as_u64()) {
+            self.ss58_prefix = prefix as u16;
+        }
+        if let Some(decimals) = first("tokenDecimals").and_then(|value| value.as_u64()) {
+            if decimals <= MAX_DECIMALS as u64 {
+                self.decimals = decimals as u8;
+            } else {
+                tracing::warn!(
+                    "ignoring the tokenDecimals {decimals} of the node, keeping {}",
+                    self.decimals
+                );
+            }
+        }
+        if let Some(symbol) = first("tokenSymbol").and_then(|value| value.as_str()) {
+            self.symbol = symbol.to_string();
+        }
+    }
+
+    /// Format a balance in token units, e.g. `1.5 DOT`
+    pub fn format_balance(&self, amount: u128) -> String {
+        // any amount is a fraction of a unit too large for a u128
+        let (whole, fraction) = match 10u128.checked_pow(self.decimals as u32) {
+            Some(unit) => (amount / unit, amount % unit),
+            None => (0, amount),
+        };
+        let fraction = format!("{fraction:0width$}", width = self.decimals as usize);
+        let fraction = fraction.trim_end_matches('0');
+        if fraction.is_empty() {
+            format!("{whole} {}", self.symbol)
+        } else {
+            format!("{whole}.{fraction} {}", self.symbol)
+        }
+    }
+
+    /// Format an account as an SS58 address with the prefix of the chain
+    pub fn format_address(&self, account: &AccountId32) -> String {
+        sp_core::crypto::AccountId32::new(account.0)
+            .to_ss58check_with_version(Ss58AddressFormat::custom(self.ss58_prefix))
+    }
+}
+
+impl Chain {
+    /// Lowercase name of the chain, as accepted on the command line
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Polkadot => "polkadot",
+            Self::Kusama => "kusama",
+            Self::Westend => "westend",
+            Self::Rococo => "rococo",
+            Self::Local => "local",
+        }
+    }
+
+    /// RPC endpoints of the chain, tried in order
+    pub fn endpoints(&self) -> &'static [&'static str] {
+        match self {
+            Self::Polkadot => &[
+                "wss://rpc.polkadot.io",
+                "wss://polkadot-rpc.dwellir.com",
+                "wss://polkadot.api.onfinality.io/public-ws",
+            ],
+            Self::Kusama => &[
+                "wss://kusama-rpc.polkadot.io",
+                "wss://kusama-rpc.dwellir.com",
+                "wss://kusama.api.onfinality.io/public-ws",
+            ],
+            Self::Westend => &[
+                "wss://westend-rpc.polkadot.io",
+                "wss://westend-rpc.dwellir.com",
+            ],
+            Self::Rococo => &[
+                "wss://rococo-rpc.polkadot.io",
+                "wss://rococo-rpc.dwellir.com",
+            ],
+            Self::Local => &["ws://127.0.0.1:9944"],
+        }
+    }
+
+    /// Genesis hash of the chain, selecting its cached metadata without a connection. A local
+    /// chain has none of its own
+    pub fn genesis_hash(&self) -> Option<H256> {
+        let hash = match self {
+            Self::Polkadot => "0x91b171bb158e2d3848fa23a9f1c25182fb8e20313b2c1eb49219da7a70ce90c3",
+            Self::Kusama => "0xb0a8d493285c2df73290dfb7e61f870f17b41801197a149ca93654499ea3dafe",
+            Self::Westend => "0xe143f23803ac50e8f6f8e62695d1ce9e4e1d68aa36c1cd2cfd15340213f3423e",
+            Self::Rococo => "0x6408de7737c59c238890533af25896a2c20608d8b380bb01029acb392781063e",
+            Self::Local => return None,
+        };
+        parse_h256(hash).ok()
+    }
+
+    /// Whether the chain addresses accounts through the indices pallet, as the substrate node
+    /// does, and is driven with [`SubstrateConfig`] rather than [`PolkadotConfig`]
+    pub fn has_indices(&self) -> bool {
+        matches!(self, Self::Local)
+    }
+
+    /// Formatting properties of the chain
+    pub fn properties(&self) -> ChainProperties {
+        let (ss58_prefix, decimals, symbol) = match self {
+            Self::Polkadot => (0, 10, "DOT"),
+            Self::Kusama => (2, 12, "KSM"),
+            Self::Westend => (42, 12, "WND"),
+            Self::Rococo => (42, 12, "ROC"),
+            Self::Local => (42, 12, "UNIT"),
+        };
+
+        ChainProperties {
+            ss58_prefix,
+            decimals,
+            symbol: symbol.to_string(),
+        }
+    }
+}
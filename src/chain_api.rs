@@ -0,0 +1,231 @@
+//! Storage access of the queries, behind a trait so they run against a node or against in-memory
+//! storage without one.
+//!
+//! [`ChainApi`] only exposes the metadata and the raw storage of the latest block, the typed
+//! [`fetch`] and [`iter`] helpers encode the keys and decode the values of the generated storage
+//! addresses with the metadata, as subxt does, and [`constant`] decodes the generated constant
+//! addresses.
+use std::collections::BTreeMap;
+
+use anyhow::{Context, Result};
+use codec::Encode;
+use jsonrpsee::core::async_trait;
+use subxt::constants::ConstantAddress;
+use subxt::metadata::DecodeWithMetadata;
+use subxt::storage::address::{StorageAddress, Yes};
+use subxt::{Metadata, OnlineClient};
+
+use crate::chain::ChainConfig;
+
+/// Metadata and raw storage of a chain
+#[async_trait]
+pub trait ChainApi: Send + Sync {
+    /// Metadata of the runtime
+    fn metadata(&self) -> Metadata;
+
+    /// Number of the latest finalized block
+    async fn latest_block_number(&self) -> Result<u32>;
+
+    /// Value stored at a key in the latest finalized block
+    async fn fetch_raw(&self, key: Vec<u8>) -> Result<Option<Vec<u8>>>;
+
+    /// Keys and values stored under a key prefix in the latest finalized block
+    async fn iter_raw(&self, prefix: Vec<u8>) -> Result<Vec<(Vec<u8>, Vec<u8>)>>;
+}
+
+#[async_trait]
+impl<C: ChainConfig> ChainApi for OnlineClient<C> {
+    fn metadata(&self) -> Metadata {
+        OnlineClient::metadata(self)
+    }
+
+    async fn latest_block_number(&self) -> Result<u32> {
+        Ok(self.blocks().at_latest().await?.number())
+    }
+
+    async fn fetch_raw(&self, key: Vec<u8>) -> Result<Option<Vec<u8>>> {
+        Ok(self.storage().at_latest().await?.fetch_raw(key).await?)
+    }
+
+    async fn iter_raw(&self, prefix: Vec<u8>) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        let block = self.backend().latest_finalized_block_ref().await?;
+        let mut values = self
+            .backend()
+            .storage_fetch_descendant_values(prefix, block.hash())
+            .await?;
+
+        let mut entries = Vec::new();
+        while let Some(value) = values.next().await {
+            let value = value?;
+            entries.push((value.key, value.value));
+        }
+        Ok(entries)
+    }
+}
+
+/// Chain kept in memory, holding the storage of a single block, to run the queries without a node
+pub struct MockChain {
+    metadata: Metadata,
+    block_number: u32,
+    storage: BTreeMap<Vec<u8>, Vec<u8>>,
+}
+
+impl MockChain {
+    pub fn new(metadata: Metadata) -> Self {
+        Self {
+            metadata,
+            block_number: 0,
+            storage: BTreeMap::new(),
+        }
+    }
+
+    /// Set the number of the block
+    pub fn set_block_number(&mut self, block_number: u32) {
+        self.block_number = block_number;
+    }
+
+    /// Store a value at a storage address, the address of a map entry must hold its keys
+    pub fn insert<Address: StorageAddress>(
+        &mut self,
+        address: &Address,
+        value: impl Encode,
+    ) -> Result<()> {
+        let key = storage_key(address, &self.metadata)?;
+        self.storage.insert(key, value.encode());
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ChainApi for MockChain {
+    fn metadata(&self) -> Metadata {
+        self.metadata.clone()
+    }
+
+    async fn latest_block_number(&self) -> Result<u32> {
+        Ok(self.block_number)
+    }
+
+    async fn fetch_raw(&self, key: Vec<u8>) -> Result<Option<Vec<u8>>> {
+        Ok(self.storage.get(&key).cloned())
+    }
+
+    async fn iter_raw(&self, prefix: Vec<u8>) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        Ok(self
+            .storage
+            .range(prefix.clone()..)
+            .take_while(|(key, _)| key.starts_with(&prefix))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect())
+    }
+}
+
+/// Fetch and decode the value at a storage address
+pub async fn fetch<Address: StorageAddress>(
+    chain: &(impl ChainApi + ?Sized),
+    address: &Address,
+) -> Result<Option<Address::Target>> {
+    let metadata = chain.metadata();
+    let Some(bytes) = chain.fetch_raw(storage_key(address, &metadata)?).await? else {
+        return Ok(None);
+    };
+    let value_type = value_type(address, &metadata)?;
+    Ok(Some(Address::Target::decode_with_metadata(
+        &mut &bytes[..],
+        value_type,
+        &metadata,
+    )?))
+}
+
+/// Fetch and decode all the entries of a storage map, with their keys. Like subxt's `iter`, the
+/// keys held by the address are ignored
+pub async fn iter<Address: StorageAddress<IsIterable = Yes>>(
+    chain: &(impl ChainApi + ?Sized),
+    address: &Address,
+) -> Result<Vec<(Vec<u8>, Address::Target)>> {
+    let metadata = chain.metadata();
+    let value_type = value_type(address, &metadata)?;
+    chain
+        .iter_raw(storage_prefix(address))
+        .await?
+        .into_iter()
+        .map(|(key, bytes)| {
+            let value =
+                Address::Target::decode_with_metadata(&mut &bytes[..], value_type, &metadata)?;
+            Ok((key, value))
+        })
+        .collect()
+}
+
+/// Decode the value of a constant from the metadata
+pub fn constant<Address: ConstantAddress>(
+    chain: &(impl ChainApi + ?Sized),
+    address: &Address,
+) -> Result<Address::Target> {
+    let metadata = chain.metadata();
+    let constant = metadata
+        .pallet_by_name(address.pallet_name())
+        .and_then(|pallet| pallet.constant_by_name(address.constant_name()))
+        .with_context(|| {
+            format!(
+                "no constant {}::{} in the metadata",
+                address.pallet_name(),
+                address.constant_name()
+            )
+        })?;
+    Ok(Address::Target::decode_with_metadata(
+        &mut constant.value(),
+        constant.ty(),
+        &metadata,
+    )?)
+}
+
+/// Key of a storage address: the hashed pallet and entry names followed by the hashed map keys
+pub fn storage_key<Address: StorageAddress>(
+    address: &Address,
+    metadata: &Metadata,
+) -> Result<Vec<u8>> {
+    let mut key = storage_prefix(address);
+    address.append_entry_bytes(metadata, &mut key)?;
+    Ok(key)
+}
+
+// Key prefix of all the values of a storage entry
+fn storage_prefix<Address: StorageAddress>(address: &Address) -> Vec<u8> {
+    let mut prefix = sp_core::twox_128(address.pallet_name().as_bytes()).to_vec();
+    prefix.extend(sp_core::twox_128(address.entry_name().as_bytes()));
+    prefix
+}
+
+/// Name of the storage entry holding a key, as `Pallet::Entry`
+pub fn entry_name(metadata: &Metadata, key: &[u8]) -> Option<String> {
+    let (pallet_hash, entry_hash) = (key.get(..16)?, key.get(16..32)?);
+    let pallet = metadata
+        .pallets()
+        .find(|pallet| sp_core::twox_128(pallet.name().as_bytes()) == pallet_hash)?;
+    let entry = pallet
+        .storage()?
+        .entries()
+        .iter()
+        .find(|entry| sp_core::twox_128(entry.name().as_bytes()) == entry_hash)?;
+    Some(format!("{}::{}", pallet.name(), entry.name()))
+}
+
+/// Type of the values of a storage entry
+pub(crate) fn value_type<Address: StorageAddress>(
+    address: &Address,
+    metadata: &Metadata,
+) -> Result<u32> {
+    metadata
+        .pallet_by_name(address.pallet_name())
+        .and_then(|pallet| pallet.storage())
+        .and_then(|storage| storage.entry_by_name(address.entry_name()))
+        .map(|entry| entry.entry_type().value_ty())
+        .with_context(|| {
+            format!(
+                "no storage entry {}::{} in the metadata",
+                address.pallet_name(),
+                address.entry_name()
+            )
+        })
+}
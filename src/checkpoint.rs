@@ -0,0 +1,53 @@
+//! Last block processed by the long-running commands, saved when they stop so that the next run
+//! resumes after it instead of missing the blocks finalized in between.
+//!
+//! Checkpoints are stored under `checkpoints/<name>-<genesis hash>` in the config dir, so each
+//! chain gets its own, as the block number in text.
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use subxt::utils::H256;
+
+use crate::config::Config;
+
+// Location of the checkpoint of a command on a chain
+fn path(name: &str, genesis_hash: H256) -> Option<PathBuf> {
+    Some(
+        Config::dir()?
+            .join("checkpoints")
+            .join(format!("{name}-{genesis_hash:?}")),
+    )
+}
+
+/// Block saved by the last run of a command on a chain, if any
+pub fn load(name: &str, genesis_hash: H256) -> Result<Option<u32>> {
+    let Some(path) = path(name, genesis_hash).filter(|path| path.exists()) else {
+        return Ok(None);
+    };
+
+    let block = std::fs::read_to_string(&path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    let block = block
+        .trim()
+        .parse()
+        .with_context(|| format!("invalid checkpoint in {}", path.display()))?;
+    Ok(Some(block))
+}
+
+/// Save the last block processed by a command on a chain
+pub fn save(name: &str, genesis_hash: H256, block: u32) -> Result<()> {
+    let Some(path) = path(name, genesis_hash) else {
+        return Ok(());
+    };
+
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("failed to create {}", dir.display()))?;
+    }
+    // written aside then renamed, a checkpoint is never left half written
+    tracing::debug!(path = %path.display(), block, "saving the checkpoint");
+    let partial = path.with_extension("partial");
+    std::fs::write(&partial, format!("{block}\n"))
+        .with_context(|| format!("failed to write {}", partial.display()))?;
+    std::fs::rename(&partial, &path).with_context(|| format!("failed to write {}", path.display()))
+}
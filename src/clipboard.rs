@@ -0,0 +1,46 @@
+//! Copy to the system clipboard through the clipboard tool of the platform: `pbcopy` on macOS,
+//! `wl-copy`, `xclip` or `xsel` on Linux and `clip.exe` on Windows and WSL.
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use anyhow::Result;
+
+// Clipboard tools with their arguments, tried in order
+const TOOLS: &[(&str, &[&str])] = &[
+    ("pbcopy", &[]),
+    ("wl-copy", &[]),
+    ("xclip", &["-selection", "clipboard"]),
+    ("xsel", &["--clipboard", "--input"]),
+    ("clip.exe", &[]),
+];
+
+/// Copy a text to the clipboard with the first clipboard tool that works
+pub fn copy(text: &str) -> Result<()> {
+    for (tool, args) in TOOLS {
+        match run(tool, args, text) {
+            Ok(true) => return Ok(()),
+            Ok(false) => tracing::debug!(tool, "clipboard tool failed"),
+            Err(err) => tracing::trace!(tool, %err, "clipboard tool unavailable"),
+        }
+    }
+
+    let tools = TOOLS.iter().map(|(tool, _)| *tool).collect::<Vec<_>>();
+    anyhow::bail!(
+        "no working clipboard tool, install one of {}",
+        tools.join(", ")
+    )
+}
+
+// Run a clipboard tool with the text on its stdin, returning whether it succeeded
+fn run(tool: &str, args: &[&str], text: &str) -> std::io::Result<bool> {
+    let mut child = Command::new(tool)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(text.as_bytes())?;
+    }
+    Ok(child.wait()?.success())
+}
@@ -0,0 +1,99 @@
+//! Balances of the signing account.
+use anyhow::Result;
+use serde_json::json;
+
+use crate::chain::ChainConfig;
+use crate::kitchensink;
+use crate::kitchensink::runtime_types::frame_system::AccountInfo;
+use crate::runtime::Runtime;
+use crate::{dynamic, Program};
+
+/// Balances subcommands
+#[derive(clap::Subcommand, Debug)]
+pub enum Command {
+    ShowBalance {
+        /// Compiled runtime whose types decode the account [default: detected from the chain
+        /// metadata]
+        #[clap(long)]
+        runtime: Option<Runtime>,
+    },
+}
+
+/// Run a balances subcommand
+pub async fn run<C: ChainConfig>(program: &Program<C>, command: Command) -> Result<()> {
+    match command {
+        Command::ShowBalance { .. } if program.dynamic => {
+            let account = program.account_id();
+            let api = program.storage().await?;
+            if let Some(info) = api.fetch(&dynamic::account(&account)).await? {
+                let info = info.to_value()?;
+                program.output.record(
+                    "account",
+                    json!({ "address": program.address(&account), "info": info }),
+                    format!("account: {info}"),
+                );
+            }
+        }
+        Command::ShowBalance { runtime }
+            if runtime.unwrap_or(program.runtime) != Runtime::Kitchensink =>
+        {
+            let runtime = runtime.unwrap_or(program.runtime);
+            let account = program.account_id();
+            let storage = program.storage().await?;
+            let info = runtime.account_info(&storage, &account).await?;
+            program.output.record(
+                "account",
+                json!({ "address": program.address(&account), "info": info }),
+                format!("account: {info}"),
+            );
+        }
+        Command::ShowBalance { .. } => {
+            let account = program.account_id();
+            let api = program.storage().await?;
+
+            // the three queries are independent, fetched at the same block
+            let account_query = kitchensink::storage().system().account(&account);
+            let holds_query = kitchensink::storage().balances().holds(&account);
+            let freezes_query = kitchensink::storage().balances().freezes(&account);
+            let (AccountInfo { data, .. }, holds, freezes) = tokio::try_join!(
+                api.fetch_or_default(&account_query),
+                api.fetch_or_default(&holds_query),
+                api.fetch_or_default(&freezes_query),
+            )?;
+            program.output.record(
+                "balance",
+                json!({
+                    "address": program.address(&account),
+                    "free": data.free,
+                    "reserved": data.reserved,
+                    "frozen": data.frozen,
+                }),
+                format!(
+                    "account {}: free: {}, reserved: {}, frozen: {}",
+                    program.address(&account),
+                    program.balance(data.free),
+                    program.balance(data.reserved),
+                    program.balance(data.frozen)
+                ),
+            );
+
+            for hold in holds.0 {
+                program.output.record(
+                    "hold",
+                    json!({ "id": format!("{:?}", hold.id), "amount": hold.amount }),
+                    format!("hold {:?}: {}", hold.id, program.balance(hold.amount)),
+                );
+            }
+
+            for freeze in freezes.0 {
+                program.output.record(
+                    "freeze",
+                    json!({ "id": format!("{:?}", freeze.id), "amount": freeze.amount }),
+                    format!("freeze {:?}: {}", freeze.id, program.balance(freeze.amount)),
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,227 @@
+//! Encoding and hashing calls, and the payloads to sign them, the commands that also run offline
+//! with cached metadata.
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde_json::json;
+use subxt::backend::RuntimeVersion;
+use subxt::client::{OfflineClient, OfflineClientT};
+use subxt::tx::TxPayload;
+use subxt::utils::H256;
+use subxt::{Metadata, PolkadotConfig, SubstrateConfig};
+
+use crate::chain::{Chain, ChainConfig};
+use crate::error::CliError;
+use crate::metadata::{self, OfflineMetadata};
+use crate::output::Output;
+use crate::units::parse_h256;
+use crate::{call_builder, encode_call, print_call_hash, CallData, Program};
+
+/// Call subcommands
+#[derive(clap::Subcommand, Debug)]
+pub enum Command {
+    HashCall {
+        #[clap(flatten)]
+        call: CallData,
+        /// Copy the call hash to the clipboard
+        #[clap(long)]
+        copy: bool,
+    },
+    BuildCall {
+        /// Copy the call data to the clipboard
+        #[clap(long)]
+        copy: bool,
+    },
+    /// Write the payload a signer signs for a call to a JSON file, e.g. to sign it on an
+    /// air-gapped machine. The transaction is immortal and without tip
+    SigningPayload {
+        #[clap(flatten)]
+        call: CallData,
+        /// Nonce of the signing account [default: its next nonce on the chain, required offline]
+        #[clap(long)]
+        nonce: Option<u64>,
+        /// Genesis hash of the chain, offline when the metadata doesn't come from its cache. Online
+        /// it must be the genesis hash of the connected chain
+        #[clap(long, value_parser = parse_h256)]
+        genesis_hash: Option<H256>,
+        /// JSON file to write the payload to
+        #[clap(long)]
+        out: PathBuf,
+    },
+}
+
+/// Run a call subcommand
+pub async fn run<C: ChainConfig>(program: &Program<C>, command: Command) -> Result<()> {
+    match command {
+        Command::HashCall { call, copy } => {
+            let image = program.encode_call(&call)?;
+            print_call_hash(&program.output, &image, false, copy)?;
+        }
+        Command::BuildCall { copy } => {
+            let payload = call_builder::build_call(&program.api.metadata())?;
+            let image = program.api.tx().call_data(&payload)?;
+            print_call_hash(&program.output, &image, true, copy)?;
+        }
+        Command::SigningPayload {
+            call,
+            nonce,
+            genesis_hash,
+            out,
+        } => {
+            // online the payload is for the connected chain
+            let chain_genesis_hash = program.api.genesis_hash();
+            if let Some(genesis_hash) = genesis_hash.filter(|hash| *hash != chain_genesis_hash) {
+                return Err(CliError::Validation {
+                    what: "--genesis-hash".into(),
+                    source: anyhow::anyhow!(
+                        "{genesis_hash:?} is not the genesis hash {chain_genesis_hash:?} of the \
+                         connected chain"
+                    ),
+                }
+                .into());
+            }
+            let image = program.encode_call(&call)?;
+            let nonce = match nonce {
+                Some(nonce) => nonce,
+                None => {
+                    program
+                        .api
+                        .tx()
+                        .account_nonce(&program.account_id())
+                        .await?
+                }
+            };
+            let payload = signing_payload(&program.api, image.clone(), nonce)?;
+            write_payload(
+                &program.output,
+                &out,
+                chain_genesis_hash,
+                &program.api.runtime_version(),
+                nonce,
+                (&image, &payload),
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Run a call subcommand with the given metadata, without a connection to the chain. The
+/// transactions of `chain` are signed with its configuration
+pub fn run_offline(
+    output: &Output,
+    offline: &OfflineMetadata,
+    chain: Chain,
+    command: Command,
+) -> Result<()> {
+    let metadata = &offline.metadata;
+    match command {
+        Command::HashCall { call, copy } => {
+            let image = encode_call(&call, metadata)?;
+            print_call_hash(output, &image, false, copy)?;
+        }
+        Command::BuildCall { copy } => {
+            let payload = call_builder::build_call(metadata)?;
+            let image = payload.encode_call_data(metadata)?;
+            print_call_hash(output, &image, true, copy)?;
+        }
+        Command::SigningPayload {
+            call,
+            nonce,
+            genesis_hash,
+            out,
+        } => {
+            let Some(nonce) = nonce else {
+                anyhow::bail!("--nonce is required offline");
+            };
+            let Some(genesis_hash) = genesis_hash.or(offline.genesis_hash) else {
+                anyhow::bail!(
+                    "the chain of {} is unknown, pass its --genesis-hash",
+                    offline.source
+                );
+            };
+            let Some(version) = metadata::runtime_version(metadata) else {
+                anyhow::bail!("no runtime version in {}", offline.source);
+            };
+            let image = encode_call(&call, metadata)?;
+            let payload = if chain.has_indices() {
+                let client = OfflineClient::<SubstrateConfig>::new(
+                    genesis_hash,
+                    version.clone(),
+                    metadata.clone(),
+                );
+                signing_payload(&client, image.clone(), nonce)?
+            } else {
+                let client = OfflineClient::<PolkadotConfig>::new(
+                    genesis_hash,
+                    version.clone(),
+                    metadata.clone(),
+                );
+                signing_payload(&client, image.clone(), nonce)?
+            };
+            write_payload(
+                output,
+                &out,
+                genesis_hash,
+                &version,
+                nonce,
+                (&image, &payload),
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+// Call already encoded, e.g. from `--hex`
+struct RawCall(Vec<u8>);
+
+impl TxPayload for RawCall {
+    fn encode_call_data_to(&self, _: &Metadata, out: &mut Vec<u8>) -> Result<(), subxt::Error> {
+        out.extend(&self.0);
+        Ok(())
+    }
+}
+
+// Payload to sign for a call, hashed when longer than 256 bytes as the signers expect
+fn signing_payload<C: ChainConfig>(
+    client: &impl OfflineClientT<C>,
+    image: Vec<u8>,
+    nonce: u64,
+) -> Result<Vec<u8>> {
+    let partial =
+        client
+            .tx()
+            .create_partial_signed_with_nonce(&RawCall(image), nonce, Default::default())?;
+    Ok(partial.signer_payload())
+}
+
+// Write a signing payload along with what it commits to
+fn write_payload(
+    output: &Output,
+    path: &Path,
+    genesis_hash: H256,
+    version: &RuntimeVersion,
+    nonce: u64,
+    (image, payload): (&[u8], &[u8]),
+) -> Result<()> {
+    let content = json!({
+        "genesis_hash": genesis_hash,
+        "spec_version": version.spec_version,
+        "transaction_version": version.transaction_version,
+        "nonce": nonce,
+        "call": format!("0x{}", hex::encode(image)),
+        "payload": format!("0x{}", hex::encode(payload)),
+    });
+    std::fs::write(path, serde_json::to_string_pretty(&content)?)
+        .with_context(|| format!("failed to write {}", path.display()))?;
+    output.record(
+        "signing_payload",
+        json!({ "path": path, "nonce": nonce }),
+        format!(
+            "signing payload with nonce {nonce} written to {}",
+            path.display()
+        ),
+    );
+    Ok(())
+}
@@ -0,0 +1,278 @@
+//! Motions of the collective pallet, shared by the council and the technical committee: propose,
+//! approve as the dev members, close and report the outcome.
+use anyhow::Result;
+use codec::Encode;
+use subxt::blocks::ExtrinsicEvents;
+use subxt::events::{Events, StaticEvent};
+use subxt::utils::H256;
+
+use crate::chain::ChainConfig;
+use crate::error::CliError;
+use crate::kitchensink::runtime_types::sp_runtime::DispatchError;
+use crate::{call_hash, kitchensink, print, success, Program, User};
+
+/// Instance of the collective pallet voting a motion
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Collective {
+    Council,
+    TechnicalCommittee,
+}
+
+// First event of a block matching the filter
+fn find<C: ChainConfig, Ev: StaticEvent>(
+    events: &Events<C>,
+    filter: impl Fn(&Ev) -> bool,
+) -> Result<Option<Ev>> {
+    for event in events.find::<Ev>() {
+        let event = event?;
+        if filter(&event) {
+            return Ok(Some(event));
+        }
+    }
+    Ok(None)
+}
+
+impl Collective {
+    /// Name of the pallet instance
+    pub fn pallet(self) -> &'static str {
+        match self {
+            Self::Council => "Council",
+            Self::TechnicalCommittee => "TechnicalCommittee",
+        }
+    }
+
+    /// Human readable name of the instance
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Council => "council",
+            Self::TechnicalCommittee => "technical committee",
+        }
+    }
+
+    /// Number of members of the instance
+    pub async fn members<C: ChainConfig>(self, program: &Program<C>) -> Result<u32> {
+        let storage = program.storage().await?;
+        let members = match self {
+            Self::Council => {
+                storage
+                    .fetch_or_default(&kitchensink::storage().council().members())
+                    .await?
+            }
+            Self::TechnicalCommittee => {
+                storage
+                    .fetch_or_default(&kitchensink::storage().technical_committee().members())
+                    .await?
+            }
+        };
+        Ok(members.len() as u32)
+    }
+
+    /// Propose a motion of a call, returning its hash, its index unless a threshold below 2
+    /// executed it at once, and the events of the proposal
+    pub async fn propose<C: ChainConfig>(
+        self,
+        program: &Program<C>,
+        threshold: u32,
+        call: kitchensink::Call,
+    ) -> Result<(H256, Option<u32>, ExtrinsicEvents<C>)> {
+        let (hash, _) = call_hash(&call.encode());
+        let length_bound = call.encoded_size() as u32;
+        // the instances have calls of their own, boxed to the same payload type
+        let tx = match self {
+            Self::Council => kitchensink::tx()
+                .council()
+                .propose(threshold, call, length_bound)
+                .boxed(),
+            Self::TechnicalCommittee => kitchensink::tx()
+                .technical_committee()
+                .propose(threshold, call, length_bound)
+                .boxed(),
+        };
+        program.confirm_transaction(&tx, None).await?;
+        let events = program.submit_and_watch(&tx).await?;
+
+        // below a threshold of 2 the motion is executed without a vote
+        if threshold < 2 {
+            return Ok((hash, None, events));
+        }
+        let index = match self {
+            Self::Council => events
+                .find_first::<kitchensink::council::events::Proposed>()?
+                .map(|proposed| proposed.proposal_index),
+            Self::TechnicalCommittee => events
+                .find_first::<kitchensink::technical_committee::events::Proposed>()?
+                .map(|proposed| proposed.proposal_index),
+        }
+        .ok_or_else(|| CliError::MissingEvent(format!("{}::Proposed", self.pallet())))?;
+        success!(program, "motion {index} proposed {hash:?}");
+        Ok((hash, Some(index), events))
+    }
+
+    /// Vote on a motion
+    pub async fn vote<C: ChainConfig>(
+        self,
+        program: &Program<C>,
+        hash: H256,
+        index: u32,
+        approve: bool,
+    ) -> Result<ExtrinsicEvents<C>> {
+        let tx = match self {
+            Self::Council => kitchensink::tx()
+                .council()
+                .vote(hash, index, approve)
+                .boxed(),
+            Self::TechnicalCommittee => kitchensink::tx()
+                .technical_committee()
+                .vote(hash, index, approve)
+                .boxed(),
+        };
+        program.submit_and_watch(&tx).await
+    }
+
+    /// Close a decided motion, returning the events of the closing call
+    pub async fn close<C: ChainConfig>(
+        self,
+        program: &Program<C>,
+        hash: H256,
+        index: u32,
+    ) -> Result<ExtrinsicEvents<C>> {
+        let storage = program.storage().await?;
+        let proposal = match self {
+            Self::Council => {
+                storage
+                    .fetch(&kitchensink::storage().council().proposal_of(hash))
+                    .await?
+            }
+            Self::TechnicalCommittee => {
+                storage
+                    .fetch(
+                        &kitchensink::storage()
+                            .technical_committee()
+                            .proposal_of(hash),
+                    )
+                    .await?
+            }
+        }
+        .ok_or_else(|| anyhow::anyhow!("motion {hash:?} not found"))?;
+
+        // the weight of the proposal is witnessed by the closing call
+        let length_bound = proposal.encoded_size() as u32;
+        let query = kitchensink::apis()
+            .transaction_payment_call_api()
+            .query_call_info(proposal, length_bound);
+        let info = program
+            .api
+            .runtime_api()
+            .at(program.snapshot().await?.hash)
+            .call(query)
+            .await?;
+
+        print!(program, "closing motion {index} ({:?})", info.weight);
+        let tx = match self {
+            Self::Council => kitchensink::tx()
+                .council()
+                .close(hash, index, info.weight, length_bound)
+                .boxed(),
+            Self::TechnicalCommittee => kitchensink::tx()
+                .technical_committee()
+                .close(hash, index, info.weight, length_bound)
+                .boxed(),
+        };
+        program.submit_and_watch(&tx).await
+    }
+
+    /// Outcome of a motion from the events of a block, `Some(true)` once approved and executed,
+    /// `Some(false)` once disapproved and `None` while it is open. Fails when the call of the
+    /// motion failed
+    pub fn outcome<C: ChainConfig>(
+        self,
+        program: &Program<C>,
+        events: &Events<C>,
+        hash: H256,
+    ) -> Result<Option<bool>> {
+        let (disapproved, executed) = match self {
+            Self::Council => (
+                find(events, |ev: &kitchensink::council::events::Disapproved| {
+                    ev.proposal_hash == hash
+                })?
+                .is_some(),
+                find(events, |ev: &kitchensink::council::events::Executed| {
+                    ev.proposal_hash == hash
+                })?
+                .map(|ev| ev.result),
+            ),
+            Self::TechnicalCommittee => (
+                find(
+                    events,
+                    |ev: &kitchensink::technical_committee::events::Disapproved| {
+                        ev.proposal_hash == hash
+                    },
+                )?
+                .is_some(),
+                find(
+                    events,
+                    |ev: &kitchensink::technical_committee::events::Executed| {
+                        ev.proposal_hash == hash
+                    },
+                )?
+                .map(|ev| ev.result),
+            ),
+        };
+
+        if disapproved {
+            return Ok(Some(false));
+        }
+        match executed {
+            Some(Err(error)) => Err(dispatch_error(program, error)),
+            Some(Ok(())) => Ok(Some(true)),
+            None => Ok(None),
+        }
+    }
+
+    /// Propose a motion of a call, approve it as the given dev members besides the signing user
+    /// and close it, returning the events of the block the call was executed in
+    pub async fn approve<C: ChainConfig>(
+        self,
+        program: &Program<C>,
+        call: kitchensink::Call,
+        members: &[User],
+    ) -> Result<ExtrinsicEvents<C>> {
+        // the proposer's aye is counted with the motion
+        let mut approvers = vec![program.user];
+        for member in members {
+            if !approvers.contains(member) {
+                approvers.push(*member);
+            }
+        }
+        let threshold = approvers.len() as u32;
+
+        print!(
+            program,
+            "proposing a {} motion with {threshold} approvals",
+            self.name()
+        );
+        let (hash, index, mut events) = self.propose(program, threshold, call).await?;
+        if let Some(index) = index {
+            for member in &approvers[1..] {
+                let member = program.as_user(*member);
+                print!(member, "approving motion {index}");
+                self.vote(&member, hash, index, true).await?;
+            }
+            events = self.close(program, hash, index).await?;
+        }
+
+        match self.outcome(program, events.all_events_in_block(), hash)? {
+            Some(true) => Ok(events),
+            Some(false) => anyhow::bail!("the {} disapproved motion {hash:?}", self.name()),
+            None => Err(CliError::MissingEvent(format!("{}::Executed", self.pallet())).into()),
+        }
+    }
+}
+
+// Error of the failed call of an executed motion
+fn dispatch_error<C: ChainConfig>(program: &Program<C>, error: DispatchError) -> anyhow::Error {
+    match subxt::error::DispatchError::decode_from(error.encode(), program.api.metadata()) {
+        Ok(error) => CliError::dispatch(error).into(),
+        Err(err) => err.into(),
+    }
+}
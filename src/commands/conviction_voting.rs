@@ -0,0 +1,276 @@
+//! Votes, delegations and locks of the conviction voting pallet.
+//!
+//! `delegate-all` reads the delegations of each track from a TOML file, keyed by track name or
+//! id:
+//!
+//! ```toml
+//! [root]
+//! target = "bob"
+//! conviction = "2x"
+//! balance = "10 UNIT"
+//!
+//! [11]
+//! target = "5HGjWAeFDfFCWPsjFQdVV2Msvz2XtMktvgocEZcCj68kUMaw"
+//! balance = "1 UNIT"
+//! ```
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_json::json;
+use subxt::ext::futures::TryFutureExt;
+use subxt::utils::AccountId32;
+
+use crate::chain::ChainConfig;
+use crate::kitchensink::runtime_types::pallet_conviction_voting::conviction::Conviction as CvConviction;
+use crate::kitchensink::runtime_types::pallet_conviction_voting::pallet::Call as CvCall;
+use crate::kitchensink::runtime_types::pallet_conviction_voting::vote::Voting;
+use crate::kitchensink::runtime_types::pallet_referenda::types::ReferendumInfo;
+use crate::output::Table;
+use crate::units::{parse_account, Balance, Conviction};
+use crate::{kitchensink, print, storage_key_class, success, Program, User};
+
+/// Conviction voting subcommands
+#[derive(clap::Subcommand, Debug)]
+pub enum Command {
+    CvRemoveVote {
+        class: u16,
+        index: u32,
+    },
+    CvUnlock {
+        class: u16,
+        target: User,
+    },
+    CvMyVotes,
+    /// Delegate the votes of several tracks in a single `utility.batch_all`, then print the
+    /// resulting class locks
+    DelegateAll {
+        /// TOML file of the delegations, a table per track name or id
+        #[clap(long)]
+        file: PathBuf,
+    },
+}
+
+/// Delegation of a track in the delegations file, as written
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Delegation {
+    /// Address or dev user name of the delegate
+    pub target: String,
+    /// Conviction of the delegated votes [default: none]
+    pub conviction: Option<String>,
+    /// Balance locked by the delegation, in planck or in token units
+    pub balance: String,
+}
+
+// Delegation resolved against the connected chain
+struct Resolved {
+    class: u16,
+    target: AccountId32,
+    conviction: Conviction,
+    balance: u128,
+}
+
+/// Run a conviction voting subcommand
+pub async fn run<C: ChainConfig>(program: &Program<C>, command: Command) -> Result<()> {
+    match command {
+        Command::CvRemoveVote { class, index } => {
+            program.require_pallet("ConvictionVoting")?;
+            let conviction_voting = kitchensink::tx().conviction_voting();
+
+            print!(
+                program,
+                "removing vote on referendum {index} (class {class})"
+            );
+            let tx = conviction_voting.remove_vote(Some(class), index);
+            let events = program.submit_and_watch(&tx).await?;
+            success!(program, "vote removed {:?}", events.extrinsic_hash());
+        }
+        Command::CvUnlock { class, target } => {
+            program.require_pallet("ConvictionVoting")?;
+            let conviction_voting = kitchensink::tx().conviction_voting();
+            let account = target.account_id();
+
+            print!(program, "unlocking class {class} for {target}");
+            let tx = conviction_voting.unlock(class, account.into());
+            let events = program.submit_and_watch(&tx).await?;
+            success!(program, "class unlocked {:?}", events.extrinsic_hash());
+        }
+        Command::CvMyVotes => {
+            program.require_pallet("ConvictionVoting")?;
+            let account = program.account_id();
+            let api = program.storage().await?;
+
+            let locks_query = kitchensink::storage()
+                .conviction_voting()
+                .class_locks_for(&account);
+            let voting_query = kitchensink::storage()
+                .conviction_voting()
+                .voting_for_iter1(&account);
+            let (locks, voting) = tokio::try_join!(
+                api.fetch_or_default(&locks_query)
+                    .err_into::<anyhow::Error>(),
+                program.iter_storage(&voting_query),
+            )?;
+            let locks = locks.0;
+
+            for (key, voting) in voting {
+                let class = storage_key_class(&key);
+                let lock = locks
+                    .iter()
+                    .find_map(|(id, amount)| (*id == class).then_some(*amount))
+                    .unwrap_or_default();
+                program.output.record(
+                    "class",
+                    json!({ "class": class, "lock": lock }),
+                    format!("class {class} (lock: {}):", program.balance(lock)),
+                );
+
+                let votes = match voting {
+                    Voting::Casting(casting) => casting.votes.0,
+                    Voting::Delegating(delegating) => {
+                        print!(program, "  delegating {delegating:?}");
+                        continue;
+                    }
+                    Voting::__Ignore(_) => continue,
+                };
+
+                let queries = votes
+                    .iter()
+                    .map(|(index, _)| {
+                        kitchensink::storage()
+                            .referenda()
+                            .referendum_info_for(index)
+                    })
+                    .collect::<Vec<_>>();
+                let infos = program.fetch_many(&queries).await?;
+                for ((index, vote), info) in votes.into_iter().zip(infos) {
+                    let finished = !matches!(info, Some(ReferendumInfo::Ongoing(_)));
+                    let status = if finished {
+                        "finished, removable"
+                    } else {
+                        "ongoing"
+                    };
+                    program.output.record(
+                        "vote",
+                        json!({
+                            "class": class,
+                            "index": index,
+                            "vote": format!("{vote:?}"),
+                            "finished": finished,
+                        }),
+                        format!("  #{index} {vote:?} ({status})"),
+                    );
+                }
+            }
+        }
+        Command::DelegateAll { file } => {
+            program.require_pallet("ConvictionVoting")?;
+            program.require_pallet("Utility")?;
+            let content = std::fs::read_to_string(&file)
+                .with_context(|| format!("failed to read {}", file.display()))?;
+            let delegations: BTreeMap<String, Delegation> = toml::from_str(&content)
+                .with_context(|| format!("invalid delegations {}", file.display()))?;
+            if delegations.is_empty() {
+                anyhow::bail!("no delegation in {}", file.display());
+            }
+            let delegations = delegations
+                .iter()
+                .map(|(track, delegation)| {
+                    resolve(program, track, delegation)
+                        .with_context(|| format!("invalid delegation of track {track}"))
+                })
+                .collect::<Result<Vec<_>>>()?;
+
+            let calls = delegations
+                .iter()
+                .map(|delegation| {
+                    kitchensink::Call::ConvictionVoting(CvCall::delegate {
+                        class: delegation.class,
+                        to: delegation.target.clone().into(),
+                        conviction: cv_conviction(delegation.conviction),
+                        balance: delegation.balance,
+                    })
+                })
+                .collect();
+            let tx = kitchensink::tx().utility().batch_all(calls);
+            // the locks of the classes overlap, the largest one is held
+            let lock = delegations
+                .iter()
+                .map(|delegation| delegation.balance)
+                .max()
+                .unwrap_or_default();
+            print!(program, "delegating {} tracks", delegations.len());
+            program
+                .confirm_transaction(&tx, Some(("lock", lock)))
+                .await?;
+            let events = program.submit_and_watch(&tx).await?;
+            success!(
+                program,
+                "delegations finalized {:?}",
+                events.extrinsic_hash()
+            );
+
+            let query = kitchensink::storage()
+                .conviction_voting()
+                .class_locks_for(program.account_id());
+            let locks = program.storage().await?.fetch_or_default(&query).await?.0;
+            let mut table = Table::new(&["class", "target", "conviction", "balance", "lock"]);
+            for delegation in &delegations {
+                let lock = locks
+                    .iter()
+                    .find_map(|(id, amount)| (*id == delegation.class).then_some(*amount))
+                    .unwrap_or_default();
+                program.output.record_json(
+                    "delegation",
+                    json!({
+                        "class": delegation.class,
+                        "target": program.address(&delegation.target),
+                        "conviction": delegation.conviction.to_string(),
+                        "balance": delegation.balance,
+                        "lock": lock,
+                    }),
+                );
+                table.add_row(vec![
+                    delegation.class.to_string(),
+                    program.address(&delegation.target),
+                    delegation.conviction.to_string(),
+                    program.balance(delegation.balance),
+                    program.balance(lock),
+                ]);
+            }
+            program.output.table(&table)?;
+        }
+    }
+
+    Ok(())
+}
+
+// Resolve the track, the target and the amounts of a delegation
+fn resolve<C: ChainConfig>(
+    program: &Program<C>,
+    track: &str,
+    delegation: &Delegation,
+) -> Result<Resolved> {
+    let balance = Balance::parse(&delegation.balance)?;
+    Ok(Resolved {
+        class: program.track_id(track)?,
+        target: parse_account(&delegation.target)?,
+        conviction: Conviction::parse_or_default(delegation.conviction.as_deref())?,
+        balance: program.planck(&balance)?,
+    })
+}
+
+// Conviction of the conviction voting pallet calls
+fn cv_conviction(conviction: Conviction) -> CvConviction {
+    match conviction {
+        Conviction::None => CvConviction::None,
+        Conviction::Locked1x => CvConviction::Locked1x,
+        Conviction::Locked2x => CvConviction::Locked2x,
+        Conviction::Locked3x => CvConviction::Locked3x,
+        Conviction::Locked4x => CvConviction::Locked4x,
+        Conviction::Locked5x => CvConviction::Locked5x,
+        Conviction::Locked6x => CvConviction::Locked6x,
+    }
+}
@@ -0,0 +1,159 @@
+//! Council motions of the collective pallet, the gov1 path of the external proposals.
+use anyhow::Result;
+use subxt::ext::futures::StreamExt;
+use subxt::utils::H256;
+
+use crate::chain::ChainConfig;
+use crate::commands::collective::Collective;
+use crate::error::CliError;
+use crate::kitchensink::runtime_types::frame_support::traits::preimages::Bounded;
+use crate::kitchensink::runtime_types::pallet_democracy::pallet::Call as DemocracyCall;
+use crate::units::parse_h256;
+use crate::{kitchensink, print, shutdown, success, warning, Program};
+
+/// Council subcommands
+#[derive(clap::Subcommand, Debug)]
+pub enum Command {
+    /// Move a council motion proposing a noted preimage as the next external proposal, then
+    /// follow its votes and close it once decided
+    ProposeExternal {
+        #[clap(value_parser = parse_h256)]
+        hash: H256,
+        len: u32,
+        /// Number of council members whose aye approves the motion, executed at once when 1
+        threshold: u32,
+        /// Vote threshold of the referendum
+        #[clap(long, value_enum, default_value = "majority")]
+        kind: External,
+    },
+}
+
+/// Vote threshold of the referendum started from an external proposal
+#[derive(clap::ValueEnum, Debug, Clone, Copy)]
+pub enum External {
+    /// Super majority approve, `external_propose`
+    SuperMajority,
+    /// Simple majority, `external_propose_majority`
+    Majority,
+    /// Negative turnout bias, `external_propose_default`
+    Default,
+}
+
+/// Run a council subcommand
+pub async fn run<C: ChainConfig>(program: &Program<C>, command: Command) -> Result<()> {
+    match command {
+        Command::ProposeExternal {
+            hash,
+            len,
+            threshold,
+            kind,
+        } => {
+            program.require_pallet("Council")?;
+            program.require_pallet("Democracy")?;
+            program.check_preimage(hash, len).await?;
+            let proposal = Bounded::Lookup { hash, len };
+            let call = kitchensink::Call::Democracy(match kind {
+                External::SuperMajority => DemocracyCall::external_propose { proposal },
+                External::Majority => DemocracyCall::external_propose_majority { proposal },
+                External::Default => DemocracyCall::external_propose_default { proposal },
+            });
+
+            print!(
+                program,
+                "proposing a council motion for ({hash:?}, {len}) with threshold {threshold}"
+            );
+            let (motion_hash, index, events) = Collective::Council
+                .propose(program, threshold, call)
+                .await?;
+            match index {
+                Some(index) => follow_motion(program, motion_hash, index, threshold).await?,
+                None => {
+                    Collective::Council.outcome(
+                        program,
+                        events.all_events_in_block(),
+                        motion_hash,
+                    )?;
+                    success!(program, "motion executed {motion_hash:?}");
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Print the votes of a motion until it is decided, close it, and report the outcome
+async fn follow_motion<C: ChainConfig>(
+    program: &Program<C>,
+    proposal_hash: H256,
+    index: u32,
+    threshold: u32,
+) -> Result<()> {
+    let members = Collective::Council.members(program).await?;
+    print!(
+        program,
+        "waiting for the votes of the {members} council members, ctrl-c to stop following"
+    );
+
+    let mut blocks = program.finalized_blocks().boxed();
+    loop {
+        let block = tokio::select! {
+            biased;
+            () = shutdown::requested() => return Ok(()),
+            block = blocks.next() => match block {
+                Some(block) => block?,
+                None => Err(CliError::Connection(anyhow::anyhow!(
+                    "the block subscription ended before the motion was closed"
+                )))?,
+            },
+        };
+
+        let mut decided = false;
+        for voted in block.events.find::<kitchensink::council::events::Voted>() {
+            let voted = voted?;
+            if voted.proposal_hash != proposal_hash {
+                continue;
+            }
+            print!(
+                program,
+                "#{} {} voted {} ({} ayes, {} nays)",
+                block.number,
+                program.address(&voted.account),
+                if voted.voted { "aye" } else { "nay" },
+                voted.yes,
+                voted.no
+            );
+            // the motion can be closed once approved, or once it can't be anymore
+            decided = voted.yes >= threshold || voted.no > members.saturating_sub(threshold);
+        }
+        // closed by another member
+        if closed(program, &block.events, proposal_hash)? {
+            return Ok(());
+        }
+        if decided {
+            break;
+        }
+    }
+
+    let events = Collective::Council
+        .close(program, proposal_hash, index)
+        .await?;
+    if !closed(program, events.all_events_in_block(), proposal_hash)? {
+        warning!(program, "motion {index} closed without an outcome event");
+    }
+    Ok(())
+}
+
+// Report the outcome of a motion if it was closed in a block
+fn closed<C: ChainConfig>(
+    program: &Program<C>,
+    events: &subxt::events::Events<C>,
+    proposal_hash: H256,
+) -> Result<bool> {
+    match Collective::Council.outcome(program, events, proposal_hash)? {
+        Some(true) => success!(program, "motion approved, the external proposal is queued"),
+        Some(false) => warning!(program, "motion disapproved"),
+        None => return Ok(false),
+    }
+    Ok(true)
+}
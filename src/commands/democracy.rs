@@ -0,0 +1,1462 @@
+//! Proposals, votes and referenda of the democracy pallet. Voting and listing the referenda are
+//! routed to OpenGov when it is the governance of the chain.
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use codec::Encode;
+use serde_json::json;
+use subxt::backend::rpc::rpc_params;
+use subxt::blocks::ExtrinsicEvents;
+use subxt::ext::futures::{StreamExt, TryStreamExt};
+use subxt::tx::TxPayload;
+use subxt::utils::{AccountId32, H256};
+
+use crate::call::CallDescription;
+use crate::chain::ChainConfig;
+use crate::commands::collective::Collective;
+use crate::commands::{referenda, technical_committee};
+use crate::error::CliError;
+use crate::kitchensink::runtime_types::frame_support::traits::preimages::Bounded;
+use crate::kitchensink::runtime_types::frame_support::traits::schedule::DispatchTime;
+use crate::kitchensink::runtime_types::pallet_conviction_voting::pallet::Call as CvCall;
+use crate::kitchensink::runtime_types::pallet_conviction_voting::vote::AccountVote as CvAccountVote;
+use crate::kitchensink::runtime_types::pallet_conviction_voting::vote::Voting;
+use crate::kitchensink::runtime_types::pallet_democracy::pallet::Call as DemocracyCall;
+use crate::kitchensink::runtime_types::pallet_democracy::types::ReferendumInfo as DemocracyReferendumInfo;
+use crate::kitchensink::runtime_types::pallet_democracy::vote::AccountVote as DemocracyAccountVote;
+use crate::kitchensink::runtime_types::pallet_democracy::vote::Voting as DemocracyVoting;
+use crate::kitchensink::runtime_types::pallet_referenda::types::ReferendumInfo as CvReferendumInfo;
+use crate::output::Table;
+use crate::units::{parse_h256, Balance, BlockNumber, Conviction};
+use crate::{
+    call_hash, check_mark, create_cv_vote, create_vote, democracy_approved, dynamic, format_ppb,
+    kitchensink, print, read_call_data, shutdown, storage_key_account, storage_key_class,
+    storage_key_index, success, vote_call, warning, wasm, CallData, Governance, Program, User,
+    DEFAULT_ENACT_AFTER, PROPOSAL_DEPOSIT, STORAGE_PAGE_SIZE,
+};
+
+/// Democracy subcommands
+#[derive(clap::Subcommand, Debug)]
+pub enum Command {
+    MakeProposal {
+        #[clap(value_parser = parse_h256)]
+        hash: H256,
+        len: u32,
+    },
+    /// Propose a `system.set_code` upgrade, after checking the runtime version of the blob against
+    /// the chain and the size of the preimage against the block length
+    ProposeRuntimeUpgrade {
+        #[clap(long)]
+        wasm: PathBuf,
+    },
+    ProposeBatchFile {
+        /// YAML list of call descriptions
+        #[clap(long)]
+        file: PathBuf,
+    },
+    Vote {
+        index: u32,
+        /// Balance to lock, in planck or in token units, e.g. `1.5` or `2 DOT`
+        balance: Balance,
+        /// Conviction multiplying the votes for a longer lock of the balance
+        #[clap(default_value = "none")]
+        conviction: Conviction,
+        /// Overwrite the existing vote of the signer on the referendum
+        #[clap(long)]
+        replace: bool,
+        /// Call data announced for the referendum, as hex or raw bytes, only vote when the
+        /// referendum proposes exactly this call
+        #[clap(long, value_name = "FILE")]
+        verify_against: Option<PathBuf>,
+    },
+    /// Cast the same vote on several referenda in a single `utility.force_batch`, a failing vote
+    /// doesn't revert the others
+    VoteAll {
+        /// Indices of the referenda
+        #[clap(required = true, num_args = 1..)]
+        indices: Vec<u32>,
+        /// Balance to lock, in planck or in token units, e.g. `1.5` or `2 DOT`
+        #[clap(long)]
+        balance: Balance,
+        /// Conviction multiplying the votes for a longer lock of the balance
+        #[clap(long, default_value = "none")]
+        conviction: Conviction,
+        /// Overwrite the existing votes of the signer on the referenda
+        #[clap(long)]
+        replace: bool,
+    },
+    /// Wait for a block to be finalized, then vote, e.g. to test a vote at the end of the
+    /// voting period
+    VoteAt {
+        index: u32,
+        /// Block to wait for, e.g. `1234` or `1_234`
+        #[clap(long)]
+        block: BlockNumber,
+        /// Balance to lock, in planck or in token units, e.g. `1.5` or `2 DOT`
+        balance: Balance,
+        /// Conviction multiplying the votes for a longer lock of the balance
+        #[clap(default_value = "none")]
+        conviction: Conviction,
+        /// Overwrite the existing vote of the signer on the referendum
+        #[clap(long)]
+        replace: bool,
+    },
+    /// Tell whether a referendum would pass with additional votes on top of its current tally,
+    /// e.g. to find how many ayes it still needs
+    Simulate {
+        index: u32,
+        /// Additional aye balance, in planck or in token units
+        #[clap(long, default_value = "0")]
+        extra_aye: Balance,
+        /// Additional nay balance, in planck or in token units
+        #[clap(long, default_value = "0")]
+        extra_nay: Balance,
+        /// Conviction of the additional votes
+        #[clap(long, default_value = "none")]
+        conviction: Conviction,
+    },
+    TrackProposalStatus,
+    List,
+    /// List the accounts delegating their votes to the current account, with the voting power
+    /// they give it
+    Delegators {
+        /// Stop after this many delegators
+        #[clap(long)]
+        limit: Option<usize>,
+        /// Keys of the voting map listed per request
+        #[clap(long, default_value_t = STORAGE_PAGE_SIZE)]
+        page_size: u32,
+    },
+    /// Remove the votes on the finished referenda and unlock the balance whose locks expired, in
+    /// a single `utility.batch_all`
+    UnlockAll,
+    /// Note a preimage, propose it and vote on the referendum from dev users, the whole demo
+    /// flow in one command
+    ProposeAndVote {
+        #[clap(flatten)]
+        call: CallData,
+        /// Start the referendum at once with the fast track voting period, instead of waiting
+        /// for the launch period: a council motion makes the preimage the external proposal and
+        /// a technical committee motion fast-tracks it, both approved by the `--approve` members
+        #[clap(long, requires = "approvers")]
+        fast_track: bool,
+        /// Dev member approving the motions of `--fast-track` besides the signing user, repeat
+        /// for more members
+        #[clap(long = "approve", value_name = "USER")]
+        approvers: Vec<User>,
+        /// Dev user voting aye, with its conviction, e.g. `bob:3x`, repeat for more voters
+        #[clap(long = "vote", value_name = "USER[:CONVICTION]", required = true)]
+        votes: Vec<Voter>,
+        /// Balance locked by each vote, in planck or in token units
+        #[clap(long)]
+        balance: Balance,
+    },
+    /// Watch the new proposals and second the ones whose preimage hash is in the allowlist,
+    /// until interrupted
+    AutoSecond {
+        /// Allowlist of preimage hashes, one hex hash per line, `#` starting a comment
+        #[clap(long)]
+        hashes: PathBuf,
+    },
+}
+
+/// Dev user voting aye with a conviction, `none` when not given
+#[derive(Debug, Clone, Copy)]
+pub struct Voter {
+    pub user: User,
+    pub conviction: Conviction,
+}
+
+impl FromStr for Voter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (user, conviction) = s.split_once(':').unwrap_or((s, "none"));
+        Ok(Voter {
+            user: User::from_str(user, true)?,
+            conviction: Conviction::parse(conviction)?,
+        })
+    }
+}
+
+/// Run a democracy subcommand
+pub async fn run<C: ChainConfig>(program: &Program<C>, command: Command) -> Result<()> {
+    match command {
+        Command::MakeProposal { hash, len } => {
+            program.make_proposal(hash, len).await?;
+        }
+        Command::ProposeRuntimeUpgrade { wasm } => {
+            program.require_pallet("Preimage")?;
+            let code = std::fs::read(&wasm)?;
+            check_runtime_upgrade(program, &code).await?;
+            let image = kitchensink::Call::System(
+                kitchensink::runtime_types::frame_system::pallet::Call::set_code { code },
+            )
+            .encode();
+            let (hash, len) = program.note_preimage(image).await?;
+            program.make_proposal(hash, len).await?;
+        }
+        Command::ProposeBatchFile { file } => {
+            program.require_pallet("Preimage")?;
+            program.require_pallet("Utility")?;
+            let mut images = Vec::new();
+            for description in CallDescription::list_from_yaml(&file)? {
+                let payload = description.payload()?;
+                images.push(program.api.tx().call_data(&payload)?);
+            }
+
+            let mut deposit = 0;
+            let mut proposals = Vec::new();
+            for image in &images {
+                let (hash, len) = call_hash(image);
+                deposit += program.preimage_deposit(len)?;
+                proposals.push(program.proposal_call(hash, len)?);
+            }
+            let hashes = images
+                .iter()
+                .map(|image| call_hash(image))
+                .collect::<Vec<_>>();
+            let count = images.len();
+            let notes = images
+                .into_iter()
+                .map(|bytes| {
+                    kitchensink::Call::Preimage(
+                        kitchensink::runtime_types::pallet_preimage::pallet::Call::note_preimage {
+                            bytes,
+                        },
+                    )
+                })
+                .collect();
+            let tx = kitchensink::tx().utility().batch_all(notes);
+            program
+                .check_balance(&tx, Some(("deposit", deposit)))
+                .await?;
+            warning!(
+                program,
+                "noting {count} preimages will reserve a deposit of {}",
+                program.balance(deposit)
+            );
+            if !program.confirm("continue?")? {
+                Err(anyhow::anyhow!("aborted"))?;
+            }
+
+            print!(program, "noting preimages");
+            program.submit_and_watch(&tx).await?;
+
+            print!(program, "submitting proposals");
+            let deposit = program.proposal_deposit()? * hashes.len() as u128;
+            let tx = kitchensink::tx().utility().batch_all(proposals);
+            program
+                .confirm_transaction(&tx, Some(("deposit", deposit)))
+                .await?;
+            let events = program.submit_and_watch(&tx).await?;
+            let mut indices = Vec::new();
+            for proposed in events.find::<kitchensink::democracy::events::Proposed>() {
+                indices.push(proposed?.proposal_index);
+            }
+            for submitted in events.find::<kitchensink::referenda::events::Submitted>() {
+                indices.push(submitted?.index);
+            }
+
+            let mut table = Table::new(&["hash", "len", "index"]);
+            for ((hash, len), index) in hashes.iter().zip(indices) {
+                program.output.record_json(
+                    "proposal",
+                    json!({ "hash": hash, "len": len, "index": index }),
+                );
+                table.add_row(vec![
+                    format!("{hash:?}"),
+                    len.to_string(),
+                    index.to_string(),
+                ]);
+            }
+            program.output.table(&table)?;
+        }
+        Command::Vote {
+            index,
+            balance,
+            conviction,
+            replace,
+            verify_against,
+        } if program.governance()? == Governance::OpenGov => {
+            let balance = program.planck(&balance)?;
+            program.check_ongoing(index).await?;
+            program.check_existing_vote(index, replace).await?;
+            if let Some(path) = verify_against {
+                verify_call(program, index, &path).await?;
+            }
+            print!(
+                program,
+                "submitting conviction vote with {conviction} conviction"
+            );
+            let events = program.vote(index, true, conviction, balance).await?;
+            success!(program, "vote finalized {:?}", events.extrinsic_hash());
+        }
+        Command::Vote {
+            index,
+            balance,
+            conviction,
+            replace,
+            verify_against,
+        } => {
+            let balance = program.planck(&balance)?;
+            program.check_ongoing(index).await?;
+            program.check_existing_vote(index, replace).await?;
+            if let Some(path) = verify_against {
+                verify_call(program, index, &path).await?;
+            }
+            print!(program, "submitting vote with {conviction} conviction");
+            let events = program.vote(index, true, conviction, balance).await?;
+            let vote_event = events.find_first::<kitchensink::democracy::events::Voted>()?;
+            success!(program, "vote finalized {:?}", vote_event);
+        }
+        Command::VoteAll {
+            indices,
+            balance,
+            conviction,
+            replace,
+        } => {
+            program.require_pallet("Utility")?;
+            let balance = program.planck(&balance)?;
+            for index in &indices {
+                program.check_ongoing(*index).await?;
+                program.check_existing_vote(*index, replace).await?;
+            }
+            let governance = program.governance()?;
+            let votes = indices
+                .iter()
+                .map(|index| vote_call(governance, *index, true, conviction, balance))
+                .collect();
+            let tx = kitchensink::tx().utility().force_batch(votes);
+            print!(
+                program,
+                "submitting {} votes with {conviction} conviction",
+                indices.len()
+            );
+            program
+                .confirm_transaction(&tx, Some(("lock", balance)))
+                .await?;
+            let events = program.submit_and_watch(&tx).await?;
+
+            // each vote is followed by an `ItemCompleted` or an `ItemFailed`, in order
+            let mut statuses = Vec::new();
+            for event in events.iter() {
+                let event = event?;
+                if event
+                    .as_event::<kitchensink::utility::events::ItemCompleted>()?
+                    .is_some()
+                {
+                    statuses.push("recorded".to_string());
+                } else if let Some(failed) =
+                    event.as_event::<kitchensink::utility::events::ItemFailed>()?
+                {
+                    let error = subxt::error::DispatchError::decode_from(
+                        failed.error.encode(),
+                        program.api.metadata(),
+                    )?;
+                    statuses.push(format!("failed: {}", CliError::dispatch(error)));
+                }
+            }
+
+            let mut table = Table::new(&["index", "vote"]);
+            for (position, index) in indices.iter().enumerate() {
+                let status = statuses
+                    .get(position)
+                    .map_or("not dispatched", String::as_str);
+                program
+                    .output
+                    .record_json("vote", json!({ "index": index, "status": status }));
+                table.add_row(vec![index.to_string(), status.to_string()]);
+            }
+            program.output.table(&table)?;
+
+            let recorded = statuses
+                .iter()
+                .filter(|status| *status == "recorded")
+                .count();
+            if recorded < indices.len() {
+                anyhow::bail!(
+                    "{} of the {} votes failed, in extrinsic {:?}",
+                    indices.len() - recorded,
+                    indices.len(),
+                    events.extrinsic_hash()
+                );
+            }
+            success!(program, "votes finalized {:?}", events.extrinsic_hash());
+        }
+        Command::VoteAt {
+            index,
+            block,
+            balance,
+            conviction,
+            replace,
+        } => {
+            let balance = program.planck(&balance)?;
+            let block = u32::from(block);
+            program.check_ongoing(index).await?;
+            program.check_existing_vote(index, replace).await?;
+            let events = match program.governance()? {
+                Governance::OpenGov => {
+                    let tx = create_cv_vote(index, true, conviction, balance);
+                    vote_at(program, &tx, balance, block).await?
+                }
+                Governance::Democracy if program.dynamic => {
+                    let tx = dynamic::vote(index, true, conviction, balance);
+                    vote_at(program, &tx, balance, block).await?
+                }
+                Governance::Democracy => {
+                    let tx = create_vote(index, true, conviction, balance);
+                    vote_at(program, &tx, balance, block).await?
+                }
+            };
+            success!(
+                program,
+                "vote with {conviction} conviction finalized {:?}",
+                events.extrinsic_hash()
+            );
+        }
+        Command::Simulate {
+            index,
+            extra_aye,
+            extra_nay,
+            conviction,
+        } => {
+            let extra_aye = program.planck(&extra_aye)?;
+            let extra_nay = program.planck(&extra_nay)?;
+            print_simulation(program, index, extra_aye, extra_nay, conviction).await?;
+        }
+        Command::TrackProposalStatus => {
+            program.require_pallet("Democracy")?;
+            let passed = program
+                .wait_for_event::<kitchensink::democracy::events::Passed>()
+                .await;
+            success!(program, "proposal passed {:?}", passed);
+        }
+        Command::List => match program.governance()? {
+            Governance::Democracy if program.dynamic => {
+                for (key, info) in program
+                    .iter_cached_storage(&dynamic::referendum_info_of())
+                    .await?
+                {
+                    let index = storage_key_index(&key);
+                    let info = info.to_value()?;
+                    program.output.record(
+                        "referendum",
+                        json!({ "index": index, "info": info }),
+                        format!("referendum {index}: {info}"),
+                    );
+                }
+            }
+            Governance::Democracy => {
+                let now = program.snapshot().await?.number;
+                let query = kitchensink::storage().democracy().referendum_info_of_iter();
+                let mut table = Table::new(&[
+                    "index",
+                    "status",
+                    "end",
+                    "threshold",
+                    "ayes",
+                    "nays",
+                    "turnout",
+                ]);
+                for (key, info) in program.iter_cached_storage(&query).await? {
+                    let index = storage_key_index(&key);
+                    let row = match info {
+                        DemocracyReferendumInfo::Ongoing(status) => {
+                            program.output.record_json(
+                                "referendum",
+                                json!({
+                                    "index": index,
+                                    "status": "ongoing",
+                                    "end": status.end,
+                                    "threshold": format!("{:?}", status.threshold),
+                                    "ayes": status.tally.ayes,
+                                    "nays": status.tally.nays,
+                                    "turnout": status.tally.turnout,
+                                }),
+                            );
+                            vec![
+                                index.to_string(),
+                                "ongoing".to_string(),
+                                program.describe_block(status.end, now),
+                                format!("{:?}", status.threshold),
+                                program.balance(status.tally.ayes),
+                                program.balance(status.tally.nays),
+                                program.balance(status.tally.turnout),
+                            ]
+                        }
+                        DemocracyReferendumInfo::Finished { approved, end } => {
+                            let status = if approved { "approved" } else { "rejected" };
+                            program.output.record_json(
+                                "referendum",
+                                json!({ "index": index, "status": status, "end": end }),
+                            );
+                            let empty = || "-".to_string();
+                            vec![
+                                index.to_string(),
+                                status.to_string(),
+                                program.describe_block(end, now),
+                                empty(),
+                                empty(),
+                                empty(),
+                                empty(),
+                            ]
+                        }
+                    };
+                    table.add_row(row);
+                }
+                program.output.table(&table)?;
+            }
+            Governance::OpenGov => referenda::list(program).await?,
+        },
+        Command::Delegators { limit, page_size } => {
+            let account = program.account_id();
+            let limit = limit.unwrap_or(usize::MAX);
+            let mut table = Table::new(&["delegator", "class", "balance", "conviction", "votes"]);
+            let mut total = 0u128;
+            let mut count = 0;
+            // the voting map is scanned page by page, the JSON records are printed as the
+            // delegations are found while the text and CSV rows are aligned once it is over
+            let mut record = |delegator, class, balance, conviction| {
+                let (votes, row) = delegator_row(program, &delegator, class, balance, conviction);
+                total = total.saturating_add(votes);
+                table.add_row(row);
+            };
+            match program.governance()? {
+                Governance::OpenGov => {
+                    let query = kitchensink::storage().conviction_voting().voting_for_iter();
+                    let entries = program.stream_storage(&query, page_size);
+                    tokio::pin!(entries);
+                    while count < limit {
+                        let Some((key, voting)) = entries.try_next().await? else {
+                            break;
+                        };
+                        if let Voting::Delegating(delegating) = voting {
+                            if delegating.target == account {
+                                // the account is followed by the hashed `u16` class
+                                let delegator = storage_key_account(&key[..key.len() - 10]);
+                                let class = Some(storage_key_class(&key));
+                                let conviction = delegating.conviction.encode()[0];
+                                record(delegator, class, delegating.balance, conviction);
+                                count += 1;
+                            }
+                        }
+                    }
+                }
+                Governance::Democracy => {
+                    let query = kitchensink::storage().democracy().voting_of_iter();
+                    let entries = program.stream_storage(&query, page_size);
+                    tokio::pin!(entries);
+                    while count < limit {
+                        let Some((key, voting)) = entries.try_next().await? else {
+                            break;
+                        };
+                        if let DemocracyVoting::Delegating {
+                            balance,
+                            target,
+                            conviction,
+                            ..
+                        } = voting
+                        {
+                            if target == account {
+                                let delegator = storage_key_account(&key);
+                                record(delegator, None, balance, conviction.encode()[0]);
+                                count += 1;
+                            }
+                        }
+                    }
+                }
+            }
+
+            program.output.table(&table)?;
+            program.output.record(
+                "delegated",
+                json!({ "delegators": count, "votes": total }),
+                format!("{count} delegations worth {} votes", program.balance(total)),
+            );
+        }
+        Command::UnlockAll => {
+            program.require_pallet("Utility")?;
+            let now = program.snapshot().await?.number;
+            let unlocks = match program.governance()? {
+                Governance::Democracy => democracy_unlocks(program, now).await?,
+                Governance::OpenGov => cv_unlocks(program, now).await?,
+            };
+            if unlocks.is_empty() {
+                print!(program, "no vote to remove nor lock to release");
+                return Ok(());
+            }
+
+            let mut table = Table::new(&["class", "call"]);
+            for Unlock {
+                class, description, ..
+            } in &unlocks
+            {
+                program
+                    .output
+                    .record_json("unlock", json!({ "class": class, "call": description }));
+                table.add_row(vec![
+                    class.map_or("-".to_string(), |class| class.to_string()),
+                    description.clone(),
+                ]);
+            }
+            program.output.table(&table)?;
+
+            let calls = unlocks.into_iter().map(|unlock| unlock.call).collect();
+            let tx = kitchensink::tx().utility().batch_all(calls);
+            program.confirm_transaction(&tx, None).await?;
+            let events = program.submit_and_watch(&tx).await?;
+            success!(program, "unlocks finalized {:?}", events.extrinsic_hash());
+        }
+        Command::ProposeAndVote {
+            call,
+            fast_track: fast,
+            approvers,
+            votes,
+            balance,
+        } => {
+            program.require_pallet("Preimage")?;
+            let balance = program.planck(&balance)?;
+            let image = program.encode_call(&call)?;
+            let (hash, len) = program.note_preimage(image).await?;
+
+            let index = match program.governance()? {
+                Governance::OpenGov if fast => Err(CliError::Validation {
+                    what: "--fast-track".into(),
+                    source: anyhow::anyhow!("only democracy proposals can be fast-tracked"),
+                })?,
+                Governance::OpenGov => start_referendum(program, hash, len).await?,
+                Governance::Democracy if fast => fast_track(program, hash, len, &approvers).await?,
+                Governance::Democracy => launch(program, hash, len).await?,
+            };
+            success!(program, "referendum {index} started");
+
+            for Voter { user, conviction } in &votes {
+                let voter = program.as_user(*user);
+                print!(
+                    voter,
+                    "voting aye on referendum {index} with {conviction} conviction"
+                );
+                let events = voter.vote(index, true, *conviction, balance).await?;
+                success!(voter, "vote finalized {:?}", events.extrinsic_hash());
+            }
+
+            program.output.record(
+                "referendum",
+                json!({ "index": index, "hash": hash, "len": len, "votes": votes.len() }),
+                format!("referendum {index} started, {} votes cast", votes.len()),
+            );
+        }
+        Command::AutoSecond { hashes } => {
+            program.require_pallet("Democracy")?;
+            let allowlist = read_allowlist(&hashes)?;
+            auto_second(program, &allowlist).await?;
+        }
+    }
+
+    Ok(())
+}
+
+// Confirm a vote, then submit it once the block is finalized
+async fn vote_at<C: ChainConfig>(
+    program: &Program<C>,
+    tx: &impl TxPayload,
+    balance: u128,
+    block: u32,
+) -> Result<ExtrinsicEvents<C>> {
+    program
+        .confirm_transaction(tx, Some(("lock", balance)))
+        .await?;
+    program.wait_for_block(block).await?;
+    print!(program, "block {block} finalized, submitting the vote");
+    program.submit_and_watch(tx).await
+}
+
+// Read the preimage hashes of an allowlist file
+fn read_allowlist(path: &Path) -> Result<HashSet<H256>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    let mut hashes = HashSet::new();
+    for (number, line) in (1..).zip(content.lines()) {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+        let hash = parse_h256(line).map_err(|err| CliError::Validation {
+            what: format!("{}:{number}", path.display()),
+            source: anyhow::anyhow!(err),
+        })?;
+        hashes.insert(hash);
+    }
+    Ok(hashes)
+}
+
+// Second the proposals of the allowlist as they are proposed, until ctrl-c or the end of the
+// subscription. A failed second is reported and the watch goes on
+async fn auto_second<C: ChainConfig>(
+    program: &Program<C>,
+    allowlist: &HashSet<H256>,
+) -> Result<()> {
+    warning!(
+        program,
+        "seconding the proposals of {} preimages as {}, each second reserves the deposit of \
+         its proposal",
+        allowlist.len(),
+        program.user
+    );
+    if !program.confirm("continue?")? {
+        Err(anyhow::anyhow!("aborted"))?;
+    }
+
+    let mut blocks = program.finalized_blocks().boxed();
+    loop {
+        let block = tokio::select! {
+            biased;
+            () = shutdown::requested() => break,
+            block = blocks.next() => match block {
+                Some(block) => block?,
+                None => break,
+            },
+        };
+
+        for proposed in block
+            .events
+            .find::<kitchensink::democracy::events::Proposed>()
+        {
+            let index = proposed?.proposal_index;
+            let query = kitchensink::storage().democracy().public_props();
+            let proposals = program
+                .api
+                .storage()
+                .at(block.hash)
+                .fetch_or_default(&query)
+                .await?
+                .0;
+            let Some(hash) = proposals
+                .into_iter()
+                .find(|(proposal_index, ..)| *proposal_index == index)
+                .map(|(_, proposal, _)| bounded_hash(&proposal))
+            else {
+                continue;
+            };
+            if !allowlist.contains(&hash) {
+                tracing::debug!(index, ?hash, "proposal not in the allowlist");
+                continue;
+            }
+
+            print!(
+                program,
+                "#{} seconding proposal {index} of {hash:?}", block.number
+            );
+            let tx = kitchensink::tx().democracy().second(index);
+            match program.submit_and_watch(&tx).await {
+                Ok(events) => {
+                    program.output.record(
+                        "second",
+                        json!({ "block": block.number, "index": index, "hash": hash }),
+                        format!("proposal {index} seconded {:?}", events.extrinsic_hash()),
+                    );
+                    program
+                        .notify(&format!("seconded proposal {index} of {hash:?}"))
+                        .await;
+                }
+                Err(err) => {
+                    warning!(program, "failed to second proposal {index}: {err:#}");
+                    program
+                        .notify(&format!("failed to second proposal {index}: {err:#}"))
+                        .await;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+// Print whether an ongoing referendum would pass with additional aye and nay votes of the
+// given balances and conviction, on top of its current tally
+async fn print_simulation<C: ChainConfig>(
+    program: &Program<C>,
+    index: u32,
+    extra_aye: u128,
+    extra_nay: u128,
+    conviction: Conviction,
+) -> Result<()> {
+    let now = program.snapshot().await?.number;
+    let (passing, details) = match program.governance()? {
+        Governance::OpenGov => {
+            let query = kitchensink::storage()
+                .referenda()
+                .referendum_info_for(index);
+            let Some(CvReferendumInfo::Ongoing(mut status)) =
+                program.storage().await?.fetch(&query).await?
+            else {
+                anyhow::bail!("referendum {index} is not ongoing");
+            };
+            let track = program.track(status.track)?;
+            let before = program.referendum_thresholds(&status, &track, now).await?;
+
+            // support counts the aye capital, without conviction
+            let tally = &mut status.tally;
+            tally.ayes = tally.ayes.saturating_add(conviction.votes(extra_aye));
+            tally.nays = tally.nays.saturating_add(conviction.votes(extra_nay));
+            tally.support = tally.support.saturating_add(extra_aye);
+            let after = program.referendum_thresholds(&status, &track, now).await?;
+
+            let passing =
+                after.approval >= after.min_approval && after.support >= after.min_support;
+            let details = json!({
+                "approval_ppb": [before.approval, after.approval],
+                "min_approval_ppb": after.min_approval,
+                "support_ppb": [before.support, after.support],
+                "min_support_ppb": after.min_support,
+            });
+            print!(
+                program,
+                "approval: {} -> {} (required {}) {}",
+                format_ppb(before.approval),
+                format_ppb(after.approval),
+                format_ppb(after.min_approval),
+                check_mark(after.approval >= after.min_approval)
+            );
+            print!(
+                program,
+                "support: {} -> {} (required {}) {}",
+                format_ppb(before.support),
+                format_ppb(after.support),
+                format_ppb(after.min_support),
+                check_mark(after.support >= after.min_support)
+            );
+            (passing, details)
+        }
+        Governance::Democracy => {
+            let api = program.storage().await?;
+            let query = kitchensink::storage().democracy().referendum_info_of(index);
+            let Some(DemocracyReferendumInfo::Ongoing(status)) = api.fetch(&query).await? else {
+                anyhow::bail!("referendum {index} is not ongoing");
+            };
+            let electorate = api
+                .fetch_or_default(&kitchensink::storage().balances().total_issuance())
+                .await?;
+
+            // the turnout counts the capital of both sides, without conviction
+            let tally = status.tally;
+            let ayes = tally.ayes.saturating_add(conviction.votes(extra_aye));
+            let nays = tally.nays.saturating_add(conviction.votes(extra_nay));
+            let turnout = tally
+                .turnout
+                .saturating_add(extra_aye)
+                .saturating_add(extra_nay);
+            let passing = democracy_approved(&status.threshold, ayes, nays, turnout, electorate);
+            let details = json!({
+                "threshold": format!("{:?}", status.threshold),
+                "ayes": [tally.ayes, ayes],
+                "nays": [tally.nays, nays],
+                "turnout": [tally.turnout, turnout],
+                "electorate": electorate,
+            });
+            print!(
+                program,
+                "threshold: {:?}, voting ends {}",
+                status.threshold,
+                program.describe_block(status.end, now)
+            );
+            print!(
+                program,
+                "ayes: {} -> {}",
+                program.balance(tally.ayes),
+                program.balance(ayes)
+            );
+            print!(
+                program,
+                "nays: {} -> {}",
+                program.balance(tally.nays),
+                program.balance(nays)
+            );
+            print!(
+                program,
+                "turnout: {} -> {} of {}",
+                program.balance(tally.turnout),
+                program.balance(turnout),
+                program.balance(electorate)
+            );
+            (passing, details)
+        }
+    };
+
+    let verdict = if passing {
+        "would pass"
+    } else {
+        "would not pass"
+    };
+    program.output.record(
+        "simulation",
+        json!({
+            "index": index,
+            "extra_aye": extra_aye,
+            "extra_nay": extra_nay,
+            "conviction": conviction.to_string(),
+            "passing": passing,
+            "details": details,
+        }),
+        format!("referendum {index} {verdict} {}", check_mark(passing)),
+    );
+    Ok(())
+}
+
+// Check that a referendum proposes the call data of a file before voting on it
+async fn verify_call<C: ChainConfig>(program: &Program<C>, index: u32, path: &Path) -> Result<()> {
+    let local = read_call_data(path)?;
+    program.verify_referendum_call(index, &local).await?;
+    success!(
+        program,
+        "referendum #{index} proposes the call data of {}",
+        path.display()
+    );
+    Ok(())
+}
+
+// Preimage hash of a proposal
+fn bounded_hash<Call, Hash>(proposal: &Bounded<Call, Hash>) -> H256 {
+    match proposal {
+        Bounded::Legacy { hash } | Bounded::Lookup { hash, .. } => *hash,
+        Bounded::Inline(call) => call_hash(&call.0).0,
+        Bounded::__Ignore(_) => H256::zero(),
+    }
+}
+
+// Propose a noted preimage and wait for the launch period to table it, returning the index of
+// the referendum
+async fn launch<C: ChainConfig>(program: &Program<C>, hash: H256, len: u32) -> Result<u32> {
+    program.check_preimage(hash, len).await?;
+    let tx = kitchensink::tx()
+        .democracy()
+        .propose(Bounded::Lookup { hash, len }, PROPOSAL_DEPOSIT);
+    program
+        .confirm_transaction(&tx, Some(("deposit", PROPOSAL_DEPOSIT)))
+        .await?;
+    let events = program.submit_and_watch(&tx).await?;
+    let proposal_index = events
+        .find_first::<kitchensink::democracy::events::Proposed>()?
+        .ok_or_else(|| CliError::MissingEvent("Democracy::Proposed".into()))?
+        .proposal_index;
+
+    print!(
+        program,
+        "proposal {proposal_index} created, waiting for the launch period"
+    );
+    program
+        .wait_for_event_where::<kitchensink::democracy::events::Tabled>(|ev| {
+            ev.proposal_index == proposal_index
+        })
+        .await?;
+
+    // `Started` doesn't name the proposal, look for the referendum of the preimage
+    let query = kitchensink::storage().democracy().referendum_info_of_iter();
+    for (key, info) in program.iter_storage(&query).await? {
+        if let DemocracyReferendumInfo::Ongoing(status) = info {
+            if matches!(status.proposal, Bounded::Lookup { hash: proposal, .. } if proposal == hash)
+            {
+                return Ok(storage_key_index(&key));
+            }
+        }
+    }
+    Err(CliError::MissingEvent("Democracy::Started".into()).into())
+}
+
+// Start a referendum for a noted preimage at once, as an external proposal of the council
+// fast-tracked by the technical committee, returning its index
+async fn fast_track<C: ChainConfig>(
+    program: &Program<C>,
+    hash: H256,
+    len: u32,
+    approvers: &[User],
+) -> Result<u32> {
+    program.require_pallet("Council")?;
+    program.require_pallet("TechnicalCommittee")?;
+    let voting_period = program.api.constants().at(&kitchensink::constants()
+        .democracy()
+        .fast_track_voting_period())?;
+
+    program.check_preimage(hash, len).await?;
+    let propose = kitchensink::Call::Democracy(DemocracyCall::external_propose_majority {
+        proposal: Bounded::Lookup { hash, len },
+    });
+    Collective::Council
+        .approve(program, propose, approvers)
+        .await?;
+    technical_committee::fast_track(program, hash, approvers, voting_period, DEFAULT_ENACT_AFTER)
+        .await
+}
+
+// Submit an OpenGov referendum for a noted preimage, place its decision deposit and wait for
+// the decision to start, returning its index
+async fn start_referendum<C: ChainConfig>(
+    program: &Program<C>,
+    hash: H256,
+    len: u32,
+) -> Result<u32> {
+    let enactment = DispatchTime::After(DEFAULT_ENACT_AFTER);
+    let index = program.submit_referendum(hash, len, enactment).await?.index;
+
+    print!(
+        program,
+        "placing the decision deposit of referendum {index}"
+    );
+    let tx = kitchensink::tx().referenda().place_decision_deposit(index);
+    let events = program.submit_and_watch(&tx).await?;
+
+    // the deposit services the referendum, the decision can start in its own block
+    for started in events.find::<kitchensink::referenda::events::DecisionStarted>() {
+        if started?.index == index {
+            success!(program, "referendum {index} entered its decision period");
+            return Ok(index);
+        }
+    }
+    // or in a block finalized before the subscription opens
+    program.unpin();
+    let query = kitchensink::storage()
+        .referenda()
+        .referendum_info_for(index);
+    if let Some(CvReferendumInfo::Ongoing(status)) = program.storage().await?.fetch(&query).await? {
+        if status.deciding.is_some() {
+            success!(program, "referendum {index} entered its decision period");
+            return Ok(index);
+        }
+    }
+
+    print!(
+        program,
+        "waiting for referendum {index} to enter its decision period"
+    );
+    program
+        .wait_for_event_where::<kitchensink::referenda::events::DecisionStarted>(|ev| {
+            ev.index == index
+        })
+        .await?;
+    Ok(index)
+}
+
+// Pre-flight checks of a runtime upgrade, `set_code` failing at enactment when the spec name
+// differs or the spec version doesn't increase
+async fn check_runtime_upgrade<C: ChainConfig>(program: &Program<C>, code: &[u8]) -> Result<()> {
+    if wasm::is_compressed(code) {
+        warning!(
+            program,
+            "compressed wasm blob, skipping the runtime version check"
+        );
+    } else {
+        let version = wasm::runtime_version(code).context("reading the runtime version")?;
+        let at = program.snapshot().await?.hash;
+        let chain: serde_json::Value = program
+            .rpc
+            .request("state_getRuntimeVersion", rpc_params![at])
+            .await?;
+        let spec_name = chain["specName"].as_str().unwrap_or_default();
+        let spec_version = chain["specVersion"].as_u64().unwrap_or_default();
+        if version.spec_name != spec_name {
+            anyhow::bail!(
+                "the blob is a {} runtime, the chain runs {spec_name}",
+                version.spec_name
+            );
+        }
+        if u64::from(version.spec_version) <= spec_version {
+            anyhow::bail!(
+                "the blob has spec version {}, not above the chain's {spec_version}",
+                version.spec_version
+            );
+        }
+        print!(
+            program,
+            "upgrading {spec_name} from spec version {spec_version} to {}", version.spec_version
+        );
+    }
+
+    // the preimage is noted by a single normal extrinsic
+    let max = program
+        .api
+        .constants()
+        .at(&kitchensink::constants().system().block_length())?
+        .max
+        .normal;
+    if code.len() as u64 >= u64::from(max) {
+        warning!(
+            program,
+            "the preimage of {} bytes exceeds the {max} bytes of a normal block, noting it will \
+             fail",
+            code.len()
+        );
+    }
+
+    let now = program.snapshot().await?.number;
+    let enactment = match program.governance()? {
+        Governance::Democracy => {
+            let constants = kitchensink::constants().democracy();
+            let launch_period = program.api.constants().at(&constants.launch_period())?;
+            let voting_period = program.api.constants().at(&constants.voting_period())?;
+            let enactment_period = program.api.constants().at(&constants.enactment_period())?;
+            // tabled at the next launch period at best, when it is the most seconded proposal
+            (now / launch_period + 1).saturating_mul(launch_period)
+                + voting_period
+                + enactment_period
+        }
+        Governance::OpenGov => {
+            let root = program.track(0)?;
+            // decided once prepared and confirmed at best, mirrors `submit_referendum`
+            now + root.prepare_period
+                + root.confirm_period
+                + DEFAULT_ENACT_AFTER.max(root.min_enactment_period)
+        }
+    };
+    print!(
+        program,
+        "the upgrade would be enacted at {} at the earliest",
+        program.describe_block(enactment, now)
+    );
+    Ok(())
+}
+
+// Record a delegator, returning the votes it delegates with its table row
+fn delegator_row<C: ChainConfig>(
+    program: &Program<C>,
+    delegator: &AccountId32,
+    class: Option<u16>,
+    balance: u128,
+    conviction: u8,
+) -> (u128, Vec<String>) {
+    let conviction = Conviction::from_index(conviction).unwrap_or_default();
+    let votes = conviction.votes(balance);
+    program.output.record_json(
+        "delegator",
+        json!({
+            "delegator": program.address(delegator),
+            "class": class,
+            "balance": balance,
+            "conviction": conviction.to_string(),
+            "votes": votes,
+        }),
+    );
+    let row = vec![
+        program.address(delegator),
+        class.map_or("-".to_string(), |class| class.to_string()),
+        program.balance(balance),
+        conviction.to_string(),
+        program.balance(votes),
+    ];
+    (votes, row)
+}
+
+// Call of `unlock-all`, with the class it applies to
+struct Unlock {
+    class: Option<u16>,
+    description: String,
+    call: kitchensink::Call,
+}
+
+// Block until which a vote keeps the balance locked once its referendum is over, the lock only
+// applies to the standard votes on the winning side
+fn vote_lock_end(vote: Option<u8>, approved: bool, end: u32, locking_period: u32) -> u32 {
+    let Some(vote) = vote else {
+        return end;
+    };
+    let aye = vote & 0b1000_0000 != 0;
+    match Conviction::from_index(vote & 0b0111_1111) {
+        Some(conviction) if aye == approved => {
+            end.saturating_add(locking_period.saturating_mul(conviction.lock_periods()))
+        }
+        _ => end,
+    }
+}
+
+// Remove the democracy votes of the finished referenda, then unlock when no lock is running
+async fn democracy_unlocks<C: ChainConfig>(program: &Program<C>, now: u32) -> Result<Vec<Unlock>> {
+    let account = program.account_id();
+    let api = program.storage().await?;
+    let locking_period = program
+        .api
+        .constants()
+        .at(&kitchensink::constants().democracy().vote_locking_period())?;
+    let query = kitchensink::storage().democracy().voting_of(&account);
+    let (votes, prior) = match api.fetch(&query).await? {
+        Some(DemocracyVoting::Direct { votes, prior, .. }) => (votes.0, prior),
+        Some(DemocracyVoting::Delegating { target, .. }) => {
+            warning!(
+                program,
+                "delegating to {}, undelegate first",
+                program.address(&target)
+            );
+            return Ok(Vec::new());
+        }
+        None => return Ok(Vec::new()),
+    };
+
+    let mut unlocks = Vec::new();
+    // the prior lock holds the conviction locks of the votes already removed
+    let mut locked_until = if prior.1 > 0 { prior.0 } else { 0 };
+    let mut ongoing = 0;
+    let queries = votes
+        .iter()
+        .map(|(index, _)| kitchensink::storage().democracy().referendum_info_of(index))
+        .collect::<Vec<_>>();
+    let infos = program.fetch_many(&queries).await?;
+    for ((index, vote), info) in votes.into_iter().zip(infos) {
+        let end = match info {
+            Some(DemocracyReferendumInfo::Ongoing(_)) => {
+                ongoing += 1;
+                continue;
+            }
+            Some(DemocracyReferendumInfo::Finished { approved, end }) => {
+                let vote = match vote {
+                    DemocracyAccountVote::Standard { vote, .. } => Some(vote.0),
+                    DemocracyAccountVote::Split { .. } => None,
+                };
+                vote_lock_end(vote, approved, end, locking_period)
+            }
+            // cancelled referenda don't lock the votes
+            None => 0,
+        };
+        locked_until = locked_until.max(end);
+        unlocks.push(Unlock {
+            class: None,
+            description: format!("remove vote on referendum {index}"),
+            call: kitchensink::Call::Democracy(DemocracyCall::remove_vote { index }),
+        });
+    }
+
+    if ongoing > 0 {
+        print!(
+            program,
+            "{ongoing} votes on ongoing referenda keep their lock"
+        );
+    } else if locked_until > now {
+        warning!(
+            program,
+            "the balance stays locked until {}",
+            program.describe_block(locked_until, now)
+        );
+    } else if !unlocks.is_empty() || prior.1 > 0 {
+        unlocks.push(Unlock {
+            class: None,
+            description: "unlock".to_string(),
+            call: kitchensink::Call::Democracy(DemocracyCall::unlock {
+                target: account.into(),
+            }),
+        });
+    }
+    Ok(unlocks)
+}
+
+// Remove the conviction votes of the finished referenda, then unlock the classes without a
+// running lock
+async fn cv_unlocks<C: ChainConfig>(program: &Program<C>, now: u32) -> Result<Vec<Unlock>> {
+    let account = program.account_id();
+    let api = program.storage().await?;
+    let locking_period = program.api.constants().at(&kitchensink::constants()
+        .conviction_voting()
+        .vote_locking_period())?;
+    let query = kitchensink::storage()
+        .conviction_voting()
+        .class_locks_for(&account);
+    let locked_classes = api
+        .fetch_or_default(&query)
+        .await?
+        .0
+        .into_iter()
+        .map(|(class, _)| class)
+        .collect::<HashSet<_>>();
+
+    let mut unlocks = Vec::new();
+    let query = kitchensink::storage()
+        .conviction_voting()
+        .voting_for_iter1(&account);
+    for (key, voting) in program.iter_storage(&query).await? {
+        let class = storage_key_class(&key);
+        let casting = match voting {
+            Voting::Casting(casting) => casting,
+            Voting::Delegating(delegating) => {
+                print!(
+                    program,
+                    "class {class} is delegated to {}, undelegate first",
+                    program.address(&delegating.target)
+                );
+                continue;
+            }
+            Voting::__Ignore(_) => continue,
+        };
+
+        let prior = casting.prior;
+        let mut locked_until = if prior.1 > 0 { prior.0 } else { 0 };
+        let mut ongoing = 0;
+        let queries = casting
+            .votes
+            .0
+            .iter()
+            .map(|(index, _)| {
+                kitchensink::storage()
+                    .referenda()
+                    .referendum_info_for(index)
+            })
+            .collect::<Vec<_>>();
+        let infos = program.fetch_many(&queries).await?;
+        for ((index, vote), info) in casting.votes.0.into_iter().zip(infos) {
+            let vote = match vote {
+                CvAccountVote::Standard { vote, .. } => Some(vote.0),
+                CvAccountVote::Split { .. } | CvAccountVote::SplitAbstain { .. } => None,
+            };
+            let end = match info {
+                Some(CvReferendumInfo::Ongoing(_)) => {
+                    ongoing += 1;
+                    continue;
+                }
+                Some(CvReferendumInfo::Approved(end, ..)) => {
+                    vote_lock_end(vote, true, end, locking_period)
+                }
+                Some(CvReferendumInfo::Rejected(end, ..)) => {
+                    vote_lock_end(vote, false, end, locking_period)
+                }
+                // cancelled, timed out and killed referenda don't lock the votes
+                _ => 0,
+            };
+            locked_until = locked_until.max(end);
+            unlocks.push(Unlock {
+                class: Some(class),
+                description: format!("remove vote on referendum {index}"),
+                call: kitchensink::Call::ConvictionVoting(CvCall::remove_vote {
+                    class: Some(class),
+                    index,
+                }),
+            });
+        }
+
+        if ongoing > 0 || !locked_classes.contains(&class) {
+            continue;
+        }
+        if locked_until > now {
+            warning!(
+                program,
+                "class {class} stays locked until {}",
+                program.describe_block(locked_until, now)
+            );
+            continue;
+        }
+        unlocks.push(Unlock {
+            class: Some(class),
+            description: "unlock".to_string(),
+            call: kitchensink::Call::ConvictionVoting(CvCall::unlock {
+                class,
+                target: account.clone().into(),
+            }),
+        });
+    }
+    Ok(unlocks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vote_lock_ends() {
+        const AYE: u8 = 0b1000_0000;
+        let (end, period) = (1_000, 100);
+
+        // the winning side is locked for the lock periods of its conviction
+        let locks = [
+            (Conviction::None, 0),
+            (Conviction::Locked1x, 1),
+            (Conviction::Locked2x, 2),
+            (Conviction::Locked3x, 4),
+            (Conviction::Locked4x, 8),
+            (Conviction::Locked5x, 16),
+            (Conviction::Locked6x, 32),
+        ];
+        for (conviction, periods) in locks {
+            let lock_end = end + periods * period;
+            assert_eq!(
+                vote_lock_end(Some(AYE | conviction as u8), true, end, period),
+                lock_end,
+                "aye {conviction:?}"
+            );
+            assert_eq!(
+                vote_lock_end(Some(conviction as u8), false, end, period),
+                lock_end,
+                "nay {conviction:?}"
+            );
+
+            // the losing side isn't locked
+            assert_eq!(
+                vote_lock_end(Some(AYE | conviction as u8), false, end, period),
+                end
+            );
+            assert_eq!(
+                vote_lock_end(Some(conviction as u8), true, end, period),
+                end
+            );
+        }
+
+        // split and abstain votes have no conviction
+        assert_eq!(vote_lock_end(None, true, end, period), end);
+        assert_eq!(vote_lock_end(None, false, end, period), end);
+        // an unknown conviction doesn't lock
+        assert_eq!(vote_lock_end(Some(AYE | 7), true, end, period), end);
+        // the lock end saturates
+        assert_eq!(
+            vote_lock_end(
+                Some(AYE | Conviction::Locked6x as u8),
+                true,
+                u32::MAX - 1,
+                period
+            ),
+            u32::MAX
+        );
+    }
+
+    // Read an allowlist written to a temporary file
+    fn allowlist(name: &str, content: &str) -> Result<HashSet<H256>> {
+        let path = std::env::temp_dir().join(format!("{name}-{}.txt", std::process::id()));
+        std::fs::write(&path, content).unwrap();
+        let allowlist = read_allowlist(&path);
+        std::fs::remove_file(&path).unwrap();
+        allowlist
+    }
+
+    #[test]
+    fn allowlist_skips_comments_and_blank_lines() {
+        let content = format!(
+            "# preimages to second\n\n  0x{} \n{}  # without prefix\n   \n",
+            "11".repeat(32),
+            "22".repeat(32)
+        );
+        let hashes = allowlist("allowlist", &content).unwrap();
+        assert_eq!(
+            hashes,
+            HashSet::from([H256::repeat_byte(0x11), H256::repeat_byte(0x22)])
+        );
+        assert!(allowlist("empty-allowlist", "# nothing\n\n")
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn allowlist_rejects_bad_hashes() {
+        let content = format!("0x{}\n\n0x1234\n", "11".repeat(32));
+        let err = allowlist("bad-allowlist", &content).unwrap_err();
+        let Some(CliError::Validation { what, source }) = err.downcast_ref() else {
+            panic!("unexpected error {err:?}");
+        };
+        // the line of the bad hash is reported
+        assert!(what.ends_with(".txt:3"), "{what}");
+        assert!(source.to_string().contains("expected 32 bytes"), "{source}");
+
+        let err = allowlist("not-hex-allowlist", "0xhello\n").unwrap_err();
+        assert!(err.downcast_ref::<CliError>().is_some());
+    }
+
+    #[test]
+    fn missing_allowlist_fails() {
+        let err = read_allowlist(Path::new("/nonexistent/allowlist.txt")).unwrap_err();
+        assert_eq!(err.to_string(), "failed to read /nonexistent/allowlist.txt");
+    }
+}
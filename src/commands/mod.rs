@@ -0,0 +1,13 @@
+//! Handlers of the subcommands, grouped by the pallet they drive. Each module has its clap
+//! subcommands and a `run` entry point taking the connected [`crate::Program`], `collective` holds
+//! the motion flow shared by the council and the technical committee.
+pub mod balances;
+pub mod call;
+mod collective;
+pub mod conviction_voting;
+pub mod council;
+pub mod democracy;
+pub mod preimage;
+pub mod referenda;
+pub mod technical_committee;
+pub mod whitelist;
@@ -0,0 +1,234 @@
+//! Noting, requesting and listing the preimages of the preimage pallet.
+use std::path::PathBuf;
+
+use anyhow::Result;
+use codec::Encode;
+use serde_json::json;
+use subxt::utils::{AccountId32, H256};
+
+use crate::call::CallDescription;
+use crate::chain::{ChainConfig, ChainProperties};
+use crate::chain_api::ChainApi;
+use crate::output::{Output, Table};
+use crate::units::parse_h256;
+use crate::{
+    copy_to_clipboard, dynamic, kitchensink, preimages, print, success, CallData, Program,
+};
+
+/// Preimage subcommands
+#[derive(clap::Subcommand, Debug)]
+pub enum Command {
+    CreateRemarkPreimage {
+        remark: String,
+        /// Copy the preimage hash to the clipboard
+        #[clap(long)]
+        copy: bool,
+    },
+    NotePreimageHex {
+        hex: String,
+        /// Copy the preimage hash to the clipboard
+        #[clap(long)]
+        copy: bool,
+    },
+    NotePreimageJson {
+        #[clap(long)]
+        file: PathBuf,
+        /// Copy the preimage hash to the clipboard
+        #[clap(long)]
+        copy: bool,
+    },
+    UnnotePreimage {
+        #[clap(value_parser = parse_h256)]
+        hash: H256,
+    },
+    RequestPreimage {
+        #[clap(value_parser = parse_h256)]
+        hash: H256,
+    },
+    UnrequestPreimage {
+        #[clap(value_parser = parse_h256)]
+        hash: H256,
+    },
+    VerifyPreimage {
+        #[clap(value_parser = parse_h256)]
+        hash: H256,
+        #[clap(flatten)]
+        call: CallData,
+    },
+    ListPreimages {
+        /// Only show the preimages deposited by the current user
+        #[clap(long)]
+        mine: bool,
+    },
+}
+
+/// Run a preimage subcommand
+pub async fn run<C: ChainConfig>(program: &Program<C>, command: Command) -> Result<()> {
+    match command {
+        Command::CreateRemarkPreimage { remark, copy } => {
+            program.require_pallet("Preimage")?;
+            let image = if program.dynamic {
+                let call = dynamic::remark(remark.into_bytes());
+                program.api.tx().call_data(&call)?
+            } else {
+                kitchensink::Call::System(
+                    kitchensink::runtime_types::frame_system::pallet::Call::remark {
+                        remark: remark.into_bytes(),
+                    },
+                )
+                .encode()
+            };
+            let (hash, _) = program.note_preimage(image).await?;
+            if copy {
+                copy_to_clipboard(&program.output, "preimage hash", &format!("{hash:?}"))?;
+            }
+        }
+        Command::NotePreimageHex { hex, copy } => {
+            program.require_pallet("Preimage")?;
+            let image = hex::decode(hex.trim_start_matches("0x"))?;
+            let (hash, _) = program.note_preimage(image).await?;
+            if copy {
+                copy_to_clipboard(&program.output, "preimage hash", &format!("{hash:?}"))?;
+            }
+        }
+        Command::NotePreimageJson { file, copy } => {
+            program.require_pallet("Preimage")?;
+            let payload = CallDescription::from_file(&file)?.payload()?;
+            let image = program.api.tx().call_data(&payload)?;
+            let (hash, _) = program.note_preimage(image).await?;
+            if copy {
+                copy_to_clipboard(&program.output, "preimage hash", &format!("{hash:?}"))?;
+            }
+        }
+        Command::UnnotePreimage { hash } => {
+            program.require_pallet("Preimage")?;
+
+            print!(program, "unnoting preimage {hash}");
+            let events = if program.dynamic {
+                let tx = dynamic::unnote_preimage(hash);
+                program.submit_and_watch(&tx).await?
+            } else {
+                let tx = kitchensink::tx().preimage().unnote_preimage(hash);
+                program.submit_and_watch(&tx).await?
+            };
+            // a requested preimage is kept, only its deposit is returned
+            if let Some(cleared) = events.find_first::<kitchensink::preimage::events::Cleared>()? {
+                success!(program, "preimage cleared {:?}", cleared);
+            } else {
+                print!(
+                    program,
+                    "preimage unnoted, not cleared as it is still requested"
+                );
+            }
+        }
+        Command::RequestPreimage { hash } => {
+            program.require_pallet("Preimage")?;
+            let call = kitchensink::Call::Preimage(
+                kitchensink::runtime_types::pallet_preimage::pallet::Call::request_preimage {
+                    hash,
+                },
+            );
+
+            // requesting is restricted to the preimage manager origin
+            print!(program, "requesting preimage {hash}");
+            let events = program.sudo_and_watch(call).await?;
+            // only the first request of a preimage emits the event
+            if let Some(requested) =
+                events.find_first::<kitchensink::preimage::events::Requested>()?
+            {
+                success!(program, "preimage requested {:?}", requested);
+            } else {
+                print!(
+                    program,
+                    "preimage already requested, its request count increased"
+                );
+            }
+        }
+        Command::UnrequestPreimage { hash } => {
+            program.require_pallet("Preimage")?;
+            let call = kitchensink::Call::Preimage(
+                kitchensink::runtime_types::pallet_preimage::pallet::Call::unrequest_preimage {
+                    hash,
+                },
+            );
+
+            print!(program, "unrequesting preimage {hash}");
+            let events = program.sudo_and_watch(call).await?;
+            if let Some(cleared) = events.find_first::<kitchensink::preimage::events::Cleared>()? {
+                success!(program, "preimage cleared {:?}", cleared);
+            } else {
+                print!(program, "preimage unrequested, still noted or requested");
+            }
+        }
+        Command::VerifyPreimage { hash, call } => {
+            program.require_pallet("Preimage")?;
+            let local = program.encode_call(&call)?;
+            program.verify_preimage(hash, &local).await?;
+            success!(
+                program,
+                "match: on-chain preimage {hash:?} equals the local call data"
+            );
+        }
+        Command::ListPreimages { mine } => {
+            program.require_pallet("Preimage")?;
+            let account = program.account_id();
+            let depositor = mine.then_some(&account);
+            print_preimages(
+                &program.chain_api(false),
+                &program.output,
+                &program.properties,
+                depositor,
+            )
+            .await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Print the preimages known to the preimage pallet, only those deposited by `depositor` when
+/// given
+pub async fn print_preimages(
+    chain: &(impl ChainApi + ?Sized),
+    output: &Output,
+    properties: &ChainProperties,
+    depositor: Option<&AccountId32>,
+) -> Result<()> {
+    let mut table = Table::new(&["hash", "len", "status", "deposit", "depositor"]);
+    for entry in preimages(chain).await? {
+        if let Some(depositor) = depositor {
+            if !matches!(&entry.deposit, Some((who, _)) if who == depositor) {
+                continue;
+            }
+        }
+
+        let fields = json!({
+            "hash": entry.hash,
+            "len": entry.len,
+            "status": entry.status,
+            "deposit": entry.deposit.as_ref().map(|(who, amount)| {
+                json!({ "depositor": properties.format_address(who), "amount": amount })
+            }),
+        });
+        output.record_json("preimage", fields);
+
+        let (deposit, depositor) =
+            entry
+                .deposit
+                .map_or(("none".to_string(), "-".to_string()), |(who, amount)| {
+                    (
+                        properties.format_balance(amount),
+                        properties.format_address(&who),
+                    )
+                });
+        table.add_row(vec![
+            format!("{:?}", entry.hash),
+            entry.len.map_or("?".to_string(), |len| len.to_string()),
+            entry.status,
+            deposit,
+            depositor,
+        ]);
+    }
+
+    output.table(&table)
+}
@@ -0,0 +1,391 @@
+//! OpenGov referenda of the referenda pallet.
+use anyhow::Result;
+use serde_json::json;
+use subxt::events::{Events, StaticEvent};
+use subxt::utils::H256;
+
+use crate::chain::{ChainConfig, ChainProperties};
+use crate::chain_api::{self, ChainApi};
+use crate::kitchensink::runtime_types::frame_support::traits::schedule::DispatchTime;
+use crate::kitchensink::runtime_types::pallet_conviction_voting::types::Tally;
+use crate::kitchensink::runtime_types::pallet_referenda::types::ReferendumInfo;
+use crate::output::{Output, Table};
+use crate::units::{parse_h256, BlockNumber};
+use crate::{
+    check_mark, format_ppb, kitchensink, ongoing_referenda, print, referendum_phase, success,
+    Program, Thresholds, DEFAULT_ENACT_AFTER,
+};
+
+/// OpenGov referenda subcommands
+#[derive(clap::Subcommand, Debug)]
+pub enum Command {
+    List,
+    /// List the tracks with their deposits and periods
+    Tracks,
+    Show {
+        index: u32,
+        /// Chart the ayes and nays sampled across the blocks since the submission, from an
+        /// archive node for referenda older than the pruning window
+        #[clap(long)]
+        chart: bool,
+    },
+    RefundSubmissionDeposit {
+        index: u32,
+    },
+    RefundAll,
+    Submit {
+        #[clap(value_parser = parse_h256)]
+        hash: H256,
+        len: u32,
+        /// Enact the proposal this many blocks after approval
+        #[clap(long, conflicts_with = "enact_at")]
+        enact_after: Option<u32>,
+        /// Enact the proposal at this block
+        #[clap(long)]
+        enact_at: Option<BlockNumber>,
+        /// Wait for the referendum to be approved and show its enactment block
+        #[clap(long)]
+        wait: bool,
+    },
+}
+
+/// Run a referenda subcommand
+pub async fn run<C: ChainConfig>(program: &Program<C>, command: Command) -> Result<()> {
+    program.require_pallet("Referenda")?;
+    match command {
+        Command::List => list(program).await?,
+        Command::Tracks => program.print_tracks()?,
+        Command::Show { index, chart } => print_referendum(program, index, chart).await?,
+        Command::RefundSubmissionDeposit { index } => {
+            let referenda = kitchensink::tx().referenda();
+
+            print!(
+                program,
+                "refunding submission deposit of referendum {index}"
+            );
+            let tx = referenda.refund_submission_deposit(index);
+            let events = program.submit_and_watch(&tx).await?;
+            let refunded =
+                events.find_first::<kitchensink::referenda::events::SubmissionDepositRefunded>()?;
+            success!(program, "submission deposit refunded {:?}", refunded);
+        }
+        Command::RefundAll => {
+            let indices = program.refundable_submission_deposits().await?;
+            if indices.is_empty() {
+                print!(program, "no submission deposit to refund");
+                return Ok(());
+            }
+
+            print!(
+                program,
+                "refunding submission deposits of referenda {indices:?}"
+            );
+            let calls = indices
+                .into_iter()
+                .map(|index| {
+                    kitchensink::Call::Referenda(
+                        kitchensink::runtime_types::pallet_referenda::pallet::Call::refund_submission_deposit {
+                            index,
+                        },
+                    )
+                })
+                .collect();
+            let tx = kitchensink::tx().utility().batch(calls);
+            let events = program.submit_and_watch(&tx).await?;
+            for refunded in
+                events.find::<kitchensink::referenda::events::SubmissionDepositRefunded>()
+            {
+                success!(program, "submission deposit refunded {:?}", refunded?);
+            }
+        }
+        Command::Submit {
+            hash,
+            len,
+            enact_after,
+            enact_at,
+            wait,
+        } => {
+            let enactment = match enact_at {
+                Some(block) => DispatchTime::At(block.into()),
+                None => DispatchTime::After(enact_after.unwrap_or(DEFAULT_ENACT_AFTER)),
+            };
+            let submitted = program.submit_referendum(hash, len, enactment).await?;
+            let index = submitted.index;
+            if !wait {
+                return Ok(());
+            }
+
+            let (approved_at, outcome) = program
+                .wait_for_events(&format!("the conclusion of referendum {index}"), |events| {
+                    conclusion(events, index)
+                })
+                .await?;
+            if outcome != "approved" {
+                anyhow::bail!("referendum {index} was {outcome} at block {approved_at}");
+            }
+
+            // mirrors `pallet_referenda::Pallet::schedule_enactment`
+            let min_enactment_period = program.track(submitted.track)?.min_enactment_period;
+            let desired = match enact_at {
+                Some(block) => block.into(),
+                None => approved_at.saturating_add(enact_after.unwrap_or(DEFAULT_ENACT_AFTER)),
+            };
+            let enactment = desired.max(approved_at.saturating_add(min_enactment_period));
+            success!(
+                program,
+                "referendum {index} approved at block {approved_at}, enactment scheduled at {}",
+                program.describe_block(enactment, approved_at)
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// How referendum `index` concluded in the events of a block, if it did
+fn conclusion<C: ChainConfig>(events: &Events<C>, index: u32) -> Result<Option<&'static str>> {
+    use kitchensink::referenda::events::{Approved, Cancelled, Killed, Rejected, TimedOut};
+
+    fn found<C: ChainConfig, Ev: StaticEvent>(
+        events: &Events<C>,
+        index: u32,
+        index_of: fn(&Ev) -> u32,
+    ) -> Result<bool> {
+        for event in events.find::<Ev>() {
+            if index_of(&event?) == index {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    let outcome = if found::<_, Approved>(events, index, |ev| ev.index)? {
+        "approved"
+    } else if found::<_, Rejected>(events, index, |ev| ev.index)? {
+        "rejected"
+    } else if found::<_, TimedOut>(events, index, |ev| ev.index)? {
+        "timed out"
+    } else if found::<_, Cancelled>(events, index, |ev| ev.index)? {
+        "cancelled"
+    } else if found::<_, Killed>(events, index, |ev| ev.index)? {
+        "killed"
+    } else {
+        return Ok(None);
+    };
+    Ok(Some(outcome))
+}
+
+/// Print the ongoing OpenGov referenda of the chain, grouped by track
+pub async fn list<C: ChainConfig>(program: &Program<C>) -> Result<()> {
+    print_referenda_by_track(
+        &program.chain_api(true),
+        &program.output,
+        &program.properties,
+    )
+    .await
+}
+
+/// Print the ongoing OpenGov referenda, grouped by track
+pub async fn print_referenda_by_track(
+    chain: &(impl ChainApi + ?Sized),
+    output: &Output,
+    properties: &ChainProperties,
+) -> Result<()> {
+    let tracks = chain_api::constant(chain, &kitchensink::constants().referenda().tracks())?;
+    let mut table = Table::new(&[
+        "track", "index", "phase", "deciding", "ayes", "nays", "support",
+    ]);
+    for (track, referenda) in ongoing_referenda(chain).await? {
+        let name = tracks
+            .iter()
+            .find_map(|(id, info)| (*id == track).then_some(info.name.as_str()))
+            .ok_or_else(|| anyhow::anyhow!("unknown track {track}"))?;
+        for (index, status) in referenda {
+            let deciding_since = status
+                .deciding
+                .as_ref()
+                .map_or("-".to_string(), |deciding| deciding.since.to_string());
+            let Tally {
+                ayes,
+                nays,
+                support,
+            } = status.tally;
+            output.record_json(
+                "referendum",
+                json!({
+                    "index": index,
+                    "track": track,
+                    "track_name": name,
+                    "phase": referendum_phase(&status),
+                    "deciding_since": status.deciding.as_ref().map(|deciding| deciding.since),
+                    "ayes": ayes,
+                    "nays": nays,
+                    "support": support,
+                }),
+            );
+            table.add_row(vec![
+                format!("{track} ({name})"),
+                format!("#{index}"),
+                referendum_phase(&status).to_string(),
+                deciding_since,
+                properties.format_balance(ayes),
+                properties.format_balance(nays),
+                properties.format_balance(support),
+            ]);
+        }
+    }
+
+    output.table(&table)
+}
+
+// Print an OpenGov referendum, with its approval and support thresholds when ongoing
+async fn print_referendum<C: ChainConfig>(
+    program: &Program<C>,
+    index: u32,
+    chart: bool,
+) -> Result<()> {
+    let api = program.storage().await?;
+    let query = kitchensink::storage()
+        .referenda()
+        .referendum_info_for(index);
+    let info = api
+        .fetch(&query)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("referendum {index} not found"))?;
+
+    let ReferendumInfo::Ongoing(status) = info else {
+        program.output.record(
+            "referendum",
+            json!({ "index": index, "info": format!("{info:?}") }),
+            format!("referendum {index}: {info:?}"),
+        );
+        return Ok(());
+    };
+
+    let track = program.track(status.track)?;
+    let now = program.snapshot().await?.number;
+    let thresholds = program.referendum_thresholds(&status, &track, now).await?;
+    let history = if chart {
+        program.tally_history(index, status.submitted, now).await?
+    } else {
+        Vec::new()
+    };
+    if program.output.is_json() {
+        let Tally {
+            ayes,
+            nays,
+            support,
+        } = status.tally;
+        program.output.record_json(
+            "referendum",
+            json!({
+                "index": index,
+                "track": status.track,
+                "track_name": track.name,
+                "phase": referendum_phase(&status),
+                "enactment": format!("{:?}", status.enactment),
+                "submitted": status.submitted,
+                "deciding_since": status.deciding.as_ref().map(|deciding| deciding.since),
+                "confirm_end": status.deciding.as_ref().and_then(|deciding| deciding.confirming),
+                "alarm": status.alarm.as_ref().map(|(alarm, _)| *alarm),
+                "ayes": ayes,
+                "nays": nays,
+                "support": support,
+                "decision_period": track.decision_period,
+                "elapsed": thresholds.elapsed,
+                "approval_ppb": thresholds.approval,
+                "min_approval_ppb": thresholds.min_approval,
+                "support_ppb": thresholds.support,
+                "min_support_ppb": thresholds.min_support,
+            }),
+        );
+        for (block, tally) in history {
+            program.output.record_json(
+                "tally",
+                json!({
+                    "index": index,
+                    "block": block,
+                    "ayes": tally.ayes,
+                    "nays": tally.nays,
+                    "support": tally.support,
+                }),
+            );
+        }
+        return Ok(());
+    }
+
+    print!(
+        program,
+        "referendum {index} on track {} ({}): {}",
+        status.track,
+        track.name,
+        referendum_phase(&status)
+    );
+    print!(
+        program,
+        "enactment: {}",
+        program.describe_enactment(&status.enactment, now)
+    );
+    print!(
+        program,
+        "submitted: {}",
+        program.describe_block(status.submitted, now)
+    );
+    if let Some(deciding) = &status.deciding {
+        print!(
+            program,
+            "deciding since: {}",
+            program.describe_block(deciding.since, now)
+        );
+        if let Some(confirm_end) = deciding.confirming {
+            let confirm_start = confirm_end.saturating_sub(track.confirm_period);
+            print!(
+                program,
+                "confirming since: {}, ends: {}",
+                program.describe_block(confirm_start, now),
+                program.describe_block(confirm_end, now)
+            );
+        }
+    }
+    if let Some((alarm, _)) = status.alarm {
+        print!(
+            program,
+            "next alarm: {}",
+            program.describe_block(alarm, now)
+        );
+    }
+
+    let Thresholds {
+        elapsed,
+        x,
+        approval,
+        min_approval,
+        support,
+        min_support,
+    } = thresholds;
+    print!(
+        program,
+        "decision period elapsed: {elapsed}/{} ({})",
+        program.describe_period(track.decision_period),
+        format_ppb(x)
+    );
+    print!(
+        program,
+        "approval: {} (required {}) {}",
+        format_ppb(approval),
+        format_ppb(min_approval),
+        check_mark(approval >= min_approval)
+    );
+    print!(
+        program,
+        "support: {} (required {}) {}",
+        format_ppb(support),
+        format_ppb(min_support),
+        check_mark(support >= min_support)
+    );
+    if chart {
+        program.print_tally_chart(&history, now);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,111 @@
+//! Technical committee motions of the collective pallet, fast-tracking the external proposals.
+use anyhow::Result;
+use subxt::utils::H256;
+
+use crate::chain::ChainConfig;
+use crate::commands::collective::Collective;
+use crate::kitchensink::runtime_types::pallet_democracy::pallet::Call as DemocracyCall;
+use crate::kitchensink::runtime_types::pallet_democracy::types::ReferendumInfo as DemocracyReferendumInfo;
+use crate::units::parse_h256;
+use crate::{bounded_call, kitchensink, print, Program, User, DEFAULT_ENACT_AFTER};
+
+/// Technical committee subcommands
+#[derive(clap::Subcommand, Debug)]
+pub enum Command {
+    /// Fast-track the external proposal of a preimage: propose the motion, approve it as the
+    /// given dev members, close it and wait for the referendum to start
+    FastTrack {
+        #[clap(value_parser = parse_h256)]
+        hash: H256,
+        /// Dev member approving the motion besides the signing user, repeat for more members
+        #[clap(long = "approve", value_name = "USER", required = true)]
+        members: Vec<User>,
+        /// Voting period of the referendum in blocks [default: the `FastTrackVotingPeriod`]
+        #[clap(long)]
+        voting_period: Option<u32>,
+        /// Enactment delay after approval, in blocks
+        #[clap(long, default_value_t = DEFAULT_ENACT_AFTER)]
+        delay: u32,
+    },
+}
+
+/// Run a technical committee subcommand
+pub async fn run<C: ChainConfig>(program: &Program<C>, command: Command) -> Result<()> {
+    match command {
+        Command::FastTrack {
+            hash: proposal_hash,
+            members,
+            voting_period,
+            delay,
+        } => {
+            program.require_pallet("TechnicalCommittee")?;
+            program.require_pallet("Democracy")?;
+            let voting_period = match voting_period {
+                Some(period) => period,
+                None => program.api.constants().at(&kitchensink::constants()
+                    .democracy()
+                    .fast_track_voting_period())?,
+            };
+            let index = fast_track(program, proposal_hash, &members, voting_period, delay).await?;
+            program.output.record(
+                "referendum",
+                serde_json::json!({ "index": index, "hash": proposal_hash }),
+                format!("referendum {index} started"),
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Fast-track the external proposal of a preimage: propose the motion, approve it as the given dev
+/// members besides the signing user, close it and wait for the referendum to start, returning its
+/// index
+pub(crate) async fn fast_track<C: ChainConfig>(
+    program: &Program<C>,
+    proposal_hash: H256,
+    members: &[User],
+    voting_period: u32,
+    delay: u32,
+) -> Result<u32> {
+    let fast_track = kitchensink::Call::Democracy(DemocracyCall::fast_track {
+        proposal_hash,
+        voting_period,
+        delay,
+    });
+    print!(
+        program,
+        "fast-tracking {proposal_hash:?}, voting period {}",
+        program.describe_period(voting_period)
+    );
+    let events = Collective::TechnicalCommittee
+        .approve(program, fast_track, members)
+        .await?;
+
+    if let Some(started) = events.find_first::<kitchensink::democracy::events::Started>()? {
+        return Ok(started.ref_index);
+    }
+
+    // `Started` doesn't name the proposal, the referenda started meanwhile, e.g. tabled at the
+    // end of a launch period, are told apart by the preimage they propose
+    let mut others = Vec::new();
+    loop {
+        let (_, started) = program
+            .wait_for_event_where::<kitchensink::democracy::events::Started>(|started| {
+                !others.contains(&started.ref_index)
+            })
+            .await?;
+        program.unpin();
+        let query = kitchensink::storage()
+            .democracy()
+            .referendum_info_of(started.ref_index);
+        if let Some(DemocracyReferendumInfo::Ongoing(status)) =
+            program.storage().await?.fetch(&query).await?
+        {
+            if bounded_call(&status.proposal).0 == proposal_hash {
+                return Ok(started.ref_index);
+            }
+        }
+        others.push(started.ref_index);
+    }
+}
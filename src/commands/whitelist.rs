@@ -0,0 +1,87 @@
+//! Whitelisting calls and dispatching the whitelisted calls of the whitelist pallet.
+use anyhow::Result;
+use codec::Decode;
+use subxt::utils::H256;
+
+use crate::chain::ChainConfig;
+use crate::error::CliError;
+use crate::units::parse_h256;
+use crate::{kitchensink, print, success, Program};
+
+/// Whitelist subcommands
+#[derive(clap::Subcommand, Debug)]
+pub enum Command {
+    WhitelistCall {
+        #[clap(value_parser = parse_h256)]
+        hash: H256,
+    },
+    DispatchWhitelistedCall {
+        #[clap(value_parser = parse_h256)]
+        hash: H256,
+        len: u32,
+    },
+}
+
+/// Run a whitelist subcommand
+pub async fn run<C: ChainConfig>(program: &Program<C>, command: Command) -> Result<()> {
+    match command {
+        Command::WhitelistCall { hash: call_hash } => {
+            program.require_pallet("Whitelist")?;
+            let call = kitchensink::Call::Whitelist(
+                kitchensink::runtime_types::pallet_whitelist::pallet::Call::whitelist_call {
+                    call_hash,
+                },
+            );
+
+            print!(program, "whitelisting call {call_hash}");
+            let events = program.sudo_and_watch(call).await?;
+            let whitelisted =
+                events.find_first::<kitchensink::whitelist::events::CallWhitelisted>()?;
+            success!(program, "call whitelisted {:?}", whitelisted);
+        }
+        Command::DispatchWhitelistedCall {
+            hash: call_hash,
+            len,
+        } => {
+            program.require_pallet("Whitelist")?;
+            let image = program
+                .fetch_preimage(call_hash, len)
+                .await?
+                .ok_or_else(|| anyhow::anyhow!("preimage not found"))?;
+            let runtime_call =
+                kitchensink::Call::decode(&mut &image[..]).map_err(|err| CliError::Decode {
+                    what: format!("the preimage {call_hash:?} as a call"),
+                    source: err.into(),
+                })?;
+
+            // the dispatch needs a weight witness, ask the runtime for the call weight
+            let query = kitchensink::apis()
+                .transaction_payment_call_api()
+                .query_call_info(runtime_call, len);
+            let info = program
+                .api
+                .runtime_api()
+                .at(program.snapshot().await?.hash)
+                .call(query)
+                .await?;
+
+            print!(
+                program,
+                "dispatching whitelisted call {call_hash} ({:?})", info.weight
+            );
+            let call = kitchensink::Call::Whitelist(
+                kitchensink::runtime_types::pallet_whitelist::pallet::Call::dispatch_whitelisted_call {
+                    call_hash,
+                    call_encoded_len: len,
+                    call_weight_witness: info.weight,
+                },
+            );
+            let events = program.sudo_and_watch(call).await?;
+            let dispatched =
+                events.find_first::<kitchensink::whitelist::events::WhitelistedCallDispatched>()?;
+            success!(program, "whitelisted call dispatched {:?}", dispatched);
+        }
+    }
+
+    Ok(())
+}
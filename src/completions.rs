@@ -0,0 +1,235 @@
+//! Shell completion scripts, generated from the command line definition with `clap_complete`.
+//!
+//! Referendum indices are completed dynamically with the hidden `complete-referenda` command,
+//! which lists the ongoing referenda when the chain can be reached. The generated scripts complete
+//! them as any other value, a hook per shell calls the command instead.
+use anyhow::Result;
+use clap::Command;
+
+/// Shells supported by the completion scripts
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl From<Shell> for clap_complete::Shell {
+    fn from(shell: Shell) -> Self {
+        match shell {
+            Shell::Bash => Self::Bash,
+            Shell::Zsh => Self::Zsh,
+            Shell::Fish => Self::Fish,
+        }
+    }
+}
+
+// Subcommand listing the ongoing referenda, for the dynamic completions
+const COMPLETE_REFERENDA: &str = "complete-referenda";
+
+// Positional argument holding a referendum index
+const INDEX: &str = "index";
+
+// A command of the tree, with its subcommands
+struct Node {
+    /// Subcommand names leading to the command, empty for the root command
+    path: Vec<String>,
+    /// Visible subcommands
+    subcommands: Vec<String>,
+    /// Whether a positional argument is a referendum index
+    takes_index: bool,
+}
+
+// Flatten the command tree
+fn nodes(command: &Command, path: Vec<String>, nodes: &mut Vec<Node>) {
+    let subcommands = command
+        .get_subcommands()
+        .filter(|sub| !sub.is_hide_set())
+        .collect::<Vec<_>>();
+
+    nodes.push(Node {
+        path: path.clone(),
+        subcommands: subcommands
+            .iter()
+            .map(|sub| sub.get_name().to_string())
+            .collect(),
+        takes_index: command.get_positionals().any(|arg| arg.get_id() == INDEX),
+    });
+
+    for sub in subcommands {
+        let mut path = path.clone();
+        path.push(sub.get_name().to_string());
+        self::nodes(sub, path, nodes);
+    }
+}
+
+/// Generate the completion script of a shell. Fails when the referendum indices can't be hooked
+/// into the script generated by `clap_complete`
+pub fn generate(shell: Shell, command: &Command) -> Result<String> {
+    let mut command = command.clone();
+    let bin = command.get_name().to_string();
+    let mut script = Vec::new();
+    clap_complete::generate(
+        clap_complete::Shell::from(shell),
+        &mut command,
+        &bin,
+        &mut script,
+    );
+    let script = String::from_utf8_lossy(&script).into_owned();
+
+    let mut tree = Vec::new();
+    nodes(&command, Vec::new(), &mut tree);
+    match shell {
+        Shell::Bash => Ok(format!("{script}{}", bash(&bin, &tree))),
+        Shell::Zsh => zsh(&bin, &script),
+        Shell::Fish => Ok(format!("{script}{}", fish(&bin, &tree))),
+    }
+}
+
+// Wrap the generated completion function, completing the indices once it returned
+fn bash(bin: &str, tree: &[Node]) -> String {
+    let function = format!("_{}_referenda", bin.replace('-', "_"));
+
+    // subcommand words, keyed by the path of their parent
+    let subcommands = tree
+        .iter()
+        .flat_map(|node| {
+            node.subcommands
+                .iter()
+                .map(|sub| format!("\"{}:{sub}\"", node.path.join(" ")))
+        })
+        .collect::<Vec<_>>()
+        .join("|");
+    let indexed = tree
+        .iter()
+        .filter(|node| node.takes_index)
+        .map(|node| format!("\"{}\"", node.path.join(" ")))
+        .collect::<Vec<_>>()
+        .join("|");
+
+    format!(
+        r#"
+{function}() {{
+    _{bin} "$@"
+    local cur="${{COMP_WORDS[COMP_CWORD]}}" prev="${{COMP_WORDS[COMP_CWORD-1]}}" path="" word
+    local globals=()
+    for word in "${{COMP_WORDS[@]:1:COMP_CWORD-1}}"; do
+        case "$path:$word" in
+            {subcommands}) path="${{path:+$path }}$word" ;;
+            *) [[ -z $path ]] && globals+=("$word") ;;
+        esac
+    done
+
+    case "$path" in
+        {indexed})
+            if [[ $cur != -* && $prev != -* ]]; then
+                COMPREPLY=($(compgen -W "$("${{COMP_WORDS[0]}}" "${{globals[@]}}" {COMPLETE_REFERENDA} 2>/dev/null)" -- "$cur"))
+            fi
+            ;;
+    esac
+}}
+complete -F {function} -o bashdefault -o default {bin}
+"#
+    )
+}
+
+// Complete the index positionals with a function listing the referenda, defined before the
+// script runs its completion function when autoloaded
+fn zsh(bin: &str, script: &str) -> Result<String> {
+    let function = format!("_{bin}_referenda");
+    let mut hooked = false;
+    let script = script
+        .lines()
+        .map(|line| match hook_index(line, &function) {
+            Some(line) => {
+                hooked = true;
+                line + "\n"
+            }
+            None => format!("{line}\n"),
+        })
+        .collect::<String>();
+    if !hooked {
+        anyhow::bail!(
+            "no `{INDEX}` argument in the zsh script generated by clap_complete, the referendum \
+             indices can't be completed"
+        );
+    }
+
+    let hook = format!(
+        r#"{function}() {{
+    local -a indices
+    indices=(${{(f)"$({bin} {COMPLETE_REFERENDA} 2>/dev/null)"}})
+    compadd -a indices
+}}
+
+"#
+    );
+    Ok(match script.rfind("if [ \"$funcstack[1]\"") {
+        Some(position) => format!("{}{hook}{}", &script[..position], &script[position..]),
+        None => format!("{script}\n{hook}"),
+    })
+}
+
+// Complete the index positional of an `_arguments` spec, `'<cardinality>:index<help>:' \`, with
+// the function, `None` for the other lines
+fn hook_index(line: &str, function: &str) -> Option<String> {
+    let spec = line.trim_start().strip_prefix('\'')?.strip_suffix("' \\")?;
+    // the positionals start with their cardinality, e.g. `:` or `*:`, unlike the subcommands
+    // described by their name
+    let (cardinality, rest) = spec.split_at(spec.find(INDEX)?);
+    if !cardinality.ends_with(':') || cardinality.chars().any(|c| c != ':' && c != '*') {
+        return None;
+    }
+    // the colons of the help are escaped, the action is empty without a value hint
+    let (name, action) = rest.rsplit_once(':')?;
+    if !action.is_empty() || (name != INDEX && !name.starts_with(&format!("{INDEX} -- "))) {
+        return None;
+    }
+
+    let indent = &line[..line.len() - line.trim_start().len()];
+    Some(format!("{indent}'{cardinality}{name}:{function}' \\"))
+}
+
+// Add the indices to the values completed after the commands taking one
+fn fish(bin: &str, tree: &[Node]) -> String {
+    let mut script = String::new();
+    for node in tree.iter().filter(|node| node.takes_index) {
+        let condition = node
+            .path
+            .iter()
+            .map(|word| format!("__fish_seen_subcommand_from {word}"))
+            .collect::<Vec<_>>()
+            .join("; and ");
+        script.push_str(&format!(
+            "complete -c {bin} -f -n '{condition}' -a '({bin} {COMPLETE_REFERENDA} 2>/dev/null)'\n"
+        ));
+    }
+    script
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zsh_hooks_the_index_positionals() {
+        let script = "_arguments \\\n\
+                      ':index:' \\\n\
+                      '::index -- Referendum index:' \\\n\
+                      'index:Index the governance events' \\\n\
+                      && ret=0\n\
+                      if [ \"$funcstack[1]\" = \"_cli\" ]; then\n";
+        let script = zsh("cli", script).unwrap();
+        assert!(script.contains("':index:_cli_referenda' \\"));
+        assert!(script.contains("'::index -- Referendum index:_cli_referenda' \\"));
+        assert!(script.contains("'index:Index the governance events' \\"));
+        let hook = script.find("_cli_referenda() {").unwrap();
+        assert!(hook < script.find("if [ \"$funcstack[1]\"").unwrap());
+    }
+
+    #[test]
+    fn zsh_fails_without_index_positional() {
+        let script = "_arguments \\\n'--url=[]:URL: ' \\\n'index:' \\\n";
+        assert!(zsh("cli", script).is_err());
+    }
+}
@@ -0,0 +1,104 @@
+//! Configuration file holding named connection profiles.
+//!
+//! The file is read from `$XDG_CONFIG_HOME/democracy-cli/config.toml`, falling back to
+//! `~/.config/democracy-cli/config.toml`:
+//!
+//! ```toml
+//! [profiles.default]
+//! url = "ws://127.0.0.1:9944"
+//! user = "alice"
+//!
+//! [profiles.rococo]
+//! chain = "rococo"
+//! urls = ["wss://rococo-rpc.polkadot.io", "wss://rococo-rpc.dwellir.com"]
+//! user = "bob"
+//! ss58_prefix = 42
+//! rps = 10
+//! audit_log = "/var/log/democracy-cli/rococo.jsonl"
+//! notify = ["notify-send", "democracy-cli"]
+//! ```
+//!
+//! The `[[auto_vote]]` tables hold the rules of the `auto-vote` daemon, see [`crate::auto_vote`].
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+use crate::auto_vote::Rule;
+use crate::chain::Chain;
+use crate::User;
+
+/// Profile used when `--profile` is not given
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// The parsed configuration file
+#[derive(Deserialize, Debug, Default)]
+pub struct Config {
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Profile>,
+    /// Rules of the `auto-vote` daemon, the first match decides
+    #[serde(default)]
+    pub auto_vote: Vec<Rule>,
+}
+
+/// Settings applied when a profile is selected, command line flags take precedence
+#[derive(Deserialize, Debug, Default, Clone)]
+pub struct Profile {
+    pub url: Option<String>,
+    /// Endpoints to fail over between, after `url`
+    #[serde(default)]
+    pub urls: Vec<String>,
+    pub chain: Option<Chain>,
+    pub user: Option<User>,
+    pub ss58_prefix: Option<u16>,
+    /// Maximum number of RPC requests per second
+    pub rps: Option<u32>,
+    /// JSONL file recording every submitted extrinsic
+    pub audit_log: Option<PathBuf>,
+    /// Command notifying the events of the daemons, run with the message as its last argument
+    #[serde(default)]
+    pub notify: Vec<String>,
+}
+
+impl Config {
+    /// Directory holding the configuration file and cached data
+    pub fn dir() -> Option<PathBuf> {
+        let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+        Some(config_dir.join("democracy-cli"))
+    }
+
+    /// Location of the configuration file
+    pub fn path() -> Option<PathBuf> {
+        Some(Self::dir()?.join("config.toml"))
+    }
+
+    /// Load the configuration file, an absent file is an empty configuration
+    pub fn load() -> Result<Self> {
+        let Some(path) = Self::path().filter(|path| path.exists()) else {
+            return Ok(Self::default());
+        };
+
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        toml::from_str(&content).with_context(|| format!("invalid config {}", path.display()))
+    }
+
+    /// Select a profile by name, or the default profile if it exists
+    pub fn profile(&self, name: Option<&str>) -> Result<Profile> {
+        match name {
+            Some(name) => self
+                .profiles
+                .get(name)
+                .cloned()
+                .with_context(|| format!("unknown profile {name}")),
+            None => Ok(self
+                .profiles
+                .get(DEFAULT_PROFILE)
+                .cloned()
+                .unwrap_or_default()),
+        }
+    }
+}
@@ -0,0 +1,444 @@
+//! Live dashboard of the governance state, redrawn on each finalized block.
+//!
+//! The panes show the ongoing referenda, the votes and locks of the signing account, the public
+//! proposal queue and a log of the latest governance events. The panes are drawn with ratatui on
+//! the alternate screen, redrawn when the terminal is resized, stop the dashboard with `q`.
+//!
+//! The last processed block is saved when the dashboard stops, the next run fills the event log
+//! with the events finalized since, if that was less than [`RESUME_WINDOW`] blocks ago.
+use std::collections::VecDeque;
+use std::io::Stdout;
+
+use anyhow::Result;
+use crossterm::event::{Event, EventStream, KeyCode, KeyEvent, KeyModifiers};
+use crossterm::terminal::{self, EnterAlternateScreen, LeaveAlternateScreen};
+use ratatui::backend::CrosstermBackend;
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Text;
+use ratatui::widgets::{Block, Borders, Paragraph, Row, Table};
+use ratatui::{Frame, Terminal};
+use subxt::ext::futures::StreamExt;
+
+use crate::bus::FinalizedEvents;
+use crate::chain::ChainConfig;
+use crate::history::governance_events;
+use crate::kitchensink;
+use crate::kitchensink::runtime_types::frame_support::traits::preimages::Bounded;
+use crate::kitchensink::runtime_types::pallet_conviction_voting::types::Tally;
+use crate::kitchensink::runtime_types::pallet_conviction_voting::vote::Voting;
+use crate::kitchensink::runtime_types::pallet_democracy::types::ReferendumInfo;
+use crate::kitchensink::runtime_types::pallet_democracy::vote::Voting as DemocracyVoting;
+use crate::{
+    checkpoint, print, referendum_phase, shutdown, storage_key_class, storage_key_index,
+    Governance, Program,
+};
+
+/// Maximum number of finalized blocks to go through when resuming from the last run
+pub const RESUME_WINDOW: u32 = 600;
+
+// Name of the checkpoint of the dashboard
+const CHECKPOINT: &str = "dashboard";
+
+// Number of events kept in the event log pane
+const EVENT_LOG_LEN: usize = 10;
+
+/// Pallets whose events are shown in the event log pane and by `history`
+pub const GOVERNANCE_PALLETS: &[&str] = &[
+    "Democracy",
+    "Referenda",
+    "ConvictionVoting",
+    "Preimage",
+    "Whitelist",
+    "Scheduler",
+];
+
+// Rows of a pane, with the column names of its header
+struct Pane {
+    header: Vec<&'static str>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Pane {
+    fn new(header: &[&'static str]) -> Self {
+        Self {
+            header: header.to_vec(),
+            rows: Vec::new(),
+        }
+    }
+
+    // A pane holding a single line of text
+    fn message(text: impl Into<String>) -> Self {
+        Self {
+            header: Vec::new(),
+            rows: vec![vec![text.into()]],
+        }
+    }
+
+    fn add_row(&mut self, row: Vec<String>) {
+        self.rows.push(row);
+    }
+}
+
+// Content of the screen at a finalized block
+struct Screen {
+    title: String,
+    referenda: Pane,
+    votes: Pane,
+    proposals: Pane,
+    events: Pane,
+}
+
+// The terminal in raw mode on the alternate screen, restored when dropped, on errors and panics
+// included
+struct Tui {
+    terminal: Terminal<CrosstermBackend<Stdout>>,
+}
+
+impl Tui {
+    fn enter() -> Result<Self> {
+        let terminal = Terminal::new(CrosstermBackend::new(std::io::stdout()))?;
+        let mut tui = Self { terminal };
+        terminal::enable_raw_mode()?;
+        crossterm::execute!(tui.terminal.backend_mut(), EnterAlternateScreen)?;
+        tui.terminal.hide_cursor()?;
+        tui.terminal.clear()?;
+        Ok(tui)
+    }
+
+    fn draw(&mut self, screen: &Screen) -> Result<()> {
+        self.terminal.draw(|frame| draw(frame, screen))?;
+        Ok(())
+    }
+}
+
+impl Drop for Tui {
+    fn drop(&mut self) {
+        let _ = terminal::disable_raw_mode();
+        let _ = crossterm::execute!(self.terminal.backend_mut(), LeaveAlternateScreen);
+        let _ = self.terminal.show_cursor();
+    }
+}
+
+/// Run the dashboard until `q`, escape or ctrl-c is pressed
+pub async fn run<C: ChainConfig>(program: &Program<C>) -> Result<()> {
+    if !program.output.is_text() {
+        anyhow::bail!("the dashboard only supports the text output");
+    }
+
+    let genesis_hash = program.api.genesis_hash();
+    let now = program.snapshot().await?.number;
+    let resume = checkpoint::load(CHECKPOINT, genesis_hash)?
+        .filter(|block| *block < now && now - block <= RESUME_WINDOW);
+    let mut blocks = match resume {
+        Some(block) => {
+            print!(program, "resuming after block {block}");
+            program.finalized_blocks_after(block).boxed()
+        }
+        None => program.finalized_blocks().boxed(),
+    };
+
+    let last = {
+        let mut tui = Tui::enter()?;
+        let mut keys = EventStream::new();
+        let mut events = VecDeque::new();
+        let mut screen = None;
+        let mut last = None;
+        loop {
+            // only the cancel safe waits race, a block is processed to the end once received
+            let block = tokio::select! {
+                biased;
+                () = shutdown::requested() => break,
+                event = keys.next() => {
+                    match event.transpose()? {
+                        Some(Event::Key(key)) if is_quit(&key) => break,
+                        Some(Event::Resize(..)) => {
+                            if let Some(screen) = &screen {
+                                tui.draw(screen)?;
+                            }
+                        }
+                        Some(_) => {}
+                        None => break,
+                    }
+                    continue;
+                }
+                block = blocks.next() => block,
+            };
+            let Some(block) = block else {
+                break;
+            };
+            let block = block?;
+            last = Some(block.number);
+            log_events(&block, &mut events);
+            if block.number < now {
+                continue;
+            }
+
+            let current = next_screen(program, &block, &events).await?;
+            tui.draw(&current)?;
+            screen = Some(current);
+        }
+        last
+    };
+
+    if let Some(block) = last {
+        checkpoint::save(CHECKPOINT, genesis_hash, block)?;
+        print!(program, "stopped after block {block}");
+    }
+    Ok(())
+}
+
+// Whether a key stops the dashboard, ctrl-c is a key press in raw mode
+fn is_quit(key: &KeyEvent) -> bool {
+    match key.code {
+        KeyCode::Char('q') | KeyCode::Esc => true,
+        KeyCode::Char('c') => key.modifiers.contains(KeyModifiers::CONTROL),
+        _ => false,
+    }
+}
+
+// Add the governance events of a block to the front of the event log
+fn log_events<C: ChainConfig>(block: &FinalizedEvents<C>, events: &mut VecDeque<String>) {
+    for event in governance_events(block.number, &block.events) {
+        events.push_front(format!(
+            "#{} {}::{}",
+            block.number,
+            event.pallet_name(),
+            event.variant_name()
+        ));
+    }
+    events.truncate(EVENT_LOG_LEN);
+}
+
+// Read the content of the panes at a finalized block
+async fn next_screen<C: ChainConfig>(
+    program: &Program<C>,
+    block: &FinalizedEvents<C>,
+    events: &VecDeque<String>,
+) -> Result<Screen> {
+    program.unpin();
+    let mut log = Pane::new(&["event"]);
+    for event in events {
+        log.add_row(vec![event.clone()]);
+    }
+    Ok(Screen {
+        title: format!(
+            "[{}] block #{} ({:?}), q to quit",
+            program.user, block.number, block.hash
+        ),
+        referenda: referenda(program).await?,
+        votes: votes(program).await?,
+        proposals: proposals(program).await?,
+        events: log,
+    })
+}
+
+// Lay the panes out in a grid below the title line
+fn draw(frame: &mut Frame, screen: &Screen) {
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(1),
+            Constraint::Percentage(50),
+            Constraint::Percentage(50),
+        ])
+        .split(frame.size());
+    let columns = |area| {
+        Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+            .split(area)
+    };
+    let (top, bottom) = (columns(rows[1]), columns(rows[2]));
+
+    frame.render_widget(
+        Paragraph::new(screen.title.as_str()).style(Style::default().add_modifier(Modifier::BOLD)),
+        rows[0],
+    );
+    draw_pane(frame, top[0], "ongoing referenda", &screen.referenda);
+    draw_pane(frame, top[1], "my votes and locks", &screen.votes);
+    draw_pane(frame, bottom[0], "public proposals", &screen.proposals);
+    draw_pane(frame, bottom[1], "events", &screen.events);
+}
+
+// Draw a pane as a bordered table, its columns as wide as their widest cell
+fn draw_pane(frame: &mut Frame, area: Rect, title: &str, pane: &Pane) {
+    let block = Block::default().borders(Borders::ALL).title(title);
+    if pane.rows.is_empty() {
+        frame.render_widget(Paragraph::new("none").block(block), area);
+        return;
+    }
+
+    let mut widths = pane
+        .header
+        .iter()
+        .map(|name| Text::from(*name).width())
+        .collect::<Vec<_>>();
+    for row in &pane.rows {
+        widths.resize(widths.len().max(row.len()), 0);
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(Text::from(cell.as_str()).width());
+        }
+    }
+    let widths = widths
+        .into_iter()
+        .map(|width| Constraint::Length(width as u16))
+        .collect::<Vec<_>>();
+
+    let rows = pane
+        .rows
+        .iter()
+        .map(|row| Row::new(row.iter().map(String::as_str)));
+    let mut table = Table::new(rows).widths(&widths).block(block);
+    if !pane.header.is_empty() {
+        table = table.header(
+            Row::new(pane.header.iter().copied())
+                .style(Style::default().add_modifier(Modifier::BOLD)),
+        );
+    }
+    frame.render_widget(table, area);
+}
+
+// The ongoing referenda of the detected governance version
+async fn referenda<C: ChainConfig>(program: &Program<C>) -> Result<Pane> {
+    match program.governance {
+        Some(Governance::OpenGov) => {
+            let mut pane = Pane::new(&["track", "index", "phase", "ayes", "nays", "support"]);
+            for (track, referenda) in program.ongoing_referenda().await? {
+                let name = program.track(track)?.name;
+                for (index, status) in referenda {
+                    let Tally {
+                        ayes,
+                        nays,
+                        support,
+                    } = status.tally;
+                    pane.add_row(vec![
+                        format!("{track} ({name})"),
+                        format!("#{index}"),
+                        referendum_phase(&status).to_string(),
+                        program.balance(ayes),
+                        program.balance(nays),
+                        program.balance(support),
+                    ]);
+                }
+            }
+            Ok(pane)
+        }
+        Some(Governance::Democracy) => {
+            let query = kitchensink::storage().democracy().referendum_info_of_iter();
+            let now = program.snapshot().await?.number;
+            let mut pane = Pane::new(&["index", "end", "threshold", "ayes", "nays", "turnout"]);
+            for (key, info) in program.iter_storage(&query).await? {
+                if let ReferendumInfo::Ongoing(status) = info {
+                    pane.add_row(vec![
+                        format!("#{}", storage_key_index(&key)),
+                        program.describe_block(status.end, now),
+                        format!("{:?}", status.threshold),
+                        program.balance(status.tally.ayes),
+                        program.balance(status.tally.nays),
+                        program.balance(status.tally.turnout),
+                    ]);
+                }
+            }
+            Ok(pane)
+        }
+        None => Ok(Pane::message("no governance pallet on this chain")),
+    }
+}
+
+// The votes and voting locks of the signing account
+async fn votes<C: ChainConfig>(program: &Program<C>) -> Result<Pane> {
+    let account = program.account_id();
+    let api = program.storage().await?;
+
+    match program.governance {
+        Some(Governance::OpenGov) => {
+            let query = kitchensink::storage()
+                .conviction_voting()
+                .class_locks_for(&account);
+            let locks = api.fetch_or_default(&query).await?.0;
+
+            let query = kitchensink::storage()
+                .conviction_voting()
+                .voting_for_iter1(&account);
+
+            let mut pane = Pane::new(&["class", "lock", "votes"]);
+            for (key, voting) in program.iter_storage(&query).await? {
+                let class = storage_key_class(&key);
+                let lock = locks
+                    .iter()
+                    .find_map(|(id, amount)| (*id == class).then_some(*amount))
+                    .unwrap_or_default();
+                let votes = match voting {
+                    Voting::Casting(casting) => casting
+                        .votes
+                        .0
+                        .iter()
+                        .map(|(index, _)| format!("#{index}"))
+                        .collect::<Vec<_>>()
+                        .join(" "),
+                    Voting::Delegating(delegating) => {
+                        format!("delegating to {}", program.address(&delegating.target))
+                    }
+                    Voting::__Ignore(_) => continue,
+                };
+                pane.add_row(vec![class.to_string(), program.balance(lock), votes]);
+            }
+            Ok(pane)
+        }
+        Some(Governance::Democracy) => {
+            let query = kitchensink::storage().democracy().voting_of(&account);
+            let mut pane = Pane::new(&["referendum", "vote"]);
+            match api.fetch(&query).await? {
+                Some(DemocracyVoting::Direct { votes, .. }) => {
+                    for (index, vote) in votes.0 {
+                        pane.add_row(vec![format!("#{index}"), format!("{vote:?}")]);
+                    }
+                }
+                Some(DemocracyVoting::Delegating {
+                    target, balance, ..
+                }) => {
+                    return Ok(Pane::message(format!(
+                        "delegating {} to {}",
+                        program.balance(balance),
+                        program.address(&target)
+                    )));
+                }
+                None => {}
+            }
+            Ok(pane)
+        }
+        None => Ok(Pane::new(&[])),
+    }
+}
+
+// The public proposals queued by the democracy pallet
+async fn proposals<C: ChainConfig>(program: &Program<C>) -> Result<Pane> {
+    if program.api.metadata().pallet_by_name("Democracy").is_none() {
+        return Ok(Pane::message("no public proposal queue on this chain"));
+    }
+
+    let api = program.storage().await?;
+    let query = kitchensink::storage().democracy().public_props();
+    let proposals = api.fetch_or_default(&query).await?.0;
+
+    let mut pane = Pane::new(&["index", "proposal", "proposer"]);
+    for (index, proposal, proposer) in proposals {
+        pane.add_row(vec![
+            format!("#{index}"),
+            describe_bounded(&proposal),
+            program.address(&proposer),
+        ]);
+    }
+    Ok(pane)
+}
+
+// Describe a bounded call by its hash, without printing inline call data
+fn describe_bounded<C, H>(bounded: &Bounded<C, H>) -> String {
+    match bounded {
+        Bounded::Legacy { hash } => format!("{hash:?} (legacy)"),
+        Bounded::Inline(call) => format!("inline call of {} bytes", call.0.len()),
+        Bounded::Lookup { hash, len } => format!("{hash:?} ({len} bytes)"),
+        Bounded::__Ignore(_) => "unknown".to_string(),
+    }
+}
@@ -0,0 +1,92 @@
+//! Democracy and preimage calls and storage queries built with subxt's dynamic API.
+//!
+//! These are resolved against the metadata of the connected node rather than the bundled
+//! `metadata.scale`, so they keep working across runtime upgrades and on other chains.
+use subxt::dynamic::Value;
+use subxt::ext::scale_value::Composite;
+use subxt::storage::DynamicAddress;
+use subxt::tx::DynamicPayload;
+use subxt::utils::{AccountId32, H256};
+
+use crate::units::Conviction;
+
+/// `Preimage::note_preimage`
+pub fn note_preimage(image: Vec<u8>) -> DynamicPayload {
+    subxt::dynamic::tx(
+        "Preimage",
+        "note_preimage",
+        Composite::named([("bytes".to_string(), Value::from_bytes(image))]),
+    )
+}
+
+/// `Preimage::unnote_preimage`
+pub fn unnote_preimage(hash: H256) -> DynamicPayload {
+    subxt::dynamic::tx(
+        "Preimage",
+        "unnote_preimage",
+        Composite::named([("hash".to_string(), Value::from_bytes(hash))]),
+    )
+}
+
+/// `System::remark`, as an encodable call value
+pub fn remark(remark: Vec<u8>) -> DynamicPayload {
+    subxt::dynamic::tx(
+        "System",
+        "remark",
+        Composite::named([("remark".to_string(), Value::from_bytes(remark))]),
+    )
+}
+
+/// `Democracy::propose` for a noted preimage
+pub fn propose(hash: H256, len: u32, value: u128) -> DynamicPayload {
+    let proposal = Value::named_variant(
+        "Lookup",
+        [
+            ("hash".to_string(), Value::from_bytes(hash)),
+            ("len".to_string(), Value::u128(len as u128)),
+        ],
+    );
+
+    subxt::dynamic::tx(
+        "Democracy",
+        "propose",
+        Composite::named([
+            ("proposal".to_string(), proposal),
+            ("value".to_string(), Value::u128(value)),
+        ]),
+    )
+}
+
+/// `Democracy::vote` with a standard vote
+pub fn vote(ref_index: u32, aye: bool, conviction: Conviction, balance: u128) -> DynamicPayload {
+    let vote = conviction.vote(aye);
+    let vote = Value::named_variant(
+        "Standard",
+        [
+            (
+                "vote".to_string(),
+                Value::unnamed_composite([Value::u128(vote as u128)]),
+            ),
+            ("balance".to_string(), Value::u128(balance)),
+        ],
+    );
+
+    subxt::dynamic::tx(
+        "Democracy",
+        "vote",
+        Composite::named([
+            ("ref_index".to_string(), Value::u128(ref_index as u128)),
+            ("vote".to_string(), vote),
+        ]),
+    )
+}
+
+/// `System::Account` of the given account
+pub fn account(account: &AccountId32) -> DynamicAddress<Value> {
+    subxt::dynamic::storage("System", "Account", vec![Value::from_bytes(account)])
+}
+
+/// All the entries of `Democracy::ReferendumInfoOf`
+pub fn referendum_info_of() -> DynamicAddress<Value> {
+    subxt::dynamic::storage("Democracy", "ReferendumInfoOf", Vec::<Value>::new())
+}
@@ -0,0 +1,145 @@
+//! Errors of the commands that the user can act upon, each mapped to an exit code by
+//! [`crate::exit::code`].
+//!
+//! They are raised where the failure occurs and travel through `anyhow`, errors that aren't a
+//! [`CliError`] are classified from their causes.
+use subxt::error::{DispatchError, MetadataError};
+
+/// Failure of a command
+#[derive(Debug, thiserror::Error)]
+pub enum CliError {
+    /// The chain couldn't be reached, or the connection dropped
+    #[error("failed to connect to the chain")]
+    Connection(#[source] anyhow::Error),
+
+    /// Bytes from the chain or the user couldn't be decoded
+    #[error("failed to decode {what}")]
+    Decode {
+        what: String,
+        #[source]
+        source: anyhow::Error,
+    },
+
+    /// The extrinsic was included but its call failed with an error of a pallet
+    #[error("transaction failed with {pallet}::{error}{}", docs_suffix(.docs))]
+    PalletError {
+        pallet: String,
+        error: String,
+        docs: String,
+    },
+
+    /// The transaction was rejected by the node or failed on-chain for another reason
+    #[error("transaction failed")]
+    Transaction(#[source] anyhow::Error),
+
+    /// An event that a successful transaction emits is missing from its events
+    #[error("the transaction didn't emit the {0} event")]
+    MissingEvent(String),
+
+    /// An awaited event didn't occur before `--timeout`
+    #[error("no {event} event after {secs}s")]
+    Timeout { event: String, secs: u64 },
+
+    /// The command was stopped by ctrl-c before completing
+    #[error("interrupted")]
+    Interrupted,
+
+    /// The free balance of the signer doesn't cover a transaction, checked before submitting it
+    #[error("insufficient balance: {0}, pass --force to submit anyway")]
+    InsufficientBalance(String),
+
+    /// The statically generated types don't match the connected chain, e.g. a node that doesn't
+    /// run the kitchensink runtime
+    #[error(
+        "{cause}: the chain doesn't match the compiled runtime, {}regenerate metadata.scale with \
+         `subxt metadata --url <node>` and rebuild",
+        dynamic_hint(*.dynamic)
+    )]
+    MetadataMismatch { cause: String, dynamic: bool },
+
+    /// Invalid command line argument or input value
+    #[error("invalid {what}")]
+    Validation {
+        what: String,
+        #[source]
+        source: anyhow::Error,
+    },
+}
+
+impl CliError {
+    /// Exit code of the error, 2 matches the code of the command line usage errors
+    pub fn code(&self) -> i32 {
+        match self {
+            CliError::Validation { .. }
+            | CliError::Decode { .. }
+            | CliError::InsufficientBalance(_) => 2,
+            CliError::MetadataMismatch { .. } => 1,
+            CliError::Connection(_) => 3,
+            CliError::PalletError { .. } | CliError::Transaction(_) | CliError::MissingEvent(_) => {
+                4
+            }
+            CliError::Timeout { .. } => 5,
+            CliError::Interrupted => 130,
+        }
+    }
+
+    /// Error of a failed dispatch, naming the pallet error when there is one
+    pub fn dispatch(error: DispatchError) -> Self {
+        if let DispatchError::Module(module) = &error {
+            if let Ok(details) = module.details() {
+                return CliError::PalletError {
+                    pallet: details.pallet.name().to_string(),
+                    error: details.variant.name.clone(),
+                    docs: details.variant.docs.join(" "),
+                };
+            }
+        }
+        CliError::Transaction(subxt::Error::from(error).into())
+    }
+}
+
+/// Replace an error caused by the static types not matching the chain metadata with a
+/// [`CliError::MetadataMismatch`], keeping the subxt error on one line rather than its causes.
+/// `dynamic` tells whether the command already ran with `--dynamic`
+pub fn metadata_mismatch(err: anyhow::Error, dynamic: bool) -> anyhow::Error {
+    if err.chain().any(|cause| cause.is::<CliError>()) {
+        return err;
+    }
+
+    let mismatch = err.chain().find_map(|cause| match cause.downcast_ref()? {
+        subxt::Error::Decode(_)
+        | subxt::Error::Metadata(
+            MetadataError::IncompatibleCodegen
+            | MetadataError::TypeNotFound(_)
+            | MetadataError::CallNameNotFound(_)
+            | MetadataError::StorageEntryNotFound(_)
+            | MetadataError::ConstantNameNotFound(_),
+        ) => Some(cause.to_string()),
+        _ => None,
+    });
+    match mismatch {
+        Some(cause) => {
+            tracing::debug!("{err:?}");
+            CliError::MetadataMismatch { cause, dynamic }.into()
+        }
+        None => err,
+    }
+}
+
+// Suggest `--dynamic` to a command that didn't use it
+fn dynamic_hint(dynamic: bool) -> &'static str {
+    if dynamic {
+        ""
+    } else {
+        "pass --dynamic to use the node metadata, or "
+    }
+}
+
+// The docs of a pallet error explain its cause, they follow its name
+fn docs_suffix(docs: &str) -> String {
+    if docs.is_empty() {
+        String::new()
+    } else {
+        format!(": {docs}")
+    }
+}
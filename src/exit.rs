@@ -0,0 +1,44 @@
+//! Exit codes of the process by failure class, so shell scripts and CI can branch on the outcome
+//! instead of parsing the output.
+//!
+//! The errors raised by the commands are [`CliError`]s carrying their exit code, other errors are
+//! classified from their causes.
+use subxt::error::RpcError;
+
+use crate::error::CliError;
+
+/// Exit codes, as printed by `--help`
+pub const HELP: &str = "\
+Exit codes:
+  0    success
+  1    other errors
+  2    invalid arguments or input
+  3    failed to connect to the chain
+  4    transaction rejected or failed on-chain
+  5    timed out waiting for an event
+  130  interrupted by ctrl-c";
+
+/// Exit code of an error, from the outermost [`CliError`] or else from its causes
+pub fn code(err: &anyhow::Error) -> i32 {
+    if let Some(err) = err
+        .chain()
+        .find_map(|cause| cause.downcast_ref::<CliError>())
+    {
+        return err.code();
+    }
+
+    for cause in err.chain() {
+        match cause.downcast_ref::<subxt::Error>() {
+            Some(subxt::Error::Rpc(RpcError::ClientError(_)) | subxt::Error::Io(_)) => return 3,
+            Some(subxt::Error::Runtime(_)) => return 4,
+            _ => {}
+        }
+        if cause.is::<hex::FromHexError>()
+            || cause.is::<std::num::ParseIntError>()
+            || cause.is::<codec::Error>()
+        {
+            return 2;
+        }
+    }
+    1
+}
@@ -0,0 +1,231 @@
+//! RPC client spread over several endpoints.
+//!
+//! Every endpoint is pinged on connect and the healthiest one, the reachable and synced node with
+//! the lowest latency, is used. When a request fails and the endpoint doesn't answer a health check
+//! anymore, the client switches to the next healthiest endpoint and retries. Block subscriptions
+//! are resubscribed on the new endpoint, so long-running watches survive an outage.
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use serde::Deserialize;
+use subxt::backend::rpc::{
+    rpc_params, RawRpcFuture, RawRpcSubscription, RawValue, RpcClient, RpcClientT,
+};
+use subxt::error::RpcError;
+use subxt::ext::futures::{future, stream, StreamExt};
+
+use crate::ipc;
+use crate::output;
+use crate::transport::Transport;
+
+/// Connect to the healthiest of the given endpoints
+pub async fn connect(urls: &[String], transport: Transport) -> Result<RpcClient> {
+    let current = healthiest(urls, &transport, None).await?;
+    let rpc = FailoverRpc(Arc::new(Inner {
+        urls: urls.to_vec(),
+        transport,
+        current: RwLock::new(current),
+    }));
+
+    Ok(RpcClient::new(rpc))
+}
+
+// Response of the `system_health` RPC method
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Health {
+    is_syncing: bool,
+}
+
+// Check that a node answers and is synced
+async fn check_health(client: &RpcClient) -> Result<()> {
+    let health: Health = client.request("system_health", rpc_params![]).await?;
+    if health.is_syncing {
+        anyhow::bail!("node is syncing");
+    }
+    Ok(())
+}
+
+// Open a client for a websocket or IPC url
+async fn open(url: &str, transport: &Transport) -> Result<RpcClient> {
+    match ipc::socket_path(url) {
+        Some(path) => ipc::connect(path).await,
+        None if transport.is_default() => Ok(RpcClient::from_url(url).await?),
+        None => transport.connect(url).await,
+    }
+}
+
+// Connect to an endpoint, returning the client and its round-trip latency when healthy
+async fn ping(url: &str, transport: &Transport) -> Result<(RpcClient, Duration)> {
+    let start = Instant::now();
+    let client = open(url, transport).await?;
+    check_health(&client).await?;
+    Ok((client, start.elapsed()))
+}
+
+// Ping the endpoints, except `skip`, and connect to the one with the lowest latency
+async fn healthiest(
+    urls: &[String],
+    transport: &Transport,
+    skip: Option<usize>,
+) -> Result<(usize, RpcClient)> {
+    let pings = future::join_all(
+        urls.iter()
+            .enumerate()
+            .filter(|(index, _)| Some(*index) != skip)
+            .map(|(index, url)| async move { (index, ping(url, transport).await) }),
+    )
+    .await;
+
+    let mut best: Option<(usize, RpcClient, Duration)> = None;
+    for (index, result) in pings {
+        match result {
+            Ok((client, latency)) => {
+                tracing::debug!(url = urls[index], ?latency, "endpoint healthy");
+                if best.as_ref().is_none_or(|(_, _, best)| latency < *best) {
+                    best = Some((index, client, latency));
+                }
+            }
+            Err(err) => output::warn(&format!("failed to connect to {}: {err}", urls[index])),
+        }
+    }
+
+    let (index, client, latency) =
+        best.ok_or_else(|| anyhow::anyhow!("no healthy endpoint among {urls:?}"))?;
+    if urls.len() > 1 {
+        tracing::info!(url = urls[index], ?latency, "using the healthiest endpoint");
+    }
+    Ok((index, client))
+}
+
+// RPC client switching endpoints when the current one goes down
+#[derive(Clone)]
+struct FailoverRpc(Arc<Inner>);
+
+struct Inner {
+    urls: Vec<String>,
+    transport: Transport,
+    current: RwLock<(usize, RpcClient)>,
+}
+
+impl FailoverRpc {
+    // The endpoint in use, with its index
+    fn current(&self) -> (usize, RpcClient) {
+        self.0.current.read().unwrap().clone()
+    }
+
+    // Switch away from the endpoint `index` if it is down, returning whether a healthy endpoint
+    // is now in use
+    async fn fail_over(&self, index: usize) -> bool {
+        let (current, client) = self.current();
+        if current != index {
+            // another request already switched endpoint
+            return true;
+        }
+        if check_health(&client).await.is_ok() {
+            return false;
+        }
+
+        tracing::info!(url = self.0.urls[index], "endpoint down, failing over");
+        match healthiest(&self.0.urls, &self.0.transport, Some(index)).await {
+            Ok(healthy) => {
+                *self.0.current.write().unwrap() = healthy;
+                true
+            }
+            Err(err) => {
+                output::warn(&format!("failover failed: {err:#}"));
+                false
+            }
+        }
+    }
+
+    // Keep a subscription alive across endpoint switches by resubscribing on the new endpoint
+    fn resubscribing(
+        &self,
+        subscription: RawRpcSubscription,
+        sub: &str,
+        params: Option<Box<RawValue>>,
+        unsub: &str,
+    ) -> RawRpcSubscription {
+        let state = (
+            self.clone(),
+            subscription.stream,
+            sub.to_string(),
+            params,
+            unsub.to_string(),
+        );
+
+        let stream = stream::unfold(state, |(rpc, mut stream, sub, params, unsub)| async move {
+            loop {
+                let (index, _) = rpc.current();
+                match stream.next().await {
+                    Some(Ok(item)) => return Some((Ok(item), (rpc, stream, sub, params, unsub))),
+                    Some(Err(err)) if !rpc.fail_over(index).await => {
+                        return Some((Err(err), (rpc, stream, sub, params, unsub)))
+                    }
+                    None if !rpc.fail_over(index).await => return None,
+                    _ => {}
+                }
+
+                let (index, client) = rpc.current();
+                tracing::debug!(url = rpc.0.urls[index], sub, "resubscribing");
+                match client.subscribe_raw(&sub, params.clone(), &unsub).await {
+                    Ok(subscription) => stream = subscription.stream,
+                    Err(err) => {
+                        let stream = stream::empty().boxed();
+                        return Some((Err(err), (rpc, stream, sub, params, unsub)));
+                    }
+                }
+            }
+        });
+
+        RawRpcSubscription {
+            stream: stream.boxed(),
+            id: subscription.id,
+        }
+    }
+}
+
+impl RpcClientT for FailoverRpc {
+    fn request_raw<'a>(
+        &'a self,
+        method: &'a str,
+        params: Option<Box<RawValue>>,
+    ) -> RawRpcFuture<'a, Box<RawValue>> {
+        Box::pin(async move {
+            let (index, client) = self.current();
+            match client.request_raw(method, params.clone()).await {
+                Err(RpcError::ClientError(_)) if self.fail_over(index).await => {
+                    let (_, client) = self.current();
+                    client.request_raw(method, params).await
+                }
+                result => result,
+            }
+        })
+    }
+
+    fn subscribe_raw<'a>(
+        &'a self,
+        sub: &'a str,
+        params: Option<Box<RawValue>>,
+        unsub: &'a str,
+    ) -> RawRpcFuture<'a, RawRpcSubscription> {
+        Box::pin(async move {
+            let (index, client) = self.current();
+            let subscription = match client.subscribe_raw(sub, params.clone(), unsub).await {
+                Err(RpcError::ClientError(_)) if self.fail_over(index).await => {
+                    let (_, client) = self.current();
+                    client.subscribe_raw(sub, params.clone(), unsub).await?
+                }
+                result => result?,
+            };
+
+            // only block subscriptions can be safely replayed on another endpoint
+            if !sub.starts_with("chain_") {
+                return Ok(subscription);
+            }
+            Ok(self.resubscribing(subscription, sub, params, unsub))
+        })
+    }
+}
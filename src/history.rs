@@ -0,0 +1,111 @@
+//! Governance events of a range of finalized blocks. The events of [`CONCURRENCY`] blocks are
+//! fetched at a time, and printed in block order as they arrive.
+use std::ops::RangeInclusive;
+
+use anyhow::Result;
+use serde_json::json;
+use subxt::backend::legacy::LegacyRpcMethods;
+use subxt::events::{EventDetails, Events};
+use subxt::ext::futures::{stream, Stream, StreamExt};
+
+use crate::chain::ChainConfig;
+use crate::dashboard::GOVERNANCE_PALLETS;
+use crate::output::{self, Spinner};
+use crate::{shutdown, Program};
+
+/// Number of blocks whose events are fetched concurrently
+pub const CONCURRENCY: usize = 16;
+
+/// Print the governance events of the blocks `from..=to`, up to the latest finalized block when
+/// `to` isn't given
+pub async fn run<C: ChainConfig>(program: &Program<C>, from: u32, to: Option<u32>) -> Result<()> {
+    let latest = program.snapshot().await?.number;
+    let to = to.unwrap_or(latest);
+    if from > to {
+        anyhow::bail!("block {from} is after block {to}");
+    }
+    if to > latest {
+        anyhow::bail!("block {to} is not finalized yet, the latest is {latest}");
+    }
+
+    let mut blocks = block_events(program, from..=to);
+
+    let spinner = Spinner::new(&format!("scanning blocks {from} to {to}"));
+    let mut count = 0;
+    loop {
+        let block = tokio::select! {
+            biased;
+            () = shutdown::requested() => break,
+            block = blocks.next() => match block {
+                Some(block) => block?,
+                None => break,
+            },
+        };
+
+        let (number, events) = block;
+        spinner.set_block(number);
+        for event in governance_events(number, &events) {
+            count += 1;
+            let fields = output::event_fields(&event)?;
+            program.output.record(
+                "event",
+                json!({ "block": number, "event": fields }),
+                format!(
+                    "#{number} {}::{} {}",
+                    event.pallet_name(),
+                    event.variant_name(),
+                    fields["fields"]
+                ),
+            );
+        }
+    }
+    let elapsed = spinner.finish_with_message(&format!(
+        "scanned blocks {from} to {to}, {count} governance events"
+    ));
+
+    tracing::debug!(count, ?elapsed, "scanned blocks {from} to {to}");
+    Ok(())
+}
+
+/// The governance events of a block, those of [`GOVERNANCE_PALLETS`]. The events after one that
+/// doesn't decode with the metadata, e.g. in a block of an older runtime, are skipped with a
+/// warning
+pub fn governance_events<C: ChainConfig>(
+    number: u32,
+    events: &Events<C>,
+) -> impl Iterator<Item = EventDetails<C>> + '_ {
+    events
+        .iter()
+        // the events are concatenated, the next one can't be found after a failure
+        .map_while(move |event| match event {
+            Ok(event) => Some(event),
+            Err(err) => {
+                tracing::warn!(
+                    "skipping the events of block {number} from the one failing to decode: {err}"
+                );
+                None
+            }
+        })
+        .filter(|event| GOVERNANCE_PALLETS.contains(&event.pallet_name()))
+}
+
+/// Stream the events of a range of finalized blocks, in block order, the events of
+/// [`CONCURRENCY`] blocks being fetched at a time
+pub fn block_events<C: ChainConfig>(
+    program: &Program<C>,
+    blocks: RangeInclusive<u32>,
+) -> impl Stream<Item = Result<(u32, Events<C>)>> + '_ {
+    let rpc = LegacyRpcMethods::<C>::new(program.rpc.clone());
+    stream::iter(blocks)
+        .map(move |number| {
+            let api = program.api.clone();
+            let rpc = rpc.clone();
+            async move {
+                let Some(hash) = rpc.chain_get_block_hash(Some(number.into())).await? else {
+                    anyhow::bail!("block {number} not found");
+                };
+                Ok((number, api.events().at(hash).await?))
+            }
+        })
+        .buffered(CONCURRENCY)
+}
@@ -0,0 +1,230 @@
+//! Incremental indexer of the governance events into the local cache, built with the `cache`
+//! feature, see [`crate::cache`].
+//!
+//! `index` walks the finalized blocks from genesis, or from the block after its checkpoint, and
+//! saves the public proposals, the referenda with their outcome and the votes it finds in their
+//! events. Once caught up, it follows the new finalized blocks until interrupted, and reads the
+//! cached storage maps again at each block with governance events, as the conviction voting
+//! pallet emits no event for the votes.
+//!
+//! The events are decoded with the metadata of the running runtime, the events of older runtimes
+//! it can't decode are skipped. Walking the blocks older than the pruning window of the node
+//! requires an archive node.
+use anyhow::{Context, Result};
+use subxt::events::{EventDetails, Events, StaticEvent};
+use subxt::ext::futures::{Stream, StreamExt};
+
+use crate::cache::{Cache, Record};
+use crate::chain::ChainConfig;
+use crate::history::{block_events, governance_events};
+use crate::kitchensink::{democracy, referenda};
+use crate::output::Spinner;
+use crate::{checkpoint, kitchensink, print, shutdown, success, Governance, Program};
+
+// Name of the checkpoint of the indexer
+const CHECKPOINT: &str = "index";
+
+/// Number of blocks indexed between two checkpoints while catching up
+pub const CHECKPOINT_INTERVAL: u32 = 1_000;
+
+/// Index the blocks from `from`, or after the checkpoint of the last run, until interrupted
+pub async fn run<C: ChainConfig>(program: &Program<C>, from: Option<u32>) -> Result<()> {
+    let Some(cache) = program.cache.as_deref() else {
+        anyhow::bail!("the index needs the local cache, which couldn't be opened");
+    };
+    let genesis_hash = program.api.genesis_hash();
+    let start = match from {
+        Some(block) => block,
+        None => checkpoint::load(CHECKPOINT, genesis_hash)?.map_or(0, |block| block + 1),
+    };
+    let latest = program.snapshot().await?.number;
+
+    let mut last = None;
+    if start <= latest {
+        print!(program, "indexing blocks {start} to {latest}");
+        let blocks = block_events(program, start..=latest);
+        let ended = index_blocks(program, cache, blocks, false, &mut last).await;
+        if let Some(block) = last {
+            checkpoint::save(CHECKPOINT, genesis_hash, block)?;
+        }
+        if !ended? {
+            return stopped(program, last);
+        }
+        success!(program, "indexed blocks {start} to {latest}");
+    }
+
+    // the maps may have changed since they were last read
+    refresh_maps(program).await?;
+    print!(program, "following the finalized blocks");
+    let blocks = program
+        .finalized_blocks_after(latest.max(start.saturating_sub(1)))
+        .map(|block| block.map(|block| (block.number, block.events.clone())));
+    let ended = index_blocks(program, cache, blocks, true, &mut last).await;
+    if let Some(block) = last {
+        checkpoint::save(CHECKPOINT, genesis_hash, block)?;
+    }
+    if ended? {
+        anyhow::bail!("the finalized block subscription ended");
+    }
+    stopped(program, last)
+}
+
+// Index the blocks of a stream, saving the checkpoint every `CHECKPOINT_INTERVAL` blocks, or at
+// each block when following the new blocks, along with the cached maps. Returns whether the
+// stream ended, rather than being interrupted
+async fn index_blocks<C: ChainConfig>(
+    program: &Program<C>,
+    cache: &Cache,
+    blocks: impl Stream<Item = Result<(u32, Events<C>)>>,
+    follow: bool,
+    last: &mut Option<u32>,
+) -> Result<bool> {
+    let genesis_hash = program.api.genesis_hash();
+    let spinner = Spinner::new("indexing");
+    tokio::pin!(blocks);
+    let ended = loop {
+        let block = tokio::select! {
+            biased;
+            () = shutdown::requested() => break false,
+            block = blocks.next() => match block {
+                Some(block) => block?,
+                None => break true,
+            },
+        };
+
+        let (number, events) = block;
+        spinner.set_block(number);
+        let (records, governance) = records(number, &events);
+        cache
+            .index_block(number, &records)
+            .with_context(|| format!("failed to index block {number}"))?;
+        *last = Some(number);
+        if follow || number % CHECKPOINT_INTERVAL == 0 {
+            checkpoint::save(CHECKPOINT, genesis_hash, number)?;
+        }
+        if follow && governance {
+            tracing::debug!(number, records = records.len(), "indexed block");
+            refresh_maps(program).await?;
+        }
+    };
+    match *last {
+        Some(last) => spinner.finish_with_message(&format!("indexed up to block {last}")),
+        None => spinner.finish(),
+    };
+    Ok(ended)
+}
+
+// Read the cached maps of the governance version from the chain again, saving them to the cache
+async fn refresh_maps<C: ChainConfig>(program: &Program<C>) -> Result<()> {
+    program.unpin();
+    match program.governance()? {
+        Governance::OpenGov => {
+            let query = kitchensink::storage()
+                .referenda()
+                .referendum_info_for_iter();
+            program.iter_storage(&query).await?;
+            let query = kitchensink::storage().conviction_voting().voting_for_iter();
+            program.iter_storage(&query).await?;
+        }
+        Governance::Democracy => {
+            let query = kitchensink::storage().democracy().referendum_info_of_iter();
+            program.iter_storage(&query).await?;
+            let query = kitchensink::storage().democracy().voting_of_iter();
+            program.iter_storage(&query).await?;
+        }
+    }
+    Ok(())
+}
+
+// Report where the indexer stopped
+fn stopped<C: ChainConfig>(program: &Program<C>, last: Option<u32>) -> Result<()> {
+    if let Some(block) = last {
+        print!(program, "stopped after block {block}");
+    }
+    Ok(())
+}
+
+/// Records of the governance events of a block, and whether the block has governance events. The
+/// events after one that doesn't decode with the metadata, e.g. in a block of an older runtime,
+/// are skipped
+pub fn records<C: ChainConfig>(number: u32, events: &Events<C>) -> (Vec<Record>, bool) {
+    let mut records = Vec::new();
+    let mut governance = false;
+    for event in governance_events(number, events) {
+        governance = true;
+        let record = match (event.pallet_name(), event.variant_name()) {
+            ("Democracy", "Proposed") => {
+                decode::<_, democracy::events::Proposed>(&event).map(|ev| Record::Proposed {
+                    index: ev.proposal_index,
+                    deposit: ev.deposit,
+                })
+            }
+            ("Democracy", "Tabled") => {
+                decode::<_, democracy::events::Tabled>(&event).map(|ev| Record::Tabled {
+                    index: ev.proposal_index,
+                })
+            }
+            ("Democracy", "Started") => {
+                decode::<_, democracy::events::Started>(&event).map(|ev| Record::Started {
+                    governance: Governance::Democracy,
+                    index: ev.ref_index,
+                    track: None,
+                })
+            }
+            ("Democracy", "Voted") => {
+                decode::<_, democracy::events::Voted>(&event).map(|ev| Record::Voted {
+                    governance: Governance::Democracy,
+                    index: ev.ref_index,
+                    voter: ev.voter,
+                    vote: format!("{:?}", ev.vote),
+                })
+            }
+            ("Democracy", "Passed") => decode::<_, democracy::events::Passed>(&event)
+                .map(|ev| concluded(Governance::Democracy, ev.ref_index, "passed")),
+            ("Democracy", "NotPassed") => decode::<_, democracy::events::NotPassed>(&event)
+                .map(|ev| concluded(Governance::Democracy, ev.ref_index, "not passed")),
+            ("Democracy", "Cancelled") => decode::<_, democracy::events::Cancelled>(&event)
+                .map(|ev| concluded(Governance::Democracy, ev.ref_index, "cancelled")),
+            ("Referenda", "Submitted") => {
+                decode::<_, referenda::events::Submitted>(&event).map(|ev| Record::Started {
+                    governance: Governance::OpenGov,
+                    index: ev.index,
+                    track: Some(ev.track),
+                })
+            }
+            ("Referenda", "Approved") => decode::<_, referenda::events::Approved>(&event)
+                .map(|ev| concluded(Governance::OpenGov, ev.index, "approved")),
+            ("Referenda", "Rejected") => decode::<_, referenda::events::Rejected>(&event)
+                .map(|ev| concluded(Governance::OpenGov, ev.index, "rejected")),
+            ("Referenda", "Cancelled") => decode::<_, referenda::events::Cancelled>(&event)
+                .map(|ev| concluded(Governance::OpenGov, ev.index, "cancelled")),
+            ("Referenda", "TimedOut") => decode::<_, referenda::events::TimedOut>(&event)
+                .map(|ev| concluded(Governance::OpenGov, ev.index, "timed out")),
+            ("Referenda", "Killed") => decode::<_, referenda::events::Killed>(&event)
+                .map(|ev| concluded(Governance::OpenGov, ev.index, "killed")),
+            _ => None,
+        };
+        records.extend(record);
+    }
+    (records, governance)
+}
+
+// Decode a governance event with the compiled runtime, `None` when its fields differ
+fn decode<C: ChainConfig, E: StaticEvent>(event: &EventDetails<C>) -> Option<E> {
+    match event.as_event::<E>() {
+        Ok(decoded) => decoded,
+        Err(err) => {
+            tracing::warn!("failed to decode {}::{}: {err}", E::PALLET, E::EVENT);
+            None
+        }
+    }
+}
+
+// Record of a concluded referendum
+fn concluded(governance: Governance, index: u32, outcome: &'static str) -> Record {
+    Record::Concluded {
+        governance,
+        index,
+        outcome,
+    }
+}
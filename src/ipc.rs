@@ -0,0 +1,70 @@
+//! JSON-RPC over a unix socket, for nodes exposing an IPC endpoint instead of a websocket port.
+//!
+//! The socket is given as `unix:///path/to/node.sock` or `ipc:///path/to/node.sock`, and carries
+//! newline delimited JSON-RPC messages.
+use std::io;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use jsonrpsee::core::async_trait;
+use jsonrpsee::core::client::{
+    ClientBuilder, ReceivedMessage, TransportReceiverT, TransportSenderT,
+};
+use subxt::backend::rpc::RpcClient;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::net::unix::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::UnixStream;
+
+/// The socket path of an IPC url, `None` for other urls
+pub fn socket_path(url: &str) -> Option<&Path> {
+    url.strip_prefix("unix://")
+        .or_else(|| url.strip_prefix("ipc://"))
+        .map(Path::new)
+}
+
+/// Connect to the node listening on the given socket
+pub async fn connect(path: &Path) -> Result<RpcClient> {
+    let stream = UnixStream::connect(path)
+        .await
+        .with_context(|| format!("failed to connect to {}", path.display()))?;
+    let (read, write) = stream.into_split();
+
+    let client = ClientBuilder::default()
+        .build_with_tokio(Sender(write), Receiver(BufReader::new(read).lines()));
+    Ok(RpcClient::new(client))
+}
+
+// Sending half of the socket, one message per line
+struct Sender(OwnedWriteHalf);
+
+#[async_trait]
+impl TransportSenderT for Sender {
+    type Error = io::Error;
+
+    async fn send(&mut self, msg: String) -> io::Result<()> {
+        self.0.write_all(msg.as_bytes()).await?;
+        self.0.write_all(b"\n").await
+    }
+
+    async fn close(&mut self) -> io::Result<()> {
+        self.0.shutdown().await
+    }
+}
+
+// Receiving half of the socket, one message per line
+struct Receiver(Lines<BufReader<OwnedReadHalf>>);
+
+#[async_trait]
+impl TransportReceiverT for Receiver {
+    type Error = io::Error;
+
+    async fn receive(&mut self) -> io::Result<ReceivedMessage> {
+        match self.0.next_line().await? {
+            Some(line) => Ok(ReceivedMessage::Text(line)),
+            None => Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "IPC socket closed",
+            )),
+        }
+    }
+}
@@ -0,0 +1,2673 @@
+//! Governance workflows of Substrate chains, the democracy pallet and OpenGov referenda, as a
+//! library embedded by the `democracy-cli` binary and other tools.
+//!
+//! A [`Program`] holds the connection to the chain and the signing account, and runs the
+//! workflows: noting preimages, proposing, voting and waiting for the governance events.
+#[subxt::subxt(runtime_metadata_path = "metadata.scale")]
+pub mod kitchensink {}
+
+pub mod audit;
+pub mod auto_vote;
+pub mod bus;
+#[cfg(feature = "cache")]
+pub mod cache;
+pub mod calendar;
+pub mod call;
+pub mod call_builder;
+pub mod chain;
+pub mod chain_api;
+pub mod checkpoint;
+mod clipboard;
+pub mod commands;
+pub mod completions;
+pub mod config;
+pub mod dashboard;
+pub mod dynamic;
+pub mod error;
+pub mod exit;
+mod failover;
+pub mod history;
+#[cfg(feature = "cache")]
+pub mod indexer;
+mod ipc;
+pub mod light;
+pub mod log;
+pub mod metadata;
+mod notify;
+pub mod output;
+pub mod plugin;
+mod rate_limit;
+pub mod report;
+pub mod runtime;
+pub mod script;
+pub mod shutdown;
+pub mod stats;
+pub mod subscription;
+pub mod transport;
+pub mod units;
+pub mod voter_report;
+pub mod wasm;
+
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::bus::{EventBus, FinalizedEvents};
+use crate::call::CallDescription;
+use crate::chain::{ChainConfig, ChainProperties};
+use crate::chain_api::ChainApi;
+use crate::error::CliError;
+use crate::kitchensink::runtime_types::{
+    frame_support::dispatch::RawOrigin,
+    frame_support::traits::preimages::Bounded,
+    frame_support::traits::schedule::DispatchTime,
+    kitchensink_runtime::OriginCaller,
+    pallet_conviction_voting::types::Tally,
+    pallet_conviction_voting::vote::{
+        AccountVote as CvAccountVote, Vote as CvVote, Voting as CvVoting,
+    },
+    pallet_democracy::types::ReferendumInfo as DemocracyReferendumInfo,
+    pallet_democracy::vote::AccountVote,
+    pallet_democracy::vote::Vote,
+    pallet_democracy::vote::Voting as DemocracyVoting,
+    pallet_democracy::vote_threshold::VoteThreshold,
+    pallet_preimage::{OldRequestStatus, RequestStatus},
+    pallet_referenda::types::{Curve, DecidingStatus, ReferendumInfo, TrackInfo},
+};
+use crate::output::{Level, Output, Spinner, Table};
+use crate::runtime::Runtime;
+use crate::subscription::FinalizedBlock;
+use crate::transport::Transport;
+use crate::units::{Balance, Conviction};
+use anyhow::{Context, Result};
+use codec::Encode;
+use jsonrpsee::core::async_trait;
+use serde_json::json;
+use subxt::backend::legacy::LegacyRpcMethods;
+use subxt::backend::rpc::{rpc_params, RpcClient};
+use subxt::blocks::ExtrinsicEvents;
+use subxt::config::Hasher;
+use subxt::dynamic::At;
+use subxt::error::{DispatchError, TransactionError};
+use subxt::events::StaticEvent;
+use subxt::ext::futures::{future, stream, Stream, StreamExt, TryStreamExt};
+use subxt::metadata::DecodeWithMetadata;
+use subxt::storage::address::{StorageAddress, Yes};
+use subxt::storage::Storage;
+use subxt::tx::{TxPayload, TxProgress, TxStatus};
+use subxt::utils::{AccountId32, H256};
+use subxt::{config::substrate::BlakeTwo256, *};
+use subxt_signer::sr25519::{dev, Keypair};
+
+/// Dev users supported by the program
+#[derive(PartialEq, Debug, Clone, Copy, clap::ValueEnum, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum User {
+    Alice,
+    Bob,
+    Charlie,
+    Dave,
+    Eve,
+    Ferdie,
+}
+impl Display for User {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&format!("{:?}", self))
+    }
+}
+impl User {
+    pub fn keypair(&self) -> Keypair {
+        match self {
+            Self::Alice => dev::alice(),
+            Self::Bob => dev::bob(),
+            Self::Charlie => dev::charlie(),
+            Self::Dave => dev::dave(),
+            Self::Eve => dev::eve(),
+            Self::Ferdie => dev::ferdie(),
+        }
+    }
+
+    pub fn account_id(&self) -> AccountId32 {
+        self.keypair().public_key().into()
+    }
+}
+
+/// Governance versions the generic commands can be routed to
+#[derive(PartialEq, Debug, Clone, Copy, clap::ValueEnum)]
+pub enum Governance {
+    /// Gov1, backed by the `Democracy` pallet
+    Democracy,
+    /// OpenGov, backed by the `Referenda` and `ConvictionVoting` pallets
+    OpenGov,
+}
+impl Display for Governance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&format!("{:?}", self))
+    }
+}
+impl Governance {
+    /// The pallets that must be present in the metadata for this governance version
+    pub fn pallets(&self) -> &'static [&'static str] {
+        match self {
+            Self::Democracy => &["Democracy"],
+            Self::OpenGov => &["Referenda", "ConvictionVoting"],
+        }
+    }
+
+    /// Whether the governance version is backed by the given metadata
+    pub fn is_available(&self, metadata: &Metadata) -> bool {
+        self.pallets()
+            .iter()
+            .all(|name| metadata.pallet_by_name(name).is_some())
+    }
+
+    /// Detect the governance version from the metadata, preferring `Democracy` when both exist
+    pub fn detect(metadata: &Metadata) -> Option<Self> {
+        [Self::Democracy, Self::OpenGov]
+            .into_iter()
+            .find(|gov| gov.is_available(metadata))
+    }
+}
+
+/// Call data, given either as hex or as a JSON call description
+#[derive(clap::Args, Debug)]
+#[group(required = true, multiple = false)]
+pub struct CallData {
+    /// SCALE encoded call, as hex
+    #[clap(long)]
+    pub hex: Option<String>,
+    /// JSON call description file
+    #[clap(long)]
+    pub file: Option<PathBuf>,
+}
+
+/// Deposit locked when creating a democracy proposal
+pub const PROPOSAL_DEPOSIT: u128 = 1_000_000_000_000_000_000;
+
+/// Enactment delay used when no enactment moment is given
+pub const DEFAULT_ENACT_AFTER: u32 = 1;
+
+// Block time in milliseconds used when the runtime doesn't expose it
+const DEFAULT_BLOCK_TIME: u64 = 6_000;
+
+// Number of blocks sampled for the tally charts
+const CHART_SAMPLES: u32 = 60;
+
+// Blocks the cached storage maps may be behind the snapshot of a command before being read again
+#[cfg(feature = "cache")]
+const MAX_CACHE_LAG: u32 = 10;
+
+// Finalized blocks searched for an extrinsic whose watch was lost before giving up
+const MAX_SEARCH_BLOCKS: u32 = 256;
+
+/// An ongoing OpenGov referendum, as stored in `Referenda::ReferendumInfoFor`
+pub type ReferendumStatus = kitchensink::runtime_types::pallet_referenda::types::ReferendumStatus<
+    u16,
+    OriginCaller,
+    u32,
+    Bounded<kitchensink::Call, kitchensink::runtime_types::sp_runtime::traits::BlakeTwo256>,
+    u128,
+    Tally<u128>,
+    AccountId32,
+    (u32, u32),
+>;
+
+/// Keys listed per `state_getKeysPaged` request when iterating over a storage map
+pub const STORAGE_PAGE_SIZE: u32 = 128;
+
+// Pages of storage values fetched concurrently when iterating over a storage map
+const STORAGE_CONCURRENCY: usize = 4;
+
+// Parts per billion, the precision of `Perbill` and `FixedI64`
+const BILLION: u64 = 1_000_000_000;
+
+// Evaluate a track curve at `x`, both expressed in parts per billion
+// This mirrors `pallet_referenda::Curve::threshold`
+fn curve_threshold(curve: &Curve, x: u64) -> u64 {
+    match curve {
+        Curve::LinearDecreasing {
+            length,
+            floor,
+            ceil,
+        } => {
+            let (length, floor, ceil) = (length.0 as u64, floor.0 as u64, ceil.0 as u64);
+            if length == 0 {
+                return floor;
+            }
+            let progress = x.min(length) * BILLION / length;
+            ceil.saturating_sub(progress * ceil.saturating_sub(floor) / BILLION)
+        }
+        Curve::SteppedDecreasing {
+            begin,
+            end,
+            step,
+            period,
+        } => {
+            let (begin, end, step, period) =
+                (begin.0 as u64, end.0 as u64, step.0 as u64, period.0 as u64);
+            let steps = x.checked_div(period).unwrap_or(0);
+            begin.saturating_sub(step * steps).max(end)
+        }
+        Curve::Reciprocal {
+            factor,
+            x_offset,
+            y_offset,
+        } => {
+            let denominator = x as i128 + x_offset.0 as i128;
+            if denominator <= 0 {
+                return BILLION;
+            }
+            let term = factor.0 as i128 * BILLION as i128 / denominator;
+            (term + y_offset.0 as i128).clamp(0, BILLION as i128) as u64
+        }
+    }
+}
+
+/// Humanize a duration in milliseconds with its two largest units, e.g. "2 days 4 hours"
+pub fn humanize_duration(ms: u64) -> String {
+    const UNITS: [(&str, u64); 4] = [
+        ("day", 86_400),
+        ("hour", 3_600),
+        ("minute", 60),
+        ("second", 1),
+    ];
+
+    let mut seconds = ms / 1000;
+    let mut parts = Vec::new();
+    for (unit, length) in UNITS {
+        let count = seconds / length;
+        seconds %= length;
+        if count > 0 {
+            let plural = if count > 1 { "s" } else { "" };
+            parts.push(format!("{count} {unit}{plural}"));
+        } else if !parts.is_empty() {
+            break;
+        }
+        if parts.len() == 2 {
+            break;
+        }
+    }
+
+    if parts.is_empty() {
+        return "0 seconds".to_string();
+    }
+    parts.join(" ")
+}
+
+// Format a parts-per-billion value as a percentage
+pub(crate) fn format_ppb(value: u64) -> String {
+    format!("{:.2}%", value as f64 / BILLION as f64 * 100.0)
+}
+
+/// Approval and support of an ongoing OpenGov referendum against its track curves, in parts
+/// per billion
+pub struct Thresholds {
+    /// Blocks elapsed in the decision period
+    pub elapsed: u32,
+    /// Progress through the decision period
+    pub x: u64,
+    pub approval: u64,
+    pub min_approval: u64,
+    pub support: u64,
+    pub min_support: u64,
+}
+
+/// Whether a democracy referendum tally is approved under its threshold, as the democracy pallet
+/// decides it at the end of the voting period. The square roots are computed in floating point,
+/// the result may differ from the pallet's right at the threshold
+pub fn democracy_approved(
+    threshold: &VoteThreshold,
+    ayes: u128,
+    nays: u128,
+    turnout: u128,
+    electorate: u128,
+) -> bool {
+    let (ayes, nays) = (ayes as f64, nays as f64);
+    let (turnout, electorate) = ((turnout as f64).sqrt(), (electorate as f64).sqrt());
+    if turnout == 0.0 {
+        return false;
+    }
+    match threshold {
+        VoteThreshold::SuperMajorityApprove => nays * electorate < ayes * turnout,
+        VoteThreshold::SuperMajorityAgainst => nays * turnout < ayes * electorate,
+        VoteThreshold::SimpleMajority => ayes > nays,
+    }
+}
+
+// A green check mark when a threshold is met, a red cross otherwise
+pub(crate) fn check_mark(met: bool) -> String {
+    if met {
+        output::paint("✓", Level::Success)
+    } else {
+        output::paint("✗", Level::Error)
+    }
+}
+
+/// Error of the failed call of an extrinsic, naming the pallet error and documenting it when
+/// there is one, with the extrinsic and its block for reference
+pub fn extrinsic_error<C: ChainConfig>(
+    events: &ExtrinsicEvents<C>,
+    error: DispatchError,
+) -> anyhow::Error {
+    anyhow::Error::from(CliError::dispatch(error)).context(format!(
+        "extrinsic {:?} (index {} in block {:?}) failed",
+        events.extrinsic_hash(),
+        events.extrinsic_index(),
+        events.block_hash()
+    ))
+}
+
+// Error of a vote on a referendum that is over
+fn not_ongoing(index: u32, outcome: &str, end: u32) -> Result<()> {
+    Err(CliError::Validation {
+        what: "vote".into(),
+        source: anyhow::anyhow!(
+            "referendum #{index} was {outcome} at block {end}, it doesn't accept votes anymore"
+        ),
+    }
+    .into())
+}
+
+// Error of a vote on a referendum that doesn't exist
+fn no_referendum(index: u32) -> anyhow::Error {
+    CliError::Validation {
+        what: "vote".into(),
+        source: anyhow::anyhow!("no referendum #{index}"),
+    }
+    .into()
+}
+
+// Hash of the call of a proposal, and whether the call is inline rather than a preimage
+fn bounded_call<Call, Hash>(proposal: &Bounded<Call, Hash>) -> (H256, bool) {
+    match proposal {
+        Bounded::Legacy { hash } | Bounded::Lookup { hash, .. } => (*hash, false),
+        Bounded::Inline(call) => (call_hash(&call.0).0, true),
+        Bounded::__Ignore(_) => (H256::zero(), false),
+    }
+}
+
+/// Human readable phase of an ongoing OpenGov referendum
+pub fn referendum_phase(status: &ReferendumStatus) -> &'static str {
+    match &status.deciding {
+        None if status.in_queue => "queued",
+        None => "preparing",
+        Some(DecidingStatus {
+            confirming: None, ..
+        }) => "deciding",
+        Some(DecidingStatus {
+            confirming: Some(_),
+            ..
+        }) => "confirming",
+    }
+}
+
+/// Create a vote for a proposal
+pub fn create_vote(
+    ref_index: u32,
+    aye: bool,
+    conviction: Conviction,
+    balance: u128,
+) -> subxt::tx::Payload<kitchensink::democracy::calls::types::Vote> {
+    let democracy = kitchensink::tx().democracy();
+    let vote = AccountVote::Standard {
+        vote: Vote(conviction.vote(aye)),
+        balance,
+    };
+
+    democracy.vote(ref_index, vote)
+}
+
+/// Create an OpenGov (conviction-voting) vote for a referendum
+pub fn create_cv_vote(
+    poll_index: u32,
+    aye: bool,
+    conviction: Conviction,
+    balance: u128,
+) -> subxt::tx::Payload<kitchensink::conviction_voting::calls::types::Vote> {
+    let conviction_voting = kitchensink::tx().conviction_voting();
+    let vote = CvAccountVote::Standard {
+        vote: CvVote(conviction.vote(aye)),
+        balance,
+    };
+
+    conviction_voting.vote(poll_index, vote)
+}
+
+/// Create the vote call of the governance version, to be dispatched in a batch
+pub fn vote_call(
+    governance: Governance,
+    index: u32,
+    aye: bool,
+    conviction: Conviction,
+    balance: u128,
+) -> kitchensink::Call {
+    match governance {
+        Governance::Democracy => kitchensink::Call::Democracy(
+            kitchensink::runtime_types::pallet_democracy::pallet::Call::vote {
+                ref_index: index,
+                vote: AccountVote::Standard {
+                    vote: Vote(conviction.vote(aye)),
+                    balance,
+                },
+            },
+        ),
+        Governance::OpenGov => kitchensink::Call::ConvictionVoting(
+            kitchensink::runtime_types::pallet_conviction_voting::pallet::Call::vote {
+                poll_index: index,
+                vote: CvAccountVote::Standard {
+                    vote: CvVote(conviction.vote(aye)),
+                    balance,
+                },
+            },
+        ),
+    }
+}
+
+/// Compute the hash and length identifying a call preimage
+pub fn call_hash(image: &[u8]) -> (H256, u32) {
+    (BlakeTwo256::hash(image), image.len() as u32)
+}
+
+/// Decode the `u32` index at the end of a `Twox64Concat`/`Blake2_128Concat` map key
+pub fn storage_key_index(key: &[u8]) -> u32 {
+    let mut index = [0u8; 4];
+    index.copy_from_slice(&key[key.len() - 4..]);
+    u32::from_le_bytes(index)
+}
+
+/// Decode the `u16` class at the end of a `ConvictionVoting::VotingFor` map key
+pub fn storage_key_class(key: &[u8]) -> u16 {
+    let mut class = [0u8; 2];
+    class.copy_from_slice(&key[key.len() - 2..]);
+    u16::from_le_bytes(class)
+}
+
+/// Decode the account at the end of a `Twox64Concat` map key, such as `Democracy::VotingOf`
+pub fn storage_key_account(key: &[u8]) -> AccountId32 {
+    let mut account = [0u8; 32];
+    account.copy_from_slice(&key[key.len() - 32..]);
+    AccountId32(account)
+}
+
+/// Decode the `H256` at the end of an `Identity` hashed map key
+pub fn storage_key_hash(key: &[u8]) -> H256 {
+    H256::from_slice(&key[key.len() - 32..])
+}
+
+/// Encode call data with the given metadata
+pub fn encode_call(call: &CallData, metadata: &Metadata) -> Result<Vec<u8>> {
+    match (&call.hex, &call.file) {
+        (Some(hex), _) => Ok(hex::decode(hex.trim_start_matches("0x"))?),
+        (None, Some(file)) => {
+            let payload = CallDescription::from_file(file)?.payload()?;
+            Ok(payload.encode_call_data(metadata)?)
+        }
+        (None, None) => anyhow::bail!("either --hex or --file must be provided"),
+    }
+}
+
+/// Read call data from a file, as hex with an optional `0x` prefix, or else as raw bytes
+pub fn read_call_data(path: &Path) -> Result<Vec<u8>> {
+    let content =
+        std::fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+    let hex = std::str::from_utf8(&content)
+        .ok()
+        .and_then(|text| hex::decode(text.trim().trim_start_matches("0x")).ok());
+    Ok(hex.unwrap_or(content))
+}
+
+/// Print the hash and length of an encoded call, and the call data itself if `with_data`, copying
+/// the data or the hash to the clipboard if `copy`
+pub fn print_call_hash(output: &Output, image: &[u8], with_data: bool, copy: bool) -> Result<()> {
+    let (hash, len) = call_hash(image);
+    let data = format!("0x{}", hex::encode(image));
+    if output.is_json() {
+        let data = with_data.then_some(&data);
+        output.record_json("call", json!({ "hash": hash, "len": len, "data": data }));
+    } else {
+        if with_data {
+            output.message(&format!("call data: {data}"));
+        }
+        output.message(&format!("call hash: {hash:?}, len: {len}"));
+    }
+
+    if copy {
+        let (name, value) = if with_data {
+            ("call data", data)
+        } else {
+            ("call hash", format!("{hash:?}"))
+        };
+        copy_to_clipboard(output, name, &value)?;
+    }
+    Ok(())
+}
+
+/// Copy a value to the clipboard
+pub fn copy_to_clipboard(output: &Output, name: &str, value: &str) -> Result<()> {
+    clipboard::copy(value)?;
+    output.success(&format!("{name} copied to the clipboard"));
+    Ok(())
+}
+
+/// Fetch the ongoing OpenGov referenda, grouped by track
+pub async fn ongoing_referenda(
+    chain: &(impl ChainApi + ?Sized),
+) -> Result<BTreeMap<u16, Vec<(u32, ReferendumStatus)>>> {
+    let query = kitchensink::storage()
+        .referenda()
+        .referendum_info_for_iter();
+
+    let mut by_track = BTreeMap::<_, Vec<_>>::new();
+    for (key, info) in chain_api::iter(chain, &query).await? {
+        if let ReferendumInfo::Ongoing(status) = info {
+            by_track
+                .entry(status.track)
+                .or_default()
+                .push((storage_key_index(&key), status));
+        }
+    }
+
+    Ok(by_track)
+}
+
+/// Find the finished referenda whose submission deposit can be refunded to an account
+pub async fn refundable_submission_deposits(
+    chain: &(impl ChainApi + ?Sized),
+    account: &AccountId32,
+) -> Result<Vec<u32>> {
+    let query = kitchensink::storage()
+        .referenda()
+        .referendum_info_for_iter();
+
+    // only approved and cancelled referenda release their submission deposit
+    let mut refundable = Vec::new();
+    for (key, info) in chain_api::iter(chain, &query).await? {
+        if let ReferendumInfo::Approved(_, Some(deposit), _)
+        | ReferendumInfo::Cancelled(_, Some(deposit), _) = info
+        {
+            if deposit.who == *account {
+                refundable.push(storage_key_index(&key));
+            }
+        }
+    }
+
+    Ok(refundable)
+}
+
+/// Fetch all the preimages known to the preimage pallet
+pub async fn preimages(chain: &(impl ChainApi + ?Sized)) -> Result<Vec<PreimageEntry>> {
+    let mut entries = Vec::new();
+
+    let query = kitchensink::storage().preimage().status_for_iter();
+    for (key, status) in chain_api::iter(chain, &query).await? {
+        let hash = storage_key_hash(&key);
+        entries.push(match status {
+            OldRequestStatus::Unrequested { deposit, len } => PreimageEntry {
+                hash,
+                len: Some(len),
+                status: "unrequested (legacy)".to_string(),
+                deposit: Some(deposit),
+            },
+            OldRequestStatus::Requested {
+                deposit,
+                count,
+                len,
+            } => PreimageEntry {
+                hash,
+                len,
+                status: format!("requested x{count} (legacy)"),
+                deposit,
+            },
+        });
+    }
+
+    let query = kitchensink::storage().preimage().request_status_for_iter();
+    for (key, status) in chain_api::iter(chain, &query).await? {
+        let hash = storage_key_hash(&key);
+        entries.push(match status {
+            RequestStatus::Unrequested {
+                ticket: (who, ticket),
+                len,
+            } => PreimageEntry {
+                hash,
+                len: Some(len),
+                status: "unrequested".to_string(),
+                deposit: Some((who, ticket.0)),
+            },
+            RequestStatus::Requested {
+                maybe_ticket,
+                count,
+                maybe_len,
+            } => PreimageEntry {
+                hash,
+                len: maybe_len,
+                status: format!("requested x{count}"),
+                deposit: maybe_ticket.map(|(who, ticket)| (who, ticket.0)),
+            },
+        });
+    }
+
+    Ok(entries)
+}
+
+/// A preimage from `Preimage::StatusFor` (legacy) or `Preimage::RequestStatusFor`
+pub struct PreimageEntry {
+    pub hash: H256,
+    pub len: Option<u32>,
+    pub status: String,
+    pub deposit: Option<(AccountId32, u128)>,
+}
+
+/// Wrap a call in `sudo.sudo`, used to act as a privileged origin on dev chains
+pub fn sudo(call: kitchensink::Call) -> subxt::tx::Payload<kitchensink::sudo::calls::types::Sudo> {
+    kitchensink::tx().sudo().sudo(call)
+}
+
+/// The program context, driving the chain with the subxt configuration `C`
+pub struct Program<C: ChainConfig = SubstrateConfig> {
+    pub api: OnlineClient<C>,
+    pub rpc: RpcClient,
+    pub user: User,
+    pub signer: Keypair,
+    pub governance: Option<Governance>,
+    pub assume_yes: bool,
+    /// Submit even when the free balance doesn't cover a transaction, see [`Self::check_balance`]
+    pub force: bool,
+    pub dynamic: bool,
+    pub runtime: Runtime,
+    pub fork: bool,
+    pub properties: ChainProperties,
+    pub output: Output,
+    pub raw: bool,
+    pub timeout: Option<std::time::Duration>,
+    pub audit_log: Option<PathBuf>,
+    /// Command notifying the events of the daemons, see [`Self::notify`]
+    pub notify: Vec<String>,
+    /// Finalized blocks shared by the consumers of the program
+    pub bus: EventBus<C>,
+    /// Requests and subscriptions sent over the connection
+    pub stats: stats::ConnectionStats,
+    /// Local cache of the referenda and votes, `None` when it can't be opened
+    #[cfg(feature = "cache")]
+    pub cache: Option<Arc<cache::Cache>>,
+    /// Block the reads run against, see [`Self::snapshot`]
+    snapshot: Arc<Mutex<Option<FinalizedBlock>>>,
+}
+
+/// How to reach the chain
+pub enum Connection {
+    /// RPC endpoints, the healthiest one is used
+    Rpc(Vec<String>, Transport),
+    /// Light client started from a chain spec
+    Light(String),
+}
+
+/// Behaviour flags of the program, set from the command line and the profile
+pub struct Options {
+    pub governance: Option<Governance>,
+    pub runtime: Option<Runtime>,
+    pub assume_yes: bool,
+    pub force: bool,
+    pub dynamic: bool,
+    pub strict: bool,
+    pub ss58_prefix: Option<u16>,
+    pub rps: Option<u32>,
+    pub fork: bool,
+    pub output: output::Format,
+    pub columns: Vec<String>,
+    pub raw: bool,
+    pub timeout: Option<std::time::Duration>,
+    pub audit_log: Option<PathBuf>,
+    /// Command notifying the events of the daemons, none when empty
+    pub notify: Vec<String>,
+    /// Read the cached referenda and votes from the chain again
+    #[cfg(feature = "cache")]
+    pub refresh: bool,
+}
+
+// Storage read through a program, see `Program::chain_api`
+struct ProgramStorage<'a, C: ChainConfig> {
+    program: &'a Program<C>,
+    cached: bool,
+}
+
+#[async_trait]
+impl<C: ChainConfig> ChainApi for ProgramStorage<'_, C> {
+    fn metadata(&self) -> Metadata {
+        self.program.api.metadata()
+    }
+
+    async fn latest_block_number(&self) -> Result<u32> {
+        Ok(self.program.snapshot().await?.number)
+    }
+
+    async fn fetch_raw(&self, key: Vec<u8>) -> Result<Option<Vec<u8>>> {
+        Ok(self.program.storage().await?.fetch_raw(key).await?)
+    }
+
+    async fn iter_raw(&self, prefix: Vec<u8>) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        self.program.raw_storage_map(prefix, self.cached).await
+    }
+}
+
+/// Helper macro to print to the console using the program context
+#[macro_export]
+macro_rules! print {
+    ($prg:expr, $($arg:tt)*) => {
+        $prg.output.message(&format!($($arg)*));
+    };
+}
+
+/// Same as `print!`, for messages reporting a successful operation
+#[macro_export]
+macro_rules! success {
+    ($prg:expr, $($arg:tt)*) => {
+        $prg.output.success(&format!($($arg)*));
+    };
+}
+
+/// Same as `print!`, for messages the user should pay attention to
+#[macro_export]
+macro_rules! warning {
+    ($prg:expr, $($arg:tt)*) => {
+        $prg.output.warning(&format!($($arg)*));
+    };
+}
+
+impl<C: ChainConfig> Program<C> {
+    /// Create a new program context
+    pub async fn new(
+        connection: Connection,
+        user: User,
+        signer: Keypair,
+        properties: ChainProperties,
+        options: Options,
+    ) -> Result<Self> {
+        let Options {
+            governance,
+            runtime,
+            assume_yes,
+            force,
+            dynamic,
+            strict,
+            ss58_prefix,
+            rps,
+            fork,
+            output,
+            columns,
+            raw,
+            timeout,
+            audit_log,
+            notify,
+            #[cfg(feature = "cache")]
+            refresh,
+        } = options;
+        let rpc = match connection {
+            Connection::Rpc(urls, transport) => failover::connect(&urls, transport)
+                .await
+                .map_err(CliError::Connection)?,
+            Connection::Light(chain_spec) => {
+                tracing::info!("syncing the light client");
+                light::connect(&chain_spec).map_err(CliError::Connection)?
+            }
+        };
+        let (rpc, stats) = stats::track(rpc);
+        let rpc = rate_limit::limit(rpc, rps);
+        let api = metadata::client(rpc.clone())
+            .await
+            .map_err(CliError::Connection)?;
+
+        // the node knows best how to format its balances and addresses
+        let mut properties = properties;
+        match LegacyRpcMethods::<C>::new(rpc.clone())
+            .system_properties()
+            .await
+        {
+            Ok(system_properties) => properties.update_from(&system_properties),
+            Err(err) => output::warn(&format!("failed to fetch the chain properties: {err}")),
+        }
+        if let Some(ss58_prefix) = ss58_prefix {
+            properties.ss58_prefix = ss58_prefix;
+        }
+        if let Err(err) = metadata::store(&api) {
+            output::warn(&format!("failed to cache the runtime metadata: {err:#}"));
+        }
+        let metadata = api.metadata();
+        let runtime = runtime
+            .or_else(|| Runtime::detect(&metadata))
+            .unwrap_or(Runtime::Kitchensink);
+        if !dynamic && !runtime.is_codegen_valid_for(&metadata) {
+            let describe =
+                |version: Option<u32>| version.map_or("?".to_string(), |v| v.to_string());
+            let mismatch = format!(
+                "the chain runs spec version {}, its metadata doesn't match the compiled {runtime:?} runtime (spec version {})",
+                api.runtime_version().spec_version,
+                describe(runtime.spec_version()),
+            );
+            if strict {
+                anyhow::bail!("{mismatch}");
+            }
+
+            output::warn(&"!".repeat(80));
+            output::warn(&format!("WARNING: {mismatch}"));
+            output::warn("some calls may fail to encode or decode, consider using --dynamic");
+            output::warn(&"!".repeat(80));
+        }
+        let governance = match governance {
+            Some(gov) if !gov.is_available(&metadata) => {
+                anyhow::bail!("{gov} governance is not available on the connected chain")
+            }
+            Some(gov) => Some(gov),
+            None => Governance::detect(&metadata),
+        };
+        tracing::info!(
+            spec_version = api.runtime_version().spec_version,
+            ?runtime,
+            ?governance,
+            "connected"
+        );
+        #[cfg(feature = "cache")]
+        let cache = match cache::Cache::open(
+            api.genesis_hash(),
+            api.runtime_version().spec_version,
+            refresh,
+        ) {
+            Ok(cache) => cache.map(Arc::new),
+            Err(err) => {
+                output::warn(&format!("failed to open the cache: {err:#}"));
+                None
+            }
+        };
+
+        Ok(Self {
+            bus: EventBus::new(&api, &rpc),
+            stats,
+            api,
+            rpc,
+            user,
+            signer,
+            governance,
+            assume_yes,
+            force,
+            dynamic,
+            runtime,
+            fork,
+            properties,
+            output: Output::new(output, user, columns),
+            raw,
+            timeout,
+            audit_log,
+            notify,
+            #[cfg(feature = "cache")]
+            cache,
+            snapshot: Arc::default(),
+        })
+    }
+
+    /// Same program signing as another dev user
+    pub fn as_user(&self, user: User) -> Self {
+        Self {
+            api: self.api.clone(),
+            rpc: self.rpc.clone(),
+            user,
+            signer: user.keypair(),
+            governance: self.governance,
+            assume_yes: self.assume_yes,
+            force: self.force,
+            dynamic: self.dynamic,
+            runtime: self.runtime,
+            fork: self.fork,
+            properties: self.properties.clone(),
+            output: self.output.for_user(user),
+            raw: self.raw,
+            timeout: self.timeout,
+            audit_log: self.audit_log.clone(),
+            notify: self.notify.clone(),
+            bus: self.bus.clone(),
+            stats: self.stats.clone(),
+            #[cfg(feature = "cache")]
+            cache: self.cache.clone(),
+            snapshot: self.snapshot.clone(),
+        }
+    }
+
+    /// Finalized block the reads of the command run against. The latest finalized block is
+    /// pinned by the first read, so that the queries of a command see a consistent state, until
+    /// [`Self::unpin`]
+    pub async fn snapshot(&self) -> Result<FinalizedBlock> {
+        if let Some(block) = *self.snapshot.lock().unwrap() {
+            return Ok(block);
+        }
+
+        let block = self.api.blocks().at_latest().await?;
+        let block = FinalizedBlock {
+            number: block.number(),
+            hash: block.hash(),
+        };
+        tracing::debug!(number = block.number, hash = ?block.hash, "pinned the snapshot");
+        Ok(*self.snapshot.lock().unwrap().get_or_insert(block))
+    }
+
+    /// Let the next read pin the latest finalized block again, once the state changed, e.g. after
+    /// waiting for an extrinsic or at each block of the long-running commands
+    pub fn unpin(&self) {
+        *self.snapshot.lock().unwrap() = None;
+    }
+
+    /// Storage at the snapshot of the command, see [`Self::snapshot`]
+    pub async fn storage(&self) -> Result<Storage<C, OnlineClient<C>>> {
+        Ok(self.api.storage().at(self.snapshot().await?.hash))
+    }
+
+    /// The governance version backing the generic commands
+    pub fn governance(&self) -> Result<Governance> {
+        self.governance.ok_or_else(|| {
+            anyhow::anyhow!(
+                "no Democracy or Referenda/ConvictionVoting pallet on the connected chain"
+            )
+        })
+    }
+
+    /// Fetch the ongoing OpenGov referenda, grouped by track
+    pub async fn ongoing_referenda(&self) -> Result<BTreeMap<u16, Vec<(u32, ReferendumStatus)>>> {
+        ongoing_referenda(&self.chain_api(false)).await
+    }
+
+    /// Same as [`Self::ongoing_referenda`], from the local cache when built with the `cache`
+    /// feature
+    pub async fn cached_ongoing_referenda(
+        &self,
+    ) -> Result<BTreeMap<u16, Vec<(u32, ReferendumStatus)>>> {
+        ongoing_referenda(&self.chain_api(true)).await
+    }
+
+    /// Look up the info of an OpenGov track
+    pub fn track(&self, id: u16) -> Result<TrackInfo<u128, u32>> {
+        let tracks = self
+            .api
+            .constants()
+            .at(&kitchensink::constants().referenda().tracks())?;
+
+        tracks
+            .into_iter()
+            .find_map(|(track, info)| (track == id).then_some(info))
+            .ok_or_else(|| anyhow::anyhow!("unknown track {id}"))
+    }
+
+    /// Resolve an OpenGov track given by name or id
+    pub fn track_id(&self, track: &str) -> Result<u16> {
+        if let Ok(id) = track.parse::<u16>() {
+            return self.track(id).map(|_| id);
+        }
+
+        self.api
+            .constants()
+            .at(&kitchensink::constants().referenda().tracks())?
+            .into_iter()
+            .find_map(|(id, info)| (info.name == track).then_some(id))
+            .ok_or_else(|| anyhow::anyhow!("unknown track {track}"))
+    }
+
+    /// Print the OpenGov tracks with their deposits and periods
+    pub fn print_tracks(&self) -> Result<()> {
+        let tracks = self
+            .api
+            .constants()
+            .at(&kitchensink::constants().referenda().tracks())?;
+
+        let mut table = Table::new(&[
+            "id",
+            "name",
+            "max_deciding",
+            "deposit",
+            "prepare",
+            "decision",
+            "confirm",
+            "enactment",
+        ]);
+        for (id, track) in tracks {
+            self.output.record_json(
+                "track",
+                json!({
+                    "id": id,
+                    "name": track.name,
+                    "max_deciding": track.max_deciding,
+                    "decision_deposit": track.decision_deposit,
+                    "prepare_period": track.prepare_period,
+                    "decision_period": track.decision_period,
+                    "confirm_period": track.confirm_period,
+                    "min_enactment_period": track.min_enactment_period,
+                }),
+            );
+            table.add_row(vec![
+                id.to_string(),
+                track.name,
+                track.max_deciding.to_string(),
+                self.balance(track.decision_deposit),
+                self.describe_period(track.prepare_period),
+                self.describe_period(track.decision_period),
+                self.describe_period(track.confirm_period),
+                self.describe_period(track.min_enactment_period),
+            ]);
+        }
+
+        self.output.table(&table)
+    }
+
+    /// Sample the tally of a referendum at evenly spaced blocks between `from` and `to`, skipping
+    /// the blocks whose state was pruned by the node
+    pub async fn tally_history(
+        &self,
+        index: u32,
+        from: u32,
+        to: u32,
+    ) -> Result<Vec<(u32, Tally<u128>)>> {
+        let rpc = LegacyRpcMethods::<C>::new(self.rpc.clone());
+        let query = kitchensink::storage()
+            .referenda()
+            .referendum_info_for(index);
+        let step = (to.saturating_sub(from) / CHART_SAMPLES).max(1);
+
+        let mut history = Vec::new();
+        for block in (from..=to).step_by(step as usize) {
+            let Some(hash) = rpc.chain_get_block_hash(Some(block.into())).await? else {
+                continue;
+            };
+            match self.api.storage().at(hash).fetch(&query).await {
+                Ok(Some(ReferendumInfo::Ongoing(status))) => history.push((block, status.tally)),
+                Ok(_) => {}
+                Err(err) => tracing::debug!(block, %err, "tally not available"),
+            }
+        }
+
+        Ok(history)
+    }
+
+    /// Print sparklines of the ayes and nays of a tally history, on a common scale
+    pub fn print_tally_chart(&self, history: &[(u32, Tally<u128>)], now: u32) {
+        let Some((first, _)) = history.first() else {
+            warning!(
+                self,
+                "no tally available, the node may have pruned the state"
+            );
+            return;
+        };
+
+        let ayes = history
+            .iter()
+            .map(|(_, tally)| tally.ayes)
+            .collect::<Vec<_>>();
+        let nays = history
+            .iter()
+            .map(|(_, tally)| tally.nays)
+            .collect::<Vec<_>>();
+        let max = ayes.iter().chain(&nays).copied().max().unwrap_or_default();
+        print!(
+            self,
+            "tally since {}, {} samples:",
+            self.describe_block(*first, now),
+            history.len()
+        );
+        print!(
+            self,
+            "ayes {} {}",
+            output::sparkline(&ayes, max),
+            self.balance(ayes.last().copied().unwrap_or_default())
+        );
+        print!(
+            self,
+            "nays {} {}",
+            output::sparkline(&nays, max),
+            self.balance(nays.last().copied().unwrap_or_default())
+        );
+    }
+
+    /// Evaluate the approval and support of an ongoing referendum against its track curves
+    pub async fn referendum_thresholds(
+        &self,
+        status: &ReferendumStatus,
+        track: &TrackInfo<u128, u32>,
+        now: u32,
+    ) -> Result<Thresholds> {
+        // progress through the decision period, the point at which the curves are evaluated
+        let elapsed = status
+            .deciding
+            .as_ref()
+            .map_or(0, |deciding| now.saturating_sub(deciding.since));
+        let x = (elapsed as u64 * BILLION)
+            .checked_div(track.decision_period as u64)
+            .unwrap_or(BILLION)
+            .min(BILLION);
+
+        // support is measured against the active issuance, see `ConvictionVoting::MaxTurnout`
+        let api = self.storage().await?;
+        let total_issuance = api
+            .fetch_or_default(&kitchensink::storage().balances().total_issuance())
+            .await?;
+        let inactive_issuance = api
+            .fetch_or_default(&kitchensink::storage().balances().inactive_issuance())
+            .await?;
+        let active_issuance = total_issuance.saturating_sub(inactive_issuance);
+
+        let Tally {
+            ayes,
+            nays,
+            support,
+        } = &status.tally;
+        let approval = (ayes * BILLION as u128)
+            .checked_div(ayes + nays)
+            .unwrap_or(0) as u64;
+        let support = (support * BILLION as u128)
+            .checked_div(active_issuance)
+            .unwrap_or(0) as u64;
+
+        Ok(Thresholds {
+            elapsed,
+            x,
+            approval,
+            min_approval: curve_threshold(&track.min_approval, x),
+            support,
+            min_support: curve_threshold(&track.min_support, x),
+        })
+    }
+
+    /// Find the finished referenda whose submission deposit can be refunded to the current user
+    pub async fn refundable_submission_deposits(&self) -> Result<Vec<u32>> {
+        refundable_submission_deposits(&self.chain_api(false), &self.account_id()).await
+    }
+
+    /// The expected block time in milliseconds, from BABE or twice the timestamp minimum period
+    pub fn block_time(&self) -> u64 {
+        let constant = |pallet: &str, name: &str| {
+            let address = subxt::dynamic::constant(pallet, name);
+            self.api
+                .constants()
+                .at(&address)
+                .ok()?
+                .to_value()
+                .ok()?
+                .as_u128()
+        };
+
+        constant("Babe", "ExpectedBlockTime")
+            .or_else(|| constant("Timestamp", "MinimumPeriod").map(|period| period * 2))
+            .map_or(DEFAULT_BLOCK_TIME, |block_time| block_time as u64)
+    }
+
+    /// Describe a block number with its estimated local time, relative to the `now` block
+    pub fn describe_block(&self, block: u32, now: u32) -> String {
+        if self.raw {
+            return format!("block {block}");
+        }
+
+        let offset_ms = (block as i64 - now as i64) * self.block_time() as i64;
+        let time = chrono::Local::now() + chrono::Duration::milliseconds(offset_ms);
+        let duration = humanize_duration(offset_ms.unsigned_abs());
+        let relative = match block.cmp(&now) {
+            std::cmp::Ordering::Greater => format!("in ~{duration}"),
+            std::cmp::Ordering::Less => format!("~{duration} ago"),
+            std::cmp::Ordering::Equal => "now".to_string(),
+        };
+
+        format!(
+            "block {block} (~{}, {relative})",
+            time.format("%Y-%m-%d %H:%M:%S")
+        )
+    }
+
+    /// Describe a number of blocks with its estimated duration
+    pub fn describe_period(&self, blocks: u32) -> String {
+        if self.raw {
+            return format!("{blocks} blocks");
+        }
+
+        let duration = humanize_duration(blocks as u64 * self.block_time());
+        format!("{blocks} blocks (~{duration})")
+    }
+
+    /// Describe the enactment moment of a referendum
+    pub fn describe_enactment(&self, enactment: &DispatchTime<u32>, now: u32) -> String {
+        match enactment {
+            DispatchTime::At(block) => format!("at {}", self.describe_block(*block, now)),
+            DispatchTime::After(blocks) => {
+                format!("{} after approval", self.describe_period(*blocks))
+            }
+        }
+    }
+
+    /// The account signing transactions
+    pub fn account_id(&self) -> AccountId32 {
+        self.signer.public_key().into()
+    }
+
+    /// Format an account as an ss58 address using the configured prefix
+    pub fn address(&self, account: &AccountId32) -> String {
+        self.properties.format_address(account)
+    }
+
+    /// Format a balance in the chain's token units
+    pub fn balance(&self, amount: u128) -> String {
+        self.properties.format_balance(amount)
+    }
+
+    /// Amount of a balance argument in planck, in the chain's token units
+    pub fn planck(&self, balance: &Balance) -> Result<u128> {
+        balance.planck(&self.properties).map_err(|source| {
+            CliError::Validation {
+                what: "balance".into(),
+                source,
+            }
+            .into()
+        })
+    }
+
+    /// Show the call, signer, amount held and estimated fee of a transaction and ask the user for
+    /// confirmation, unless `--yes` was passed
+    pub async fn confirm_transaction(
+        &self,
+        tx: &impl TxPayload,
+        amount: Option<(&str, u128)>,
+    ) -> Result<()> {
+        let fee = self.check_balance(tx, amount).await?;
+        if self.assume_yes {
+            return Ok(());
+        }
+
+        let call = self.call_name(&self.api.tx().call_data(tx)?);
+        print!(self, "call: {call}");
+        print!(self, "signer: {}", self.address(&self.account_id()));
+        if let Some((label, amount)) = amount {
+            print!(self, "{label}: {}", self.balance(amount));
+        }
+        print!(self, "estimated fee: {}", self.balance(fee));
+        if !self.confirm("submit?")? {
+            anyhow::bail!("aborted");
+        }
+        Ok(())
+    }
+
+    /// Check that the free balance of the signer covers the amount a transaction holds, such as a
+    /// deposit or a vote lock, plus its estimated fee, returning the fee. The locks overlap, so
+    /// the balance already locked, e.g. by other votes, can be locked again, but the fee is paid
+    /// from the unlocked part. With `--force`, an insufficient balance is only a warning and the
+    /// chain decides
+    pub async fn check_balance(
+        &self,
+        tx: &impl TxPayload,
+        amount: Option<(&str, u128)>,
+    ) -> Result<u128> {
+        let fee = self
+            .api
+            .tx()
+            .create_signed(tx, &self.signer, Default::default())
+            .await?
+            .partial_fee_estimate()
+            .await?;
+        let (free, frozen) = self.account_balance().await?;
+        let held = amount.map_or(0, |(_, amount)| amount);
+        let address = self.address(&self.account_id());
+        let shortfall = if held.saturating_add(fee) > free {
+            let needed = match amount {
+                Some((label, amount)) => format!(
+                    "the {label} of {} plus the estimated fee of {}",
+                    self.balance(amount),
+                    self.balance(fee)
+                ),
+                None => format!("the estimated fee of {}", self.balance(fee)),
+            };
+            format!(
+                "the free balance of {address} is {}, less than {needed}",
+                self.balance(free)
+            )
+        } else if free.saturating_sub(frozen) < fee {
+            format!(
+                "{} of the free balance of {address} is locked, leaving {} for the estimated fee of {}",
+                self.balance(frozen),
+                self.balance(free.saturating_sub(frozen)),
+                self.balance(fee)
+            )
+        } else {
+            return Ok(fee);
+        };
+
+        if self.force {
+            warning!(self, "{shortfall}, submitting anyway");
+            return Ok(fee);
+        }
+        Err(CliError::InsufficientBalance(shortfall).into())
+    }
+
+    /// Free balance of the signing account
+    pub async fn free_balance(&self) -> Result<u128> {
+        Ok(self.account_balance().await?.0)
+    }
+
+    /// Free and frozen balances of the signing account, the frozen balance being held by the
+    /// largest of its locks and freezes
+    pub async fn account_balance(&self) -> Result<(u128, u128)> {
+        let account = dynamic::account(&self.account_id());
+        let Some(info) = self.storage().await?.fetch(&account).await? else {
+            return Ok((0, 0));
+        };
+        let info = info.to_value()?;
+        let field = |name: &str| {
+            info.at("data")
+                .and_then(|data| data.at(name))
+                .and_then(|value| value.as_u128())
+        };
+        let free = field("free").ok_or_else(|| {
+            anyhow::anyhow!("no free balance in the System::Account of the signer")
+        })?;
+        // older runtimes freeze the balance for the fees and the other reasons separately
+        let frozen = field("frozen")
+            .or_else(|| field("misc_frozen"))
+            .unwrap_or_default();
+        Ok((free, frozen))
+    }
+
+    /// Name of an encoded call, as `Pallet::call`
+    pub fn call_name(&self, call_data: &[u8]) -> String {
+        let metadata = self.api.metadata();
+        // call data shorter than its pallet and call indices is an unknown call
+        call_data
+            .first()
+            .and_then(|index| metadata.pallet_by_index(*index))
+            .and_then(|pallet| {
+                let call = pallet.call_variant_by_index(*call_data.get(1)?)?;
+                Some(format!("{}::{}", pallet.name(), call.name))
+            })
+            .unwrap_or_else(|| "unknown call".to_string())
+    }
+
+    /// The deposit reserved when creating a proposal with the detected governance version
+    pub fn proposal_deposit(&self) -> Result<u128> {
+        match self.governance()? {
+            Governance::Democracy => Ok(PROPOSAL_DEPOSIT),
+            Governance::OpenGov => Ok(self
+                .api
+                .constants()
+                .at(&kitchensink::constants().referenda().submission_deposit())?),
+        }
+    }
+
+    /// Ask the user for confirmation, unless `--assume-yes` was passed
+    pub fn confirm(&self, question: &str) -> Result<bool> {
+        if self.assume_yes {
+            return Ok(true);
+        }
+
+        eprint!("[{}] {question} [y/N] ", self.user);
+        std::io::stderr().flush()?;
+        let mut answer = String::new();
+        std::io::stdin().read_line(&mut answer)?;
+        // the prompt blocks the runtime thread, ctrl-c is only seen once it returns
+        if shutdown::is_requested() {
+            Err(CliError::Interrupted)?;
+        }
+        Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+    }
+
+    /// Ensure the given pallet exists on the connected chain
+    pub fn require_pallet(&self, name: &str) -> Result<()> {
+        match self.api.metadata().pallet_by_name(name) {
+            Some(_) => Ok(()),
+            None => anyhow::bail!("this command requires the {name} pallet, which is not available on the connected chain"),
+        }
+    }
+
+    /// Subscribe to the finalized blocks and their events, through the bus shared with the other
+    /// consumers of the program
+    pub fn finalized_blocks(
+        &self,
+    ) -> impl Stream<Item = Result<Arc<FinalizedEvents<C>>>> + Send + 'static {
+        self.bus.subscribe()
+    }
+
+    /// Subscribe to the blocks finalized after a block and their events, starting with the
+    /// already finalized ones, through a subscription of its own
+    pub fn finalized_blocks_after(
+        &self,
+        block: u32,
+    ) -> impl Stream<Item = Result<Arc<FinalizedEvents<C>>>> + Send + 'static {
+        let api = self.api.clone();
+        subscription::finalized_blocks(&self.api, &self.rpc, Some(block)).and_then(move |block| {
+            let api = api.clone();
+            async move { Ok(Arc::new(bus::with_events(&api, block).await?)) }
+        })
+    }
+
+    /// Fetch and decode the entries of a storage map at the snapshot of the command, with their
+    /// keys. The progress is shown by a spinner when the map spans several pages
+    pub async fn iter_storage<Address: StorageAddress<IsIterable = Yes>>(
+        &self,
+        address: &Address,
+    ) -> Result<Vec<(Vec<u8>, Address::Target)>> {
+        self.decode_storage_map(address, false).await
+    }
+
+    /// Same as [`Self::iter_storage`] for the list and report commands, the referenda and votes
+    /// being read from the local cache when built with the `cache` feature, see [`cache`]
+    pub async fn iter_cached_storage<Address: StorageAddress<IsIterable = Yes>>(
+        &self,
+        address: &Address,
+    ) -> Result<Vec<(Vec<u8>, Address::Target)>> {
+        self.decode_storage_map(address, true).await
+    }
+
+    // Entries of a storage map decoded with the metadata, see `Self::raw_storage_map`
+    async fn decode_storage_map<Address: StorageAddress<IsIterable = Yes>>(
+        &self,
+        address: &Address,
+        cached: bool,
+    ) -> Result<Vec<(Vec<u8>, Address::Target)>> {
+        let metadata = self.api.metadata();
+        let value_type = chain_api::value_type(address, &metadata)?;
+        let prefix = chain_api::storage_key(address, &metadata)?;
+        self.raw_storage_map(prefix, cached)
+            .await?
+            .into_iter()
+            .map(|(key, value)| {
+                let value =
+                    Address::Target::decode_with_metadata(&mut &value[..], value_type, &metadata)?;
+                Ok((key, value))
+            })
+            .collect()
+    }
+
+    // Raw entries stored under a key prefix at the snapshot of the command. With the `cache`
+    // feature, the cached maps are read from the cache when `cached`, unless `--refresh`, and
+    // saved to it when read from the chain
+    #[cfg_attr(not(feature = "cache"), allow(unused_variables))]
+    async fn raw_storage_map(
+        &self,
+        prefix: Vec<u8>,
+        cached: bool,
+    ) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        let name = chain_api::entry_name(&self.api.metadata(), &prefix)
+            .unwrap_or_else(|| "storage".to_string());
+        // only whole maps are cached, not the entries under some of their keys
+        #[cfg(feature = "cache")]
+        let cache = self
+            .cache
+            .as_deref()
+            .filter(|_| prefix.len() == 32 && cache::is_cached(&name));
+        #[cfg(feature = "cache")]
+        if cached {
+            if let Some(entries) = self.cached_entries(&prefix).await? {
+                return Ok(entries);
+            }
+        }
+
+        let block = self.snapshot().await?;
+        let spinner = Spinner::new(&format!("fetching {name}"));
+        let pages = self.raw_storage_pages(prefix, STORAGE_PAGE_SIZE, block.hash);
+        tokio::pin!(pages);
+
+        let mut entries = Vec::new();
+        while let Some(page) = pages.try_next().await? {
+            entries.extend(page);
+            spinner.set_message(&format!("fetching {name} ({} entries)", entries.len()));
+        }
+        spinner.finish();
+        tracing::debug!(entries = entries.len(), "fetched {name}");
+
+        #[cfg(feature = "cache")]
+        if let Some(cache) = cache {
+            if let Err(err) = cache.store(&name, block.number, &entries) {
+                output::warn(&format!("failed to cache {name}: {err:#}"));
+            }
+        }
+        Ok(entries)
+    }
+
+    // Raw entries of a whole map read from the cache, unless `--refresh`, `None` when it isn't
+    // cached or its entries are too old for the snapshot of the command
+    #[cfg(feature = "cache")]
+    async fn cached_entries(&self, prefix: &[u8]) -> Result<Option<Vec<(Vec<u8>, Vec<u8>)>>> {
+        let Some(name) = chain_api::entry_name(&self.api.metadata(), prefix) else {
+            return Ok(None);
+        };
+        let Some(cache) = self
+            .cache
+            .as_deref()
+            .filter(|cache| prefix.len() == 32 && cache::is_cached(&name) && !cache.refresh())
+        else {
+            return Ok(None);
+        };
+
+        let block = self.snapshot().await?;
+        match cache.entries(&name)? {
+            // the commands combine the entries with the current block, e.g. for the blocks
+            // left, they can't be much older
+            Some((cached, entries)) if block.number.saturating_sub(cached) <= MAX_CACHE_LAG => {
+                tracing::info!(
+                    block = cached,
+                    entries = entries.len(),
+                    "read {name} from the cache"
+                );
+                Ok(Some(entries))
+            }
+            Some((cached, _)) => {
+                tracing::info!(block = cached, "{name} cached at block {cached} is stale");
+                Ok(None)
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Stream the decoded entries of a storage map at the snapshot of the command, with their keys.
+    /// The keys are listed by pages of `page_size`, the values of a few pages being fetched
+    /// concurrently, and the entries are yielded in order as their page arrives
+    pub fn stream_storage<'a, Address: StorageAddress<IsIterable = Yes>>(
+        &'a self,
+        address: &'a Address,
+        page_size: u32,
+    ) -> impl Stream<Item = Result<(Vec<u8>, Address::Target)>> + 'a {
+        let pages = async move {
+            let metadata = self.api.metadata();
+            let value_type = chain_api::value_type(address, &metadata)?;
+            let prefix = chain_api::storage_key(address, &metadata)?;
+            let at = self.snapshot().await?.hash;
+            let entries = self
+                .raw_storage_pages(prefix, page_size, at)
+                .map_ok(move |page| {
+                    let entries = page.into_iter().map(|(key, value)| {
+                        let value = Address::Target::decode_with_metadata(
+                            &mut &value[..],
+                            value_type,
+                            &metadata,
+                        )?;
+                        anyhow::Ok((key, value))
+                    });
+                    stream::iter(entries.collect::<Vec<_>>())
+                });
+            anyhow::Ok(entries.try_flatten())
+        };
+
+        stream::once(pages).try_flatten()
+    }
+
+    /// Same as [`Self::stream_storage`] for the report commands, the referenda and votes being
+    /// read from the local cache when built with the `cache` feature, see [`cache`]. The entries
+    /// streamed from the chain aren't saved to the cache, which would hold them all in memory
+    pub fn stream_cached_storage<'a, Address: StorageAddress<IsIterable = Yes>>(
+        &'a self,
+        address: &'a Address,
+        page_size: u32,
+    ) -> impl Stream<Item = Result<(Vec<u8>, Address::Target)>> + 'a {
+        let entries = async move {
+            let metadata = self.api.metadata();
+            let prefix = chain_api::storage_key(address, &metadata)?;
+            #[cfg(feature = "cache")]
+            let cached = self.cached_entries(&prefix).await?;
+            #[cfg(not(feature = "cache"))]
+            let cached: Option<Vec<(Vec<u8>, Vec<u8>)>> = None;
+
+            let entries = match cached {
+                Some(entries) => {
+                    let value_type = chain_api::value_type(address, &metadata)?;
+                    let entries = entries.into_iter().map(move |(key, value)| {
+                        let value = Address::Target::decode_with_metadata(
+                            &mut &value[..],
+                            value_type,
+                            &metadata,
+                        )?;
+                        anyhow::Ok((key, value))
+                    });
+                    stream::iter(entries).left_stream()
+                }
+                None => self.stream_storage(address, page_size).right_stream(),
+            };
+            anyhow::Ok(entries)
+        };
+
+        stream::once(entries).try_flatten()
+    }
+
+    // Pages of the raw entries stored under a key prefix at a block, the keys being listed by
+    // pages of `page_size` and the values of a few pages fetched concurrently
+    fn raw_storage_pages(
+        &self,
+        prefix: Vec<u8>,
+        page_size: u32,
+        at: C::Hash,
+    ) -> impl Stream<Item = Result<Vec<(Vec<u8>, Vec<u8>)>>> {
+        let rpc = LegacyRpcMethods::<C>::new(self.rpc.clone());
+
+        // the start key of the next page, `None` once the last page is listed
+        let keys = stream::try_unfold(Some(None::<Vec<u8>>), {
+            let rpc = rpc.clone();
+            move |start| {
+                let (rpc, prefix) = (rpc.clone(), prefix.clone());
+                async move {
+                    let Some(start) = start else {
+                        return anyhow::Ok(None);
+                    };
+                    let keys = rpc
+                        .state_get_keys_paged(&prefix, page_size, start.as_deref(), Some(at))
+                        .await?;
+                    if keys.is_empty() {
+                        return Ok(None);
+                    }
+                    let next = (keys.len() == page_size as usize).then(|| keys.last().cloned());
+                    Ok(Some((keys, next)))
+                }
+            }
+        });
+        keys.map_ok(move |keys| {
+            let rpc = rpc.clone();
+            async move {
+                let changes = rpc
+                    .state_query_storage_at(keys.iter().map(Vec::as_slice), Some(at))
+                    .await?;
+                // the keys removed between the listing and the query have no value
+                anyhow::Ok(
+                    changes
+                        .into_iter()
+                        .flat_map(|set| set.changes)
+                        .filter_map(|(key, value)| Some((key.0, value?.0)))
+                        .collect::<Vec<_>>(),
+                )
+            }
+        })
+        .try_buffered(STORAGE_CONCURRENCY)
+    }
+
+    /// Fetch and decode the values at several storage addresses at the snapshot of the command, in
+    /// the order of the addresses. The values are queried together by `state_queryStorageAt`, in
+    /// a single request up to [`STORAGE_PAGE_SIZE`] addresses
+    pub async fn fetch_many<Address: StorageAddress<IsFetchable = Yes>>(
+        &self,
+        addresses: &[Address],
+    ) -> Result<Vec<Option<Address::Target>>> {
+        let Some(first) = addresses.first() else {
+            return Ok(Vec::new());
+        };
+        let metadata = self.api.metadata();
+        let value_type = chain_api::value_type(first, &metadata)?;
+        let keys = addresses
+            .iter()
+            .map(|address| chain_api::storage_key(address, &metadata))
+            .collect::<Result<Vec<_>>>()?;
+        let at = self.snapshot().await?.hash;
+
+        let rpc = LegacyRpcMethods::<C>::new(self.rpc.clone());
+        let pages = keys
+            .chunks(STORAGE_PAGE_SIZE as usize)
+            .map(|keys| rpc.state_query_storage_at(keys.iter().map(Vec::as_slice), Some(at)));
+        let values = future::try_join_all(pages)
+            .await?
+            .into_iter()
+            .flatten()
+            .flat_map(|set| set.changes)
+            .filter_map(|(key, value)| Some((key.0, value?.0)))
+            .collect::<BTreeMap<_, _>>();
+        keys.iter()
+            .map(|key| {
+                let Some(value) = values.get(key) else {
+                    return Ok(None);
+                };
+                let value =
+                    Address::Target::decode_with_metadata(&mut &value[..], value_type, &metadata)?;
+                Ok(Some(value))
+            })
+            .collect()
+    }
+
+    // Storage at the snapshot as read by the program, from the local cache when `cached`, see
+    // `Self::raw_storage_map`
+    pub(crate) fn chain_api(&self, cached: bool) -> ProgramStorage<'_, C> {
+        ProgramStorage {
+            program: self,
+            cached,
+        }
+    }
+
+    /// Wait for a specific event to occur
+    pub async fn wait_for_event<Ev: StaticEvent>(&self) -> Result<Ev> {
+        let (_, event) = self.wait_for_event_where::<Ev>(|_| true).await?;
+        Ok(event)
+    }
+
+    /// Wait for an event matching the filter, returning it with its block number
+    pub async fn wait_for_event_where<Ev: StaticEvent>(
+        &self,
+        filter: impl Fn(&Ev) -> bool + Sync,
+    ) -> Result<(u32, Ev)> {
+        let name = format!("{}::{}", Ev::PALLET, Ev::EVENT);
+        self.wait_for_events(&name, |events| {
+            for event in events.find::<Ev>() {
+                let event = event?;
+                if filter(&event) {
+                    return Ok(Some(event));
+                }
+            }
+            Ok(None)
+        })
+        .await
+    }
+
+    /// Wait for the first finalized block whose events `find` picks something from, such as one
+    /// of several events, returning it with the block number. `name` describes what is awaited
+    pub async fn wait_for_events<T: Send>(
+        &self,
+        name: &str,
+        find: impl Fn(&events::Events<C>) -> Result<Option<T>> + Sync,
+    ) -> Result<(u32, T)> {
+        let find = &find;
+        let mut events = self
+            .finalized_blocks()
+            .try_filter_map(|block| async move {
+                tracing::debug!(block = block.number, "looking for {name}");
+                Ok(find(&block.events)?.map(|found| (block.number, found)))
+            })
+            .boxed();
+
+        let wait = async {
+            let next = events.try_next();
+            let event =
+                match self.timeout {
+                    Some(timeout) => tokio::time::timeout(timeout, next).await.map_err(|_| {
+                        CliError::Timeout {
+                            event: name.to_string(),
+                            secs: timeout.as_secs(),
+                        }
+                    })?,
+                    None => next.await,
+                };
+            anyhow::Ok(event?)
+        };
+        let (event, elapsed) = self
+            .with_spinner(&format!("waiting for {name}"), wait)
+            .await;
+        let (block, event) = event?.ok_or_else(|| {
+            CliError::Connection(anyhow::anyhow!(
+                "the block subscription ended before {name}"
+            ))
+        })?;
+        print!(
+            self,
+            "{name} found in block {block} after {}",
+            humanize_duration(elapsed.as_millis() as u64)
+        );
+        Ok((block, event))
+    }
+
+    /// Wait for a block to be finalized, returning at once if it already is. The `--timeout`
+    /// doesn't apply, the block was chosen by the user
+    pub async fn wait_for_block(&self, number: u32) -> Result<()> {
+        let now = self.snapshot().await?.number;
+        if now >= number {
+            return Ok(());
+        }
+
+        print!(
+            self,
+            "waiting for block {}",
+            self.describe_block(number, now)
+        );
+        let mut blocks = self
+            .finalized_blocks()
+            .try_filter(|block| std::future::ready(block.number >= number))
+            .boxed();
+        let (block, _) = self
+            .with_spinner(&format!("waiting for block {number}"), blocks.try_next())
+            .await;
+        block?.ok_or_else(|| {
+            CliError::Connection(anyhow::anyhow!(
+                "the block subscription ended before block {number}"
+            ))
+        })?;
+        Ok(())
+    }
+
+    /// Run a future with a spinner showing the finalized block height and the elapsed time,
+    /// returning its output with the time it took
+    pub async fn with_spinner<T>(
+        &self,
+        message: &str,
+        future: impl std::future::Future<Output = T>,
+    ) -> (T, std::time::Duration) {
+        // the chain moves on during the wait, the next reads see the blocks finalized meanwhile
+        let future = async {
+            let output = future.await;
+            self.unpin();
+            output
+        };
+        let spinner = Spinner::new(message);
+        if !spinner.is_enabled() {
+            print!(self, "{message}...");
+            let output = future.await;
+            return (output, spinner.finish());
+        }
+
+        let mut heights = self
+            .finalized_blocks()
+            .filter_map(|block| async move { block.ok().map(|block| block.number) })
+            .boxed();
+        tokio::pin!(future);
+        loop {
+            tokio::select! {
+                output = &mut future => return (output, spinner.finish()),
+                Some(block) = heights.next() => spinner.set_block(block),
+            }
+        }
+    }
+
+    /// Encode the given call data
+    pub fn encode_call(&self, call: &CallData) -> Result<Vec<u8>> {
+        encode_call(call, &self.api.metadata())
+    }
+
+    /// Note a preimage of an encoded call, returning its hash and length
+    pub async fn note_preimage(&self, image: Vec<u8>) -> Result<(H256, u32)> {
+        let (image_hash, image_len) = call_hash(&image);
+
+        // don't flood the terminal with large images such as runtime blobs
+        if image.len() <= 256 {
+            print!(self, "adding image: {}", hex::encode(&image));
+        } else {
+            print!(self, "adding image of {image_len} bytes");
+        }
+
+        let deposit = self.preimage_deposit(image_len)?;
+        let confirm = || {
+            warning!(
+                self,
+                "preimage ({image_hash:?}, {image_len}) will reserve a deposit of {}",
+                self.balance(deposit)
+            );
+            if !self.confirm("continue?")? {
+                anyhow::bail!("aborted");
+            }
+            Ok(())
+        };
+
+        let deposit = Some(("deposit", deposit));
+        if self.dynamic {
+            let tx = dynamic::note_preimage(image);
+            self.check_balance(&tx, deposit).await?;
+            confirm()?;
+            self.submit_and_watch(&tx).await?;
+        } else {
+            let tx = kitchensink::tx().preimage().note_preimage(image);
+            self.check_balance(&tx, deposit).await?;
+            confirm()?;
+            self.submit_and_watch(&tx).await?;
+        }
+        self.output.record(
+            "preimage",
+            json!({ "hash": image_hash, "len": image_len }),
+            format!("preimage created ({image_hash:?}, {image_len})"),
+        );
+
+        Ok((image_hash, image_len))
+    }
+
+    /// Fetch the bytes of a noted preimage
+    pub async fn fetch_preimage(&self, hash: H256, len: u32) -> Result<Option<Vec<u8>>> {
+        let query = kitchensink::storage().preimage().preimage_for(hash, len);
+        let image = self.storage().await?.fetch(&query).await?;
+        Ok(image.map(|image| image.0))
+    }
+
+    /// Check that the preimage of `hash` is noted with `len` bytes before proposing it, a proposal
+    /// of a missing preimage or with another length can't be enacted
+    pub async fn check_preimage(&self, hash: H256, len: u32) -> Result<()> {
+        let api = self.storage().await?;
+        let storage = kitchensink::storage().preimage();
+        let noted = match api.fetch(&storage.request_status_for(hash)).await? {
+            Some(RequestStatus::Unrequested { len, .. }) => Some(Some(len)),
+            Some(RequestStatus::Requested { maybe_len, .. }) => Some(maybe_len),
+            None => match api.fetch(&storage.status_for(hash)).await? {
+                Some(OldRequestStatus::Unrequested { len, .. }) => Some(Some(len)),
+                Some(OldRequestStatus::Requested { len, .. }) => Some(len),
+                None => None,
+            },
+        };
+
+        let problem = match noted {
+            Some(Some(noted)) if noted == len => return Ok(()),
+            Some(Some(noted)) => {
+                format!("it is {noted} bytes, not {len}, propose it with a length of {noted}")
+            }
+            Some(None) => {
+                "it is requested but not noted yet, note it before proposing it".to_string()
+            }
+            None => "it isn't noted, note it before proposing it, e.g. with note-preimage-hex"
+                .to_string(),
+        };
+        Err(CliError::Validation {
+            what: format!("preimage {hash:?}"),
+            source: anyhow::anyhow!(problem),
+        }
+        .into())
+    }
+
+    /// Check that the on-chain preimage of `hash` is byte-for-byte equal to the local call data
+    pub async fn verify_preimage(&self, hash: H256, local: &[u8]) -> Result<()> {
+        let (local_hash, local_len) = call_hash(local);
+        if local_hash != hash {
+            warning!(
+                self,
+                "local call data hashes to {local_hash:?}, not {hash:?}"
+            );
+        }
+
+        let Some(image) = self.fetch_preimage(hash, local_len).await? else {
+            let noted_len = self
+                .preimages()
+                .await?
+                .into_iter()
+                .find(|entry| entry.hash == hash)
+                .and_then(|entry| entry.len);
+            match noted_len {
+                Some(len) => anyhow::bail!(
+                    "mismatch: on-chain preimage is {len} bytes, local call data is {local_len} bytes"
+                ),
+                None => anyhow::bail!("no preimage noted for {hash:?}"),
+            }
+        };
+
+        if let Some(offset) = image.iter().zip(local).position(|(a, b)| a != b) {
+            anyhow::bail!(
+                "mismatch: preimages differ from byte {offset} (on-chain 0x{}, local 0x{})",
+                hex::encode(&image[offset..]),
+                hex::encode(&local[offset..])
+            );
+        }
+        if image.len() != local.len() {
+            anyhow::bail!(
+                "mismatch: on-chain preimage is {} bytes, local call data is {} bytes",
+                image.len(),
+                local.len()
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Fetch all the preimages known to the preimage pallet
+    pub async fn preimages(&self) -> Result<Vec<PreimageEntry>> {
+        preimages(&self.chain_api(false)).await
+    }
+
+    /// Estimate the deposit held when noting a preimage of the given length
+    pub fn preimage_deposit(&self, len: u32) -> Result<u128> {
+        // kitchensink's `PreimageBaseDeposit` and `PreimageByteDeposit`, used when the runtime
+        // doesn't expose them as constants
+        const BASE_DEPOSIT: u128 = 100_000_000_000_000;
+        const BYTE_DEPOSIT: u128 = 1_000_000_000_000;
+
+        let constant = |name: &str, default: u128| -> Result<u128> {
+            let address = subxt::dynamic::constant("Preimage", name);
+            match self.api.constants().at(&address) {
+                Ok(value) => value
+                    .to_value()?
+                    .as_u128()
+                    .ok_or_else(|| anyhow::anyhow!("invalid Preimage::{name} constant")),
+                Err(_) => Ok(default),
+            }
+        };
+
+        let base = constant("BaseDeposit", BASE_DEPOSIT)?;
+        let per_byte = constant("ByteDeposit", BYTE_DEPOSIT)?;
+        Ok(base + per_byte * len as u128)
+    }
+
+    /// The call creating a proposal for a noted preimage, using the detected governance version
+    pub fn proposal_call(&self, hash: H256, len: u32) -> Result<kitchensink::Call> {
+        let proposal = Bounded::Lookup { hash, len };
+        let call = match self.governance()? {
+            Governance::Democracy => kitchensink::Call::Democracy(
+                kitchensink::runtime_types::pallet_democracy::pallet::Call::propose {
+                    proposal,
+                    value: PROPOSAL_DEPOSIT,
+                },
+            ),
+            Governance::OpenGov => kitchensink::Call::Referenda(
+                kitchensink::runtime_types::pallet_referenda::pallet::Call::submit {
+                    proposal_origin: Box::new(OriginCaller::system(RawOrigin::Root)),
+                    proposal,
+                    enactment_moment: DispatchTime::After(DEFAULT_ENACT_AFTER),
+                },
+            ),
+        };
+
+        Ok(call)
+    }
+
+    /// Vote of the signer on a referendum, as debug text, `None` when it didn't vote on it
+    pub async fn existing_vote(&self, index: u32) -> Result<Option<String>> {
+        let account = self.account_id();
+        match self.governance()? {
+            Governance::OpenGov => {
+                let query = kitchensink::storage()
+                    .conviction_voting()
+                    .voting_for_iter1(&account);
+                let entries = self.stream_storage(&query, STORAGE_PAGE_SIZE);
+                tokio::pin!(entries);
+                while let Some((_, voting)) = entries.try_next().await? {
+                    let CvVoting::Casting(casting) = voting else {
+                        continue;
+                    };
+                    if let Some((_, vote)) = casting.votes.0.into_iter().find(|(i, _)| *i == index)
+                    {
+                        return Ok(Some(format!("{vote:?}")));
+                    }
+                }
+                Ok(None)
+            }
+            Governance::Democracy => {
+                let query = kitchensink::storage().democracy().voting_of(&account);
+                let votes = match self.storage().await?.fetch(&query).await? {
+                    Some(DemocracyVoting::Direct { votes, .. }) => votes.0,
+                    _ => Vec::new(),
+                };
+                Ok(votes
+                    .into_iter()
+                    .find(|(i, _)| *i == index)
+                    .map(|(_, vote)| format!("{vote:?}")))
+            }
+        }
+    }
+
+    /// Fail when the signer already voted on a referendum, printing its vote, unless `replace`
+    pub async fn check_existing_vote(&self, index: u32, replace: bool) -> Result<()> {
+        let Some(vote) = self.existing_vote(index).await? else {
+            return Ok(());
+        };
+        if replace {
+            warning!(self, "replacing the vote on referendum #{index}: {vote}");
+            return Ok(());
+        }
+
+        print!(self, "existing vote on referendum #{index}: {vote}");
+        Err(CliError::Validation {
+            what: "vote".into(),
+            source: anyhow::anyhow!(
+                "already voted on referendum #{index}, pass --replace to overwrite the vote"
+            ),
+        }
+        .into())
+    }
+
+    /// Fail when a referendum doesn't accept votes anymore, or else warn when its current period
+    /// ends
+    pub async fn check_ongoing(&self, index: u32) -> Result<()> {
+        let now = self.snapshot().await?.number;
+        let api = self.storage().await?;
+        let (end, period) = match self.governance()? {
+            Governance::OpenGov => {
+                let query = kitchensink::storage()
+                    .referenda()
+                    .referendum_info_for(index);
+                let status = match api.fetch(&query).await? {
+                    Some(ReferendumInfo::Ongoing(status)) => status,
+                    Some(ReferendumInfo::Approved(end, ..)) => {
+                        return not_ongoing(index, "approved", end)
+                    }
+                    Some(ReferendumInfo::Rejected(end, ..)) => {
+                        return not_ongoing(index, "rejected", end)
+                    }
+                    Some(ReferendumInfo::Cancelled(end, ..)) => {
+                        return not_ongoing(index, "cancelled", end)
+                    }
+                    Some(ReferendumInfo::TimedOut(end, ..)) => {
+                        return not_ongoing(index, "timed out", end)
+                    }
+                    Some(ReferendumInfo::Killed(end)) => return not_ongoing(index, "killed", end),
+                    None => return Err(no_referendum(index)),
+                };
+                let track = self.track(status.track)?;
+                match status.deciding {
+                    // a queued referendum waits for a deciding slot, without deadline
+                    None if status.in_queue => return Ok(()),
+                    None => (
+                        status.submitted.saturating_add(track.prepare_period),
+                        "prepare",
+                    ),
+                    Some(DecidingStatus {
+                        confirming: None,
+                        since,
+                    }) => (since.saturating_add(track.decision_period), "decision"),
+                    Some(DecidingStatus {
+                        confirming: Some(end),
+                        ..
+                    }) => (end, "confirm"),
+                }
+            }
+            Governance::Democracy => {
+                let query = kitchensink::storage().democracy().referendum_info_of(index);
+                match api.fetch(&query).await? {
+                    Some(DemocracyReferendumInfo::Ongoing(status)) => (status.end, "voting"),
+                    Some(DemocracyReferendumInfo::Finished { approved, end }) => {
+                        let outcome = if approved { "approved" } else { "rejected" };
+                        return not_ongoing(index, outcome, end);
+                    }
+                    None => return Err(no_referendum(index)),
+                }
+            }
+        };
+
+        warning!(
+            self,
+            "the {period} period of referendum #{index} ends at {}, {} blocks left",
+            self.describe_block(end, now),
+            end.saturating_sub(now)
+        );
+        Ok(())
+    }
+
+    /// Check that an ongoing referendum proposes the local call data, resolving the preimage of
+    /// its proposal unless the call is inline
+    pub async fn verify_referendum_call(&self, index: u32, local: &[u8]) -> Result<()> {
+        let api = self.storage().await?;
+        let (hash, inline) = match self.governance()? {
+            Governance::OpenGov => {
+                let query = kitchensink::storage()
+                    .referenda()
+                    .referendum_info_for(index);
+                match api.fetch(&query).await? {
+                    Some(ReferendumInfo::Ongoing(status)) => bounded_call(&status.proposal),
+                    Some(_) => anyhow::bail!("referendum #{index} isn't ongoing"),
+                    None => return Err(no_referendum(index)),
+                }
+            }
+            Governance::Democracy => {
+                let query = kitchensink::storage().democracy().referendum_info_of(index);
+                match api.fetch(&query).await? {
+                    Some(DemocracyReferendumInfo::Ongoing(status)) => {
+                        bounded_call(&status.proposal)
+                    }
+                    Some(_) => anyhow::bail!("referendum #{index} isn't ongoing"),
+                    None => return Err(no_referendum(index)),
+                }
+            }
+        };
+
+        let (local_hash, _) = call_hash(local);
+        if local_hash != hash {
+            anyhow::bail!(
+                "mismatch: referendum #{index} proposes {hash:?}, local call data hashes to {local_hash:?}"
+            );
+        }
+        if !inline {
+            self.verify_preimage(hash, local).await?;
+        }
+        Ok(())
+    }
+
+    /// Vote on a referendum with the detected governance version, locking the balance
+    pub async fn vote(
+        &self,
+        index: u32,
+        aye: bool,
+        conviction: Conviction,
+        balance: u128,
+    ) -> Result<ExtrinsicEvents<C>> {
+        let lock = Some(("lock", balance));
+        if self.governance()? == Governance::OpenGov {
+            let vote = create_cv_vote(index, aye, conviction, balance);
+            self.confirm_transaction(&vote, lock).await?;
+            self.submit_and_watch(&vote).await
+        } else if self.dynamic {
+            let vote = dynamic::vote(index, aye, conviction, balance);
+            self.confirm_transaction(&vote, lock).await?;
+            self.submit_and_watch(&vote).await
+        } else {
+            let vote = create_vote(index, aye, conviction, balance);
+            self.confirm_transaction(&vote, lock).await?;
+            self.submit_and_watch(&vote).await
+        }
+    }
+
+    /// Create a proposal for a noted preimage, using the detected governance version
+    pub async fn make_proposal(&self, hash: H256, len: u32) -> Result<()> {
+        if self.governance()? == Governance::OpenGov {
+            let enactment = DispatchTime::After(DEFAULT_ENACT_AFTER);
+            self.submit_referendum(hash, len, enactment).await?;
+            return Ok(());
+        }
+
+        self.check_preimage(hash, len).await?;
+        print!(self, "creating proposal for ({hash}, {len})");
+        let deposit = Some(("deposit", PROPOSAL_DEPOSIT));
+        let events = if self.dynamic {
+            let tx = dynamic::propose(hash, len, PROPOSAL_DEPOSIT);
+            self.confirm_transaction(&tx, deposit).await?;
+            self.submit_and_watch(&tx).await?
+        } else {
+            let runtime_call = Bounded::Lookup { hash, len };
+            let tx = kitchensink::tx()
+                .democracy()
+                .propose(runtime_call, PROPOSAL_DEPOSIT);
+            self.confirm_transaction(&tx, deposit).await?;
+            self.submit_and_watch(&tx).await?
+        };
+        success!(self, "proposal created {:?}", events);
+
+        let tabled = self
+            .wait_for_event::<kitchensink::democracy::events::Tabled>()
+            .await;
+        print!(self, "proposal tabled {:?}", tabled);
+
+        let started = self
+            .wait_for_event::<kitchensink::democracy::events::Started>()
+            .await;
+        print!(self, "proposal started {:?}", started);
+
+        Ok(())
+    }
+
+    /// Submit an OpenGov referendum for a noted preimage
+    pub async fn submit_referendum(
+        &self,
+        hash: H256,
+        len: u32,
+        enactment: DispatchTime<u32>,
+    ) -> Result<kitchensink::referenda::events::Submitted> {
+        let referenda = kitchensink::tx().referenda();
+        self.check_preimage(hash, len).await?;
+        let runtime_call = Bounded::Lookup { hash, len };
+        let origin = OriginCaller::system(RawOrigin::Root);
+
+        let now = self.snapshot().await?.number;
+        print!(
+            self,
+            "submitting referendum for ({hash}, {len}), enactment {}",
+            self.describe_enactment(&enactment, now)
+        );
+        let tx = referenda.submit(origin, runtime_call, enactment);
+        let deposit = self.proposal_deposit()?;
+        self.confirm_transaction(&tx, Some(("deposit", deposit)))
+            .await?;
+        let events = self.submit_and_watch(&tx).await?;
+        let submitted = events
+            .find_first::<kitchensink::referenda::events::Submitted>()?
+            .ok_or_else(|| CliError::MissingEvent("Referenda::Submitted".into()))?;
+        success!(self, "referendum submitted {:?}", submitted);
+
+        Ok(submitted)
+    }
+
+    /// Submit a call through `sudo.sudo` and wait for it to be finalized, failing when the call
+    /// failed as the `sudo` extrinsic itself succeeds
+    pub async fn sudo_and_watch(&self, call: kitchensink::Call) -> Result<ExtrinsicEvents<C>> {
+        let events = self.submit_and_watch(&sudo(call)).await?;
+        let sudid = events
+            .find_first::<kitchensink::sudo::events::Sudid>()?
+            .ok_or_else(|| CliError::MissingEvent("Sudo::Sudid".into()))?;
+        if let Err(error) = sudid.sudo_result {
+            let error = DispatchError::decode_from(error.encode(), self.api.metadata())?;
+            return Err(extrinsic_error(&events, error));
+        }
+        Ok(events)
+    }
+
+    /// Submit a transaction and wait for it to be finalized
+    pub async fn submit_and_watch(&self, tx: &impl TxPayload) -> Result<ExtrinsicEvents<C>> {
+        let account = self.account_id();
+        let call_data = self.api.tx().call_data(tx)?;
+        let submit = async {
+            // the extrinsic is searched after the finalized head when its watch is lost, the
+            // snapshot of the command may be far behind in the long-running loops
+            let from = self.api.blocks().at_latest().await?.number();
+            let nonce = self.api.tx().account_nonce(&account).await?;
+            let progress = self
+                .api
+                .tx()
+                .create_signed_with_nonce(tx, &self.signer, nonce, Default::default())?
+                .submit_and_watch()
+                .await?;
+            tracing::info!(hash = ?progress.extrinsic_hash(), nonce, "transaction submitted");
+            Ok::<_, subxt::Error>((from, nonce, progress))
+        };
+        let (from, nonce, progress) = submit
+            .await
+            .map_err(|err| CliError::Transaction(err.into()))?;
+
+        // chopsticks finalizes blocks as soon as they are built, the events are fetched whether
+        // the extrinsic succeeded or not so failures are audited too
+        let (events, elapsed) = if self.fork {
+            let wait = async { Ok(progress.wait_for_in_block().await?.fetch_events().await?) };
+            self.with_spinner("waiting for transaction to be in block", wait)
+                .await
+        } else {
+            let wait = self.wait_for_finalized(progress, nonce, from);
+            self.with_spinner("waiting for transaction to be finalized", wait)
+                .await
+        };
+        let events = events.map_err(CliError::Transaction)?;
+        if let Err(err) = self.audit(&call_data, nonce, &events) {
+            output::warn(&format!("failed to write the audit log: {err:#}"));
+        }
+        for event in events.iter() {
+            let event = event?;
+            if event.pallet_name() == "System" && event.variant_name() == "ExtrinsicFailed" {
+                let error = DispatchError::decode_from(event.field_bytes(), self.api.metadata())
+                    .map_err(|err| CliError::Decode {
+                        what: "the dispatch error".into(),
+                        source: err.into(),
+                    })?;
+                return Err(extrinsic_error(&events, error));
+            }
+        }
+        print!(
+            self,
+            "transaction {:?} in block {:?} after {}",
+            events.extrinsic_hash(),
+            events.block_hash(),
+            humanize_duration(elapsed.as_millis() as u64)
+        );
+
+        if self.output.is_json() {
+            self.output.record_json(
+                "extrinsic",
+                json!({
+                    "hash": events.extrinsic_hash(),
+                    "events": output::events(&events)?,
+                }),
+            );
+        }
+        Ok(events)
+    }
+
+    // Wait for a submitted extrinsic to be finalized. When its watch ends before, e.g. the
+    // connection dropped or the node dropped it after a re-org retracted its block, the extrinsic
+    // is searched in the blocks finalized after `from` instead
+    async fn wait_for_finalized(
+        &self,
+        mut progress: TxProgress<C, OnlineClient<C>>,
+        nonce: u64,
+        from: u32,
+    ) -> Result<ExtrinsicEvents<C>> {
+        let ext_hash = progress.extrinsic_hash();
+        let mut included = false;
+        let lost = loop {
+            match progress.next().await {
+                Some(Ok(TxStatus::InFinalizedBlock(block))) => {
+                    return Ok(block.fetch_events().await?)
+                }
+                Some(Ok(TxStatus::InBestBlock(_))) => included = true,
+                Some(Ok(TxStatus::NoLongerInBestBlock)) => {
+                    tracing::debug!(?ext_hash, "block of the extrinsic retracted");
+                }
+                Some(Ok(TxStatus::Invalid { message })) => {
+                    return Err(subxt::Error::from(TransactionError::Invalid(message)).into())
+                }
+                // never included, the node gave up on the extrinsic
+                Some(Ok(TxStatus::Dropped { message })) if !included => {
+                    return Err(subxt::Error::from(TransactionError::Dropped(message)).into())
+                }
+                Some(Ok(TxStatus::Error { message })) if !included => {
+                    return Err(subxt::Error::from(TransactionError::Error(message)).into())
+                }
+                Some(Ok(TxStatus::Dropped { message } | TxStatus::Error { message })) => {
+                    break message
+                }
+                Some(Ok(_)) => {}
+                Some(Err(err)) => break err.to_string(),
+                None => break "the watch ended".to_string(),
+            }
+        };
+
+        warning!(
+            self,
+            "lost track of extrinsic {ext_hash:?} ({lost}), searching the finalized blocks"
+        );
+        self.find_finalized_extrinsic(ext_hash, nonce, from).await
+    }
+
+    // Search the blocks finalized after `from` for an extrinsic of the signer, until it's found,
+    // its nonce is used by another extrinsic or `MAX_SEARCH_BLOCKS` blocks were searched
+    async fn find_finalized_extrinsic(
+        &self,
+        ext_hash: H256,
+        nonce: u64,
+        from: u32,
+    ) -> Result<ExtrinsicEvents<C>> {
+        let account = self.account_id();
+        let blocks = subscription::finalized_blocks(&self.api, &self.rpc, Some(from));
+        tokio::pin!(blocks);
+        for _ in 0..MAX_SEARCH_BLOCKS {
+            let Some(block) = blocks.next().await else {
+                anyhow::bail!("the finalized block subscription ended");
+            };
+            let block = self.api.blocks().at(block?.hash).await?;
+            for extrinsic in block.extrinsics().await?.iter() {
+                let extrinsic = extrinsic?;
+                // the hash of an extrinsic covers its length prefix, which `bytes` strips
+                if BlakeTwo256::hash_of(&extrinsic.bytes()) == ext_hash {
+                    print!(
+                        self,
+                        "found extrinsic {ext_hash:?} in finalized block {}",
+                        block.number()
+                    );
+                    return Ok(extrinsic.events().await?);
+                }
+            }
+            if block.account_nonce(&account).await? > nonce {
+                anyhow::bail!(
+                    "extrinsic {ext_hash:?} was dropped, another extrinsic used its nonce {nonce}"
+                );
+            }
+        }
+        anyhow::bail!(
+            "extrinsic {ext_hash:?} not found in the {MAX_SEARCH_BLOCKS} blocks finalized after block {from}"
+        )
+    }
+
+    /// Record a submitted extrinsic in the audit log
+    pub fn audit(&self, call_data: &[u8], nonce: u64, events: &ExtrinsicEvents<C>) -> Result<()> {
+        let Some(path) = &self.audit_log else {
+            return Ok(());
+        };
+
+        let entry = json!({
+            "time": chrono::Utc::now().to_rfc3339(),
+            "genesis_hash": self.api.genesis_hash(),
+            "call": self.call_name(call_data),
+            "call_data": format!("0x{}", hex::encode(call_data)),
+            "signer": self.address(&self.account_id()),
+            "nonce": nonce,
+            "extrinsic_hash": events.extrinsic_hash(),
+            "block_hash": events.block_hash(),
+            "events": output::events(events)?,
+        });
+        audit::append(path, &entry)
+    }
+
+    /// Notify an event of a daemon with the `notify` command of the profile, a failure is only
+    /// reported
+    pub async fn notify(&self, message: &str) {
+        if self.notify.is_empty() {
+            return;
+        }
+        if let Err(err) = notify::send(&self.notify, message).await {
+            warning!(self, "failed to notify: {err:#}");
+        }
+    }
+
+    /// Build a block at the given height on a chopsticks fork, skipping the blocks in between
+    pub async fn fast_forward(&self, to: u32) -> Result<()> {
+        if !self.fork {
+            anyhow::bail!(
+                "fast forwarding is only supported on chopsticks forks, use --fork-local"
+            );
+        }
+
+        let params = serde_json::json!({ "unsafeBlockHeight": to });
+        let head: H256 = self
+            .rpc
+            .request("dev_newBlock", rpc_params![params])
+            .await?;
+        self.unpin();
+        self.output.record(
+            "block",
+            json!({ "number": to, "hash": head }),
+            format!("built block {to} ({head:?})"),
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kitchensink::runtime_types::sp_arithmetic::fixed_point::FixedI64;
+    use crate::kitchensink::runtime_types::sp_arithmetic::per_things::Perbill;
+
+    // A curve value given as a percentage, in parts per billion
+    fn percent(value: u64) -> u64 {
+        value * BILLION / 100
+    }
+
+    #[test]
+    fn linear_decreasing_curve() {
+        let curve = Curve::LinearDecreasing {
+            length: Perbill(percent(50) as u32),
+            floor: Perbill(percent(10) as u32),
+            ceil: Perbill(percent(90) as u32),
+        };
+        assert_eq!(curve_threshold(&curve, 0), percent(90));
+        assert_eq!(curve_threshold(&curve, percent(25)), percent(50));
+        // the floor is reached at the end of the curve and kept until the end of the period
+        assert!(curve_threshold(&curve, percent(50) - 1) > percent(10));
+        assert_eq!(curve_threshold(&curve, percent(50)), percent(10));
+        assert_eq!(curve_threshold(&curve, percent(75)), percent(10));
+        assert_eq!(curve_threshold(&curve, BILLION), percent(10));
+
+        let flat = Curve::LinearDecreasing {
+            length: Perbill(0),
+            floor: Perbill(percent(10) as u32),
+            ceil: Perbill(percent(90) as u32),
+        };
+        assert_eq!(curve_threshold(&flat, 0), percent(10));
+    }
+
+    #[test]
+    fn stepped_decreasing_curve() {
+        let curve = Curve::SteppedDecreasing {
+            begin: Perbill(percent(80) as u32),
+            end: Perbill(percent(30) as u32),
+            step: Perbill(percent(10) as u32),
+            period: Perbill(percent(20) as u32),
+        };
+        assert_eq!(curve_threshold(&curve, 0), percent(80));
+        assert_eq!(curve_threshold(&curve, percent(20) - 1), percent(80));
+        assert_eq!(curve_threshold(&curve, percent(20)), percent(70));
+        assert_eq!(curve_threshold(&curve, percent(50)), percent(60));
+        // clamped to the end once the steps reach it
+        assert_eq!(curve_threshold(&curve, percent(100)), percent(30));
+
+        // the steps saturate below zero before the clamp
+        let steep = Curve::SteppedDecreasing {
+            begin: Perbill(percent(80) as u32),
+            end: Perbill(percent(30) as u32),
+            step: Perbill(percent(10) as u32),
+            period: Perbill(percent(10) as u32),
+        };
+        assert_eq!(curve_threshold(&steep, BILLION), percent(30));
+
+        let no_period = Curve::SteppedDecreasing {
+            begin: Perbill(percent(80) as u32),
+            end: Perbill(percent(30) as u32),
+            step: Perbill(percent(10) as u32),
+            period: Perbill(0),
+        };
+        assert_eq!(curve_threshold(&no_period, BILLION), percent(80));
+    }
+
+    #[test]
+    fn reciprocal_curve() {
+        let reciprocal = |factor: i64, x_offset: i64, y_offset: i64| Curve::Reciprocal {
+            factor: FixedI64(factor),
+            x_offset: FixedI64(x_offset),
+            y_offset: FixedI64(y_offset),
+        };
+        let (tenth, twentieth) = (BILLION as i64 / 10, BILLION as i64 / 20);
+
+        // 0.1 / (x + 0.1) - 0.05
+        let curve = reciprocal(tenth, tenth, -twentieth);
+        assert_eq!(curve_threshold(&curve, 0), percent(95));
+        assert_eq!(curve_threshold(&curve, percent(40)), percent(15));
+        assert_eq!(curve_threshold(&curve, BILLION), 40_909_090);
+
+        // clamped to 100% and 0%
+        let high = reciprocal(5 * tenth, tenth, 0);
+        assert_eq!(curve_threshold(&high, 0), BILLION);
+        let low = reciprocal(tenth, tenth, -(BILLION as i64));
+        assert_eq!(curve_threshold(&low, BILLION), 0);
+
+        // the curve isn't defined at or left of its asymptote
+        let negative = reciprocal(tenth, -(BILLION as i64), 0);
+        assert_eq!(curve_threshold(&negative, 0), BILLION);
+    }
+
+    #[test]
+    fn democracy_thresholds() {
+        use VoteThreshold::*;
+
+        // (threshold, ayes, nays, turnout, electorate, approved)
+        let cases = [
+            // a quarter of the electorate turned out, sqrt(25) = 5 against sqrt(100) = 10
+            (SuperMajorityApprove, 60, 40, 25, 100, false),
+            (SuperMajorityApprove, 70, 30, 25, 100, true),
+            (SuperMajorityAgainst, 40, 60, 25, 100, true),
+            (SuperMajorityAgainst, 30, 70, 25, 100, false),
+            (SimpleMajority, 51, 49, 25, 100, true),
+            (SimpleMajority, 50, 50, 25, 100, false),
+            // with a full turnout, the super majorities are simple majorities
+            (SuperMajorityApprove, 51, 49, 100, 100, true),
+            (SuperMajorityApprove, 50, 50, 100, 100, false),
+            (SuperMajorityAgainst, 51, 49, 100, 100, true),
+            (SuperMajorityAgainst, 49, 51, 100, 100, false),
+            // nobody voted
+            (SuperMajorityApprove, 0, 0, 0, 100, false),
+            (SuperMajorityAgainst, 10, 0, 0, 100, false),
+            (SimpleMajority, 10, 0, 0, 100, false),
+            // no electorate, as the pallet compares the rationals
+            (SuperMajorityApprove, 10, 20, 25, 0, true),
+            (SuperMajorityApprove, 0, 20, 25, 0, false),
+            (SuperMajorityAgainst, 20, 10, 25, 0, false),
+            (SimpleMajority, 20, 10, 25, 0, true),
+        ];
+        for (threshold, ayes, nays, turnout, electorate, approved) in cases {
+            assert_eq!(
+                democracy_approved(&threshold, ayes, nays, turnout, electorate),
+                approved,
+                "{threshold:?} with {ayes} ayes, {nays} nays, {turnout} turnout, {electorate} \
+                 electorate"
+            );
+        }
+    }
+
+    #[test]
+    fn humanized_durations() {
+        const MINUTE: u64 = 60_000;
+        const HOUR: u64 = 60 * MINUTE;
+        const DAY: u64 = 24 * HOUR;
+        assert_eq!(humanize_duration(0), "0 seconds");
+        // milliseconds are truncated
+        assert_eq!(humanize_duration(999), "0 seconds");
+        assert_eq!(humanize_duration(1_000), "1 second");
+        assert_eq!(humanize_duration(MINUTE - 1), "59 seconds");
+        // units roll over
+        assert_eq!(humanize_duration(MINUTE), "1 minute");
+        assert_eq!(humanize_duration(HOUR), "1 hour");
+        assert_eq!(humanize_duration(DAY), "1 day");
+        assert_eq!(
+            humanize_duration(2 * HOUR + 30 * MINUTE),
+            "2 hours 30 minutes"
+        );
+        // at most two units are kept, stopping at the first zero one
+        assert_eq!(
+            humanize_duration(DAY + HOUR + MINUTE + 1_000),
+            "1 day 1 hour"
+        );
+        assert_eq!(humanize_duration(2 * DAY + 5 * MINUTE), "2 days");
+    }
+}
@@ -0,0 +1,99 @@
+//! Light client connection, syncing the chain with an embedded smoldot node instead of trusting
+//! an RPC endpoint.
+//!
+//! Chain specs are read from `--chain-spec`, or from `chain-specs/<chain>.json` under the config
+//! dir. They can be exported from a trusted node with the `sync_state_genSyncSpec` RPC method.
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use subxt::backend::rpc::{RawRpcFuture, RawRpcSubscription, RawValue, RpcClient, RpcClientT};
+use subxt::error::RpcError;
+use subxt::ext::futures::StreamExt;
+use subxt_lightclient::{AddChainConfig, AddChainConfigJsonRpc, LightClientRpc};
+
+use crate::chain::Chain;
+use crate::config::Config;
+
+/// Read the chain spec of a chain, from the given path or the config dir
+pub fn chain_spec(chain: Chain, path: Option<&Path>) -> Result<String> {
+    let path = match path {
+        Some(path) => path.to_path_buf(),
+        None => Config::dir()
+            .context("no config dir to read the chain spec from")?
+            .join("chain-specs")
+            .join(format!("{}.json", chain.name())),
+    };
+
+    std::fs::read_to_string(&path)
+        .with_context(|| format!("failed to read chain spec {}", path.display()))
+}
+
+/// Start a light client for the given chain spec and connect to it
+pub fn connect(chain_spec: &str) -> Result<RpcClient> {
+    let config = AddChainConfig {
+        specification: chain_spec,
+        json_rpc: AddChainConfigJsonRpc::Enabled {
+            max_pending_requests: u32::MAX.try_into()?,
+            max_subscriptions: u32::MAX,
+        },
+        potential_relay_chains: std::iter::empty(),
+        database_content: "",
+        user_data: (),
+    };
+    let rpc = LightClientRpc::new(config)?;
+    Ok(RpcClient::new(LightRpc(rpc)))
+}
+
+// Adapter exposing the light client JSON-RPC interface to subxt
+struct LightRpc(LightClientRpc);
+
+// Error reported when the light client task is gone
+fn background_closed() -> RpcError {
+    RpcError::ClientError("light client background task closed".into())
+}
+
+impl RpcClientT for LightRpc {
+    fn request_raw<'a>(
+        &'a self,
+        method: &'a str,
+        params: Option<Box<RawValue>>,
+    ) -> RawRpcFuture<'a, Box<RawValue>> {
+        Box::pin(async move {
+            let params = params.map_or("[]".to_string(), |params| params.get().to_string());
+            let response = self
+                .0
+                .method_request(method.to_string(), params)
+                .map_err(|_| background_closed())?
+                .await
+                .map_err(|_| background_closed())?;
+
+            response.map_err(|err| RpcError::ClientError(Box::new(err)))
+        })
+    }
+
+    fn subscribe_raw<'a>(
+        &'a self,
+        sub: &'a str,
+        params: Option<Box<RawValue>>,
+        _unsub: &'a str,
+    ) -> RawRpcFuture<'a, RawRpcSubscription> {
+        Box::pin(async move {
+            let params = params.map_or("[]".to_string(), |params| params.get().to_string());
+            let (id, mut notifications) = self
+                .0
+                .subscription_request(sub.to_string(), params)
+                .map_err(|_| background_closed())?;
+            let id = id
+                .await
+                .map_err(|_| background_closed())?
+                .map_err(|err| RpcError::ClientError(Box::new(err)))?;
+
+            let stream =
+                subxt::ext::futures::stream::poll_fn(move |cx| notifications.poll_recv(cx));
+            Ok(RawRpcSubscription {
+                stream: stream.map(Ok).boxed(),
+                id: Some(id.get().trim_matches('"').to_string()),
+            })
+        })
+    }
+}
@@ -0,0 +1,21 @@
+//! Diagnostic logs written to stderr, for debugging connections and decoding.
+//!
+//! Only errors are logged by default. `-v` adds the connection and progress details, `-vv` the
+//! decoding and subscription internals, and `-vvv` the RPC traffic.
+use tracing_subscriber::filter::LevelFilter;
+
+/// Install the subscriber logging at the verbosity of the `-v` and `-q` flags
+pub fn init(verbose: u8, quiet: bool) {
+    let level = match verbose {
+        _ if quiet => LevelFilter::OFF,
+        0 => LevelFilter::ERROR,
+        1 => LevelFilter::INFO,
+        2 => LevelFilter::DEBUG,
+        _ => LevelFilter::TRACE,
+    };
+
+    tracing_subscriber::fmt()
+        .with_max_level(level)
+        .with_writer(std::io::stderr)
+        .init();
+}
@@ -1,233 +1,694 @@
-#[subxt::subxt(runtime_metadata_path = "metadata.scale")]
-pub mod kitchensink {}
+use std::ffi::OsString;
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
 
-use std::fmt::Display;
-
-use crate::kitchensink::runtime_types::frame_system::AccountInfo;
-use crate::kitchensink::runtime_types::{
-    frame_support::traits::preimages::Bounded, pallet_democracy::vote::AccountVote,
-    pallet_democracy::vote::Vote,
-};
 use anyhow::Result;
-use clap::Parser;
-use codec::Encode;
-use subxt::blocks::ExtrinsicEvents;
-use subxt::config::Hasher;
-use subxt::events::StaticEvent;
-use subxt::ext::futures::{StreamExt, TryStreamExt};
-use subxt::tx::TxPayload;
-use subxt::utils::H256;
-use subxt::{config::substrate::BlakeTwo256, *};
-use subxt_signer::sr25519::dev;
+use clap::{CommandFactory, Parser, ValueEnum};
+use democracy_cli::chain::{Chain, ChainConfig};
+use democracy_cli::config::Config;
+use democracy_cli::error::{self, CliError};
+use democracy_cli::kitchensink::runtime_types::pallet_democracy::types::ReferendumInfo as DemocracyReferendumInfo;
+use democracy_cli::output::{self, Level, Output};
+use democracy_cli::runtime::Runtime;
+use democracy_cli::transport::Transport;
+use democracy_cli::units::BlockNumber;
+use democracy_cli::*;
+use subxt::{PolkadotConfig, SubstrateConfig};
+use subxt_signer::sr25519::Keypair;
+use subxt_signer::SecretUri;
 
 // Parsed command instructions from the command line
 #[derive(Parser)]
-#[clap(author, about, version)]
+#[clap(author, about, version, after_help = exit::HELP)]
 struct CliCommand {
-    #[clap(long, default_value = "ws://127.0.0.1:9944")]
-    url: String,
+    /// Profile from the configuration file to use
+    #[clap(long, env = "DEMOCRACY_PROFILE")]
+    profile: Option<String>,
+
+    /// Node url, `ws(s)://` or `unix://` for an IPC socket, repeat to fail over between
+    /// endpoints [default: ws://127.0.0.1:9944]
+    #[clap(long, env = "DEMOCRACY_URL", value_delimiter = ',')]
+    url: Vec<String>,
+
+    /// Connect to a well-known chain, using its public endpoints and properties
+    #[clap(long, env = "DEMOCRACY_CHAIN")]
+    chain: Option<Chain>,
+
+    /// Connect through an embedded light client instead of trusting an RPC endpoint
+    #[clap(long, env = "DEMOCRACY_LIGHT", conflicts_with = "url")]
+    light: bool,
+
+    /// Chain spec used by the light client [default: chain-specs/<chain>.json in the config dir]
+    #[clap(long, env = "DEMOCRACY_CHAIN_SPEC", requires = "light")]
+    chain_spec: Option<PathBuf>,
+
+    /// Signing user [default: alice]
+    #[clap(short, long, env = "DEMOCRACY_USER")]
+    user: Option<User>,
+
+    /// Secret URI of the signing account, overriding the dev user keypair
+    #[clap(long, env = "DEMOCRACY_SURI", hide_env_values = true)]
+    suri: Option<String>,
+
+    /// Override the governance version detected from the chain metadata
+    #[clap(long, env = "DEMOCRACY_GOVERNANCE")]
+    governance: Option<Governance>,
+
+    /// Don't ask for confirmation before reserving deposits, voting or proposing
+    #[clap(short = 'y', long, visible_alias = "yes", env = "DEMOCRACY_ASSUME_YES")]
+    assume_yes: bool,
+
+    /// Submit the transactions even when the free balance doesn't cover their deposit or lock
+    /// and fee, warning instead of failing
+    #[clap(long, env = "DEMOCRACY_FORCE")]
+    force: bool,
+
+    /// Build democracy and preimage calls and queries from the node metadata instead of the
+    /// bundled one, for runtimes the static types don't match
+    #[clap(long, env = "DEMOCRACY_DYNAMIC")]
+    dynamic: bool,
+
+    /// Connect through a SOCKS5 proxy, e.g. 127.0.0.1:9050 for Tor
+    #[clap(long, env = "DEMOCRACY_SOCKS5")]
+    socks5: Option<String>,
+
+    /// PEM file of additional certificates to trust for wss endpoints
+    #[clap(long, env = "DEMOCRACY_CA_CERT")]
+    ca_cert: Option<PathBuf>,
+
+    /// Accept any certificate for wss endpoints, for self-signed private networks
+    #[clap(long, env = "DEMOCRACY_INSECURE")]
+    insecure: bool,
+
+    /// Maximum number of RPC requests per second, for public endpoints [default: unlimited]
+    #[clap(long, env = "DEMOCRACY_RPS")]
+    rps: Option<u32>,
+
+    /// Connect to a local chopsticks fork, assuming blocks are finalized as soon as built
+    #[clap(long, env = "DEMOCRACY_FORK_LOCAL", conflicts_with_all = ["url", "light"])]
+    fork_local: bool,
+
+    /// Port of the chopsticks fork
+    #[clap(long, env = "DEMOCRACY_FORK_PORT", default_value_t = 8000)]
+    fork_port: u16,
+
+    /// Don't connect to the chain, for the commands that only encode calls
+    #[clap(long, env = "DEMOCRACY_OFFLINE", conflicts_with_all = ["url", "light"])]
+    offline: bool,
+
+    /// Metadata file to encode the calls with offline [default: the latest cached metadata of
+    /// the chain]
+    #[clap(
+        long,
+        env = "DEMOCRACY_METADATA",
+        value_name = "FILE",
+        requires = "offline"
+    )]
+    metadata: Option<PathBuf>,
+
+    /// Fail instead of warning when the chain runtime doesn't match the compiled runtime
+    #[clap(long, env = "DEMOCRACY_STRICT")]
+    strict: bool,
+
+    /// Output format, `json` prints one JSON object per line for scripting, `csv` prints the
+    /// tables of the list commands as CSV
+    #[clap(long, env = "DEMOCRACY_OUTPUT", default_value = "text")]
+    output: output::Format,
+
+    /// Columns of the list tables to show, by header name [default: all]
+    #[clap(long, env = "DEMOCRACY_COLUMNS", value_delimiter = ',')]
+    columns: Vec<String>,
+
+    /// Print block numbers and periods without their estimated time and duration
+    #[clap(long, env = "DEMOCRACY_RAW")]
+    raw: bool,
+
+    /// Give up waiting for an event after this many seconds [default: no timeout]
+    #[clap(long, env = "DEMOCRACY_TIMEOUT")]
+    timeout: Option<u64>,
+
+    /// JSONL file recording every submitted extrinsic [default: audit.jsonl in the config dir]
+    #[clap(long, env = "DEMOCRACY_AUDIT_LOG")]
+    audit_log: Option<PathBuf>,
+
+    /// Read the cached referenda and votes from the chain again instead of from the local cache
+    #[cfg(feature = "cache")]
+    #[clap(long, env = "DEMOCRACY_REFRESH")]
+    refresh: bool,
+
+    /// Don't color the output, also disabled by setting `NO_COLOR`
+    #[clap(long, env = "DEMOCRACY_NO_COLOR")]
+    no_color: bool,
+
+    /// Don't show the tables longer than the terminal through `$PAGER`
+    #[clap(long, env = "DEMOCRACY_NO_PAGER")]
+    no_pager: bool,
 
-    #[clap(short, long, default_value = "alice")]
-    user: User,
+    /// Log details to stderr, repeat for decoding details (-vv) and RPC traffic (-vvv)
+    #[clap(short, long, action = clap::ArgAction::Count, conflicts_with = "quiet")]
+    verbose: u8,
+
+    /// Don't print warnings and logs to stderr, only errors
+    #[clap(short, long, env = "DEMOCRACY_QUIET")]
+    quiet: bool,
 
     #[clap(subcommand)]
     command: SubCommand,
 }
 
-// Dev users supported by the program
-#[derive(PartialEq, Debug, Clone, Copy)]
-enum User {
-    Alice,
-    Bob,
-}
-impl Display for User {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_str(&format!("{:?}", self))
-    }
-}
-impl User {
-    fn keypair(&self) -> subxt_signer::sr25519::Keypair {
-        match self {
-            Self::Alice => dev::alice(),
-            Self::Bob => dev::bob(),
-        }
-    }
-}
-impl From<&str> for User {
-    fn from(s: &str) -> Self {
-        match s {
-            "alice" => Self::Alice,
-            "bob" => Self::Bob,
-            _ => panic!("invalid user"),
-        }
-    }
-}
+// Defaults used when neither the command line nor the profile set a value
+const DEFAULT_CHAIN: Chain = Chain::Local;
+const DEFAULT_USER: User = User::Alice;
+
+// Interval of the websocket pings keeping the connection of the REPL and the scripts open
+const KEEP_ALIVE: Duration = Duration::from_secs(30);
 
 /// The subcommand to execute
 #[derive(Parser, Debug)]
 enum SubCommand {
-    ShowBalance,
-    CreateRemarkPreimage {
-        remark: String,
+    #[clap(flatten)]
+    Balances(commands::balances::Command),
+    #[clap(flatten)]
+    Preimage(commands::preimage::Command),
+    #[clap(flatten)]
+    Call(commands::call::Command),
+    #[clap(flatten)]
+    Democracy(commands::democracy::Command),
+    #[clap(flatten)]
+    Whitelist(commands::whitelist::Command),
+    #[clap(flatten)]
+    ConvictionVoting(commands::conviction_voting::Command),
+    Referenda {
+        #[clap(subcommand)]
+        command: commands::referenda::Command,
+    },
+    Council {
+        #[clap(subcommand)]
+        command: commands::council::Command,
+    },
+    TechnicalCommittee {
+        #[clap(subcommand)]
+        command: commands::technical_committee::Command,
+    },
+    /// Live dashboard of the referenda, my votes and the latest governance events, refreshed on
+    /// each finalized block
+    Dashboard,
+    /// List the upcoming governance deadlines with their estimated date: the next launch period,
+    /// the end of the periods of the ongoing referenda and the scheduled calls
+    Calendar,
+    /// Print the governance events of a range of finalized blocks
+    History {
+        /// First block of the range
+        #[clap(long)]
+        from: BlockNumber,
+        /// Last block of the range [default: the latest finalized block]
+        #[clap(long)]
+        to: Option<BlockNumber>,
     },
-    MakeProposal {
-        hash: String,
-        len: u32,
+    /// Index the governance events into the local cache, from genesis or the last indexed block,
+    /// then follow the finalized blocks until interrupted
+    #[cfg(feature = "cache")]
+    Index {
+        /// First block to index [default: the block after the last indexed one, or genesis]
+        #[clap(long)]
+        from: Option<BlockNumber>,
     },
-    Vote {
-        index: u32,
-        balance: u128,
-        conviction: u8,
+    /// Vote on the referenda as they start, following the `[[auto_vote]]` rules of the config
+    /// file, until interrupted
+    AutoVote,
+    /// Summarize the governance posture of the current account: its locks, votes, delegations
+    /// and deposits, and the referenda awaiting its action
+    Report,
+    /// Rank the voters of a referendum by their conviction-weighted votes, with their identity
+    /// and the votes delegated to them
+    VoterReport { index: u32 },
+    /// Run the steps of a YAML scenario, e.g. noting a preimage, proposing and voting as several
+    /// dev users, see the `script` module for the format
+    RunScript { file: PathBuf },
+    /// Read commands from stdin, e.g. `referenda list`, and run them on the same connection until
+    /// `exit` or the end of the input
+    Repl,
+    /// Measure the round trip to the node and print the statistics of the connection
+    Ping,
+    /// Print the completion script of a shell, e.g. `source <(democracy-cli completions bash)`
+    Completions { shell: completions::Shell },
+    /// List the ongoing referenda indices, used by the completion scripts
+    #[clap(hide = true)]
+    CompleteReferenda,
+    /// Build a block at a later height on a chopsticks fork, e.g. to skip a launch period
+    FastForward {
+        /// Number of blocks to skip
+        #[clap(required_unless_present = "to", conflicts_with = "to")]
+        blocks: Option<u32>,
+        /// Block number of the new block
+        #[clap(long)]
+        to: Option<BlockNumber>,
     },
-    TrackProposalStatus,
+    /// Run the `democracy-cli-<name>` executable found in `PATH`, see [`plugin`]
+    #[clap(external_subcommand)]
+    Plugin(Vec<OsString>),
 }
 
-// Create a vote for a proposal
-fn create_vote(
-    ref_index: u32,
-    aye: bool,
-    conviction: u8,
-    balance: u128,
-) -> subxt::tx::Payload<kitchensink::democracy::calls::types::Vote> {
-    let vote = conviction | if aye { 0b1000_0000 } else { 0 };
-    let democracy = kitchensink::tx().democracy();
-    let vote = AccountVote::Standard {
-        vote: Vote(vote), // Aye + Convinction::None
-        balance,
-    };
+// Run a command that doesn't need a connection to the chain, using the metadata of `file` or the
+// cached metadata of the chain
+fn run_offline(
+    command: SubCommand,
+    format: output::Format,
+    chain: Option<Chain>,
+    file: Option<&Path>,
+) -> Result<()> {
+    let genesis_hash = chain.and_then(|chain| chain.genesis_hash());
+    let offline = metadata::offline(file, genesis_hash)?;
+    let output = Output::new(format, "offline", Vec::new());
+    output.message(&format!("using metadata from {}", offline.source));
+    let chain = chain.unwrap_or(DEFAULT_CHAIN);
 
-    democracy.vote(ref_index, vote)
+    match command {
+        SubCommand::Call(command) => {
+            commands::call::run_offline(&output, &offline, chain, command)?
+        }
+        _ => anyhow::bail!("this command needs a connection to the chain, remove --offline"),
+    }
+
+    Ok(())
 }
 
-// The program context
-struct Program {
-    api: OnlineClient<SubstrateConfig>,
-    user: User,
+// Command line spelling of a value, for the environment of the plugins
+fn value_name(value: impl ValueEnum) -> String {
+    value
+        .to_possible_value()
+        .map(|value| value.get_name().to_string())
+        .unwrap_or_default()
 }
 
-// Helper macro to print to the console using the program context
-macro_rules! print {
-    ($prg:expr, $($arg:tt)*) => {
-        println!("[{}] {}", $prg.user, format!($($arg)*));
-    };
+// A line of the REPL, parsed as the subcommand of the command line
+#[derive(Parser)]
+#[clap(no_binary_name = true, disable_version_flag = true)]
+struct ReplLine {
+    #[clap(subcommand)]
+    command: SubCommand,
+}
+
+#[tokio::main]
+pub async fn main() {
+    let result = run().await;
+    // lines may be pending when a command is interrupted
+    let _ = std::io::stdout().flush();
+    if let Err(err) = result {
+        print_error(&err);
+        std::process::exit(exit::code(&err));
+    }
+}
+
+// Print the error of a command with its causes
+fn print_error(err: &anyhow::Error) {
+    eprintln!(
+        "{}",
+        output::paint(&format!("Error: {err:?}"), Level::Error)
+    );
 }
 
-impl Program {
-    /// Create a new program context
-    async fn new(url: &str, user: User) -> Result<Self> {
-        let api = OnlineClient::<SubstrateConfig>::from_url(url).await?;
-        Ok(Self { api, user })
+// Run the parsed command
+async fn run() -> Result<()> {
+    let CliCommand {
+        profile,
+        url,
+        chain,
+        light,
+        chain_spec,
+        command,
+        user,
+        suri,
+        governance,
+        assume_yes,
+        force,
+        dynamic,
+        strict,
+        socks5,
+        ca_cert,
+        insecure,
+        rps,
+        fork_local,
+        fork_port,
+        offline,
+        metadata,
+        output,
+        columns,
+        no_color,
+        no_pager,
+        verbose,
+        quiet,
+        raw,
+        timeout,
+        audit_log,
+        #[cfg(feature = "cache")]
+        refresh,
+    } = CliCommand::parse();
+    output::init(no_color, no_pager, quiet);
+    log::init(verbose, quiet);
+
+    if let SubCommand::Completions { shell } = command {
+        std::print!("{}", completions::generate(shell, &CliCommand::command())?);
+        return Ok(());
     }
 
-    /// Wait for a specific event to occur
-    async fn wait_for_event<Ev: StaticEvent>(&self) -> Result<Ev> {
-        let event = self
-            .api
-            .blocks()
-            .subscribe_finalized()
-            .await?
-            .try_filter_map(|block| async move { block.events().await?.find_first::<Ev>() })
-            .boxed()
-            .try_next()
-            .await?;
-
-        event.ok_or_else(|| anyhow::anyhow!("event not found"))
+    let profile = Config::load()?.profile(profile.as_deref())?;
+    if offline {
+        run_offline(
+            command,
+            output,
+            chain.or(profile.chain),
+            metadata.as_deref(),
+        )?;
+        return Ok(());
     }
 
-    /// Submit a transaction and wait for it to be finalized
-    async fn submit_and_watch(
-        &self,
-        tx: &impl TxPayload,
-    ) -> Result<ExtrinsicEvents<SubstrateConfig>, subxt::Error> {
-        self.api
-            .tx()
-            .sign_and_submit_then_watch_default(tx, &self.user.keypair())
-            .await
-            .map(|e| {
-                print!(self, "waiting for transaction to be in block...");
-                e
-            })?
-            .wait_for_finalized_success()
-            .await
+    let chain = chain.or(profile.chain).unwrap_or(DEFAULT_CHAIN);
+    let urls = if fork_local {
+        vec![format!("ws://127.0.0.1:{fork_port}")]
+    } else if !url.is_empty() {
+        url
+    } else if profile.url.is_some() || !profile.urls.is_empty() {
+        profile.url.into_iter().chain(profile.urls).collect()
+    } else {
+        chain
+            .endpoints()
+            .iter()
+            .map(|url| url.to_string())
+            .collect()
+    };
+    let user = user.or(profile.user).unwrap_or(DEFAULT_USER);
+
+    if let SubCommand::Plugin(args) = command {
+        let mut env = vec![
+            ("DEMOCRACY_URL", urls.join(",")),
+            ("DEMOCRACY_USER", value_name(user)),
+            ("DEMOCRACY_CHAIN", value_name(chain)),
+            ("DEMOCRACY_OUTPUT", value_name(output)),
+        ];
+        if let Some(suri) = suri {
+            env.push(("DEMOCRACY_SURI", suri));
+        }
+        if light {
+            env.push(("DEMOCRACY_LIGHT", "true".to_string()));
+        }
+        if let Some(path) = chain_spec {
+            env.push(("DEMOCRACY_CHAIN_SPEC", path.display().to_string()));
+        }
+        if assume_yes {
+            env.push(("DEMOCRACY_ASSUME_YES", "true".to_string()));
+        }
+        if force {
+            env.push(("DEMOCRACY_FORCE", "true".to_string()));
+        }
+        std::process::exit(plugin::run(&args, &env)?);
     }
-}
 
-#[tokio::main]
-pub async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let CliCommand { url, command, user } = CliCommand::parse();
-    let program = Program::new(&url, user).await?;
+    let signer = match suri {
+        Some(suri) => SecretUri::from_str(&suri)
+            .map_err(anyhow::Error::from)
+            .and_then(|uri| Ok(Keypair::from_uri(&uri)?))
+            .map_err(|source| CliError::Validation {
+                what: "secret URI".into(),
+                source,
+            })?,
+        None => user.keypair(),
+    };
+    let connection = if light {
+        Connection::Light(light::chain_spec(chain, chain_spec.as_deref())?)
+    } else {
+        Connection::Rpc(
+            urls,
+            Transport {
+                socks5,
+                ca_cert,
+                insecure,
+                keep_alive: matches!(command, SubCommand::Repl | SubCommand::RunScript { .. })
+                    .then_some(KEEP_ALIVE),
+            },
+        )
+    };
+    // only show-balance has the types of the other runtimes, the other commands are built with
+    // the kitchensink types
+    let runtime = match &command {
+        SubCommand::Balances(commands::balances::Command::ShowBalance { runtime }) => *runtime,
+        _ => Some(Runtime::Kitchensink),
+    };
+    let options = Options {
+        governance,
+        runtime,
+        assume_yes,
+        force,
+        dynamic,
+        strict,
+        ss58_prefix: profile.ss58_prefix,
+        rps: rps.or(profile.rps),
+        fork: fork_local,
+        output,
+        columns,
+        raw,
+        timeout: timeout.map(std::time::Duration::from_secs),
+        audit_log: audit_log.or(profile.audit_log).or_else(audit::default_path),
+        notify: profile.notify,
+        #[cfg(feature = "cache")]
+        refresh,
+    };
+    let properties = chain.properties();
+
+    // the program is dropped on ctrl-c, closing the connection
+    shutdown::install();
+    shutdown::or_interrupted(async {
+        // the relay chains have no indices pallet, their addresses are plain account ids
+        if chain.has_indices() {
+            let program =
+                Program::<SubstrateConfig>::new(connection, user, signer, properties, options)
+                    .await?;
+            execute(&program, command)
+                .await
+                .map_err(|err| error::metadata_mismatch(err, program.dynamic))
+        } else {
+            let program =
+                Program::<PolkadotConfig>::new(connection, user, signer, properties, options)
+                    .await?;
+            execute(&program, command)
+                .await
+                .map_err(|err| error::metadata_mismatch(err, program.dynamic))
+        }
+    })
+    .await
+}
 
+// Run a subcommand with the connected program
+async fn execute<C: ChainConfig>(program: &Program<C>, command: SubCommand) -> Result<()> {
     match command {
-        SubCommand::ShowBalance => {
-            let account = user.keypair().public_key().into();
-            let api = program.api.storage().at_latest().await?;
-
-            let query = kitchensink::storage().system().account(&account);
-            let AccountInfo { data, .. } = api.fetch_or_default(&query).await?;
-            print!(program, "account: {data:?}");
-
-            let query = kitchensink::storage().balances().holds(&account);
-            let holds = api.fetch_or_default(&query).await?;
-            print!(program, "holds: {holds:?}");
-
-            let query = kitchensink::storage().balances().freezes(&account);
-            let freezes = api.fetch_or_default(&query).await?;
-            print!(program, "freezes: {freezes:?}");
-        }
-        SubCommand::CreateRemarkPreimage { remark } => {
-            let image = kitchensink::Call::System(
-                kitchensink::runtime_types::frame_system::pallet::Call::remark {
-                    remark: remark.into_bytes(),
-                },
-            )
-            .encode();
-            let image_hash = BlakeTwo256::hash(&image);
-            let image_len = image.len() as u32;
-
-            print!(program, "adding image: {}", hex::encode(&image));
-            let preimage = kitchensink::tx().preimage();
-            let tx = preimage.note_preimage(image);
-            program.submit_and_watch(&tx).await?;
-            print!(program, "preimage created ({image_hash:?}, {image_len})");
-        }
-        SubCommand::MakeProposal { hash, len } => {
-            let democracy = kitchensink::tx().democracy();
-            let hash = H256::from_slice(&hex::decode(hash)?);
-            let runtime_call = Bounded::Lookup { hash, len };
-
-            print!(program, "creating proposal for ({hash}, {len})");
-            let tx = democracy.propose(runtime_call, 1_000_000_000_000_000_000u128);
-            let events = program.submit_and_watch(&tx).await?;
-            print!(program, "proposal created {:?}", events);
-
-            let tabled = program
-                .wait_for_event::<kitchensink::democracy::events::Tabled>()
-                .await;
-            print!(program, "proposal tabled {:?}", tabled);
-
-            let started = program
-                .wait_for_event::<kitchensink::democracy::events::Started>()
-                .await;
-            print!(program, "proposal started {:?}", started);
-        }
-        SubCommand::Vote {
-            index,
-            balance,
-            conviction,
-        } => {
-            print!(program, "submitting vote");
-            let vote = create_vote(index, true, conviction, balance);
-            let events = program.submit_and_watch(&vote).await?;
-            let vote_event = events.find_first::<kitchensink::democracy::events::Voted>()?;
-            print!(program, "vote finalized {:?}", vote_event);
-        }
-        SubCommand::TrackProposalStatus => {
-            let passed = program
-                .wait_for_event::<kitchensink::democracy::events::Passed>()
-                .await;
-            print!(program, "proposal passed {:?}", passed);
+        SubCommand::Balances(command) => commands::balances::run(program, command).await?,
+        SubCommand::Preimage(command) => commands::preimage::run(program, command).await?,
+        SubCommand::Call(command) => commands::call::run(program, command).await?,
+        SubCommand::Democracy(command) => commands::democracy::run(program, command).await?,
+        SubCommand::Whitelist(command) => commands::whitelist::run(program, command).await?,
+        SubCommand::ConvictionVoting(command) => {
+            commands::conviction_voting::run(program, command).await?
+        }
+        SubCommand::Referenda { command } => commands::referenda::run(program, command).await?,
+        SubCommand::Council { command } => commands::council::run(program, command).await?,
+        SubCommand::TechnicalCommittee { command } => {
+            commands::technical_committee::run(program, command).await?
+        }
+        SubCommand::Dashboard => dashboard::run(program).await?,
+        SubCommand::Calendar => calendar::run(program).await?,
+        SubCommand::History { from, to } => {
+            history::run(program, from.into(), to.map(u32::from)).await?
+        }
+        #[cfg(feature = "cache")]
+        SubCommand::Index { from } => indexer::run(program, from.map(u32::from)).await?,
+        SubCommand::AutoVote => auto_vote::run(program).await?,
+        SubCommand::Report => report::run(program).await?,
+        SubCommand::VoterReport { index } => voter_report::run(program, index).await?,
+        SubCommand::RunScript { file } => script::run(program, &file).await?,
+        SubCommand::Repl => repl(program).await?,
+        SubCommand::Ping => stats::ping(program).await?,
+        SubCommand::Completions { .. } => unreachable!("completions don't need a connection"),
+        SubCommand::Plugin(_) => unreachable!("plugins connect to the chain themselves"),
+        SubCommand::CompleteReferenda => match program.governance()? {
+            Governance::OpenGov => {
+                for (index, _) in program
+                    .cached_ongoing_referenda()
+                    .await?
+                    .into_values()
+                    .flatten()
+                {
+                    println!("{index}");
+                }
+            }
+            Governance::Democracy => {
+                let query = kitchensink::storage().democracy().referendum_info_of_iter();
+                for (key, info) in program.iter_cached_storage(&query).await? {
+                    if matches!(info, DemocracyReferendumInfo::Ongoing(_)) {
+                        println!("{}", storage_key_index(&key));
+                    }
+                }
+            }
+        },
+        SubCommand::FastForward { blocks, to } => {
+            let now = program.snapshot().await?.number;
+            let to = to.map_or(now.saturating_add(blocks.unwrap_or_default()), u32::from);
+            if to <= now {
+                Err(anyhow::anyhow!(
+                    "block {to} is not after the current block {now}"
+                ))?;
+            }
+            program.fast_forward(to).await?;
         }
     }
 
     Ok(())
 }
+
+// Run the commands read from stdin with the connected program, a failed command is reported
+// without leaving the REPL
+async fn repl<C: ChainConfig>(program: &Program<C>) -> Result<()> {
+    let interactive = std::io::stdin().is_terminal();
+    while !shutdown::is_requested() {
+        if interactive {
+            eprint!("> ");
+            let _ = std::io::stderr().flush();
+        }
+        let line = tokio::task::spawn_blocking(|| {
+            let mut line = String::new();
+            std::io::stdin()
+                .read_line(&mut line)
+                .map(|read| (read > 0).then_some(line))
+        })
+        .await??;
+        let Some(line) = line else {
+            break;
+        };
+
+        let words = match split_words(&line) {
+            Ok(words) => words,
+            Err(err) => {
+                print_error(&err);
+                continue;
+            }
+        };
+        match words.first().map(String::as_str) {
+            None => continue,
+            Some("exit" | "quit") => break,
+            Some(_) => {}
+        }
+        let command = match ReplLine::try_parse_from(&words) {
+            Ok(line) => line.command,
+            Err(err) => {
+                let _ = err.print();
+                continue;
+            }
+        };
+        if matches!(
+            command,
+            SubCommand::Repl | SubCommand::Completions { .. } | SubCommand::Plugin(_)
+        ) {
+            output::warn("this command can't run in the REPL");
+            continue;
+        }
+
+        // each command reads the latest state, like a new invocation
+        program.unpin();
+        if let Err(err) = Box::pin(execute(program, command)).await {
+            print_error(&error::metadata_mismatch(err, program.dynamic));
+        }
+        let _ = std::io::stdout().flush();
+    }
+    Ok(())
+}
+
+// Split a line of the REPL into words, keeping the quoted and backslash escaped characters
+fn split_words(line: &str) -> Result<Vec<String>> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut quote = None;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            // as in a shell, backslashes are kept verbatim between single quotes
+            (None | Some('"'), '\\') => {
+                let escaped = chars
+                    .next()
+                    .ok_or_else(|| anyhow::anyhow!("unterminated escape"))?;
+                word.get_or_insert_with(String::new).push(escaped);
+            }
+            (Some(_), c) => word.get_or_insert_with(String::new).push(c),
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                word.get_or_insert_with(String::new);
+            }
+            (None, c) if c.is_whitespace() => words.extend(word.take()),
+            (None, c) => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    if let Some(q) = quote {
+        anyhow::bail!("unterminated {q} quote");
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the scripts of clap_complete are patched to complete the referendum indices
+    #[test]
+    fn completions_hook_the_referenda() {
+        let command = CliCommand::command();
+        let bash = completions::generate(completions::Shell::Bash, &command).unwrap();
+        assert!(bash.contains("complete-referenda 2>/dev/null"));
+        assert!(bash.contains("complete -F _democracy_cli_referenda"));
+
+        let zsh = completions::generate(completions::Shell::Zsh, &command).unwrap();
+        assert!(zsh.contains(":index:_democracy-cli_referenda' \\"));
+        assert!(zsh.contains("$(democracy-cli complete-referenda 2>/dev/null)"));
+
+        let fish = completions::generate(completions::Shell::Fish, &command).unwrap();
+        assert!(fish.contains("-a '(democracy-cli complete-referenda 2>/dev/null)'"));
+    }
+
+    fn words(line: &str) -> Vec<String> {
+        split_words(line).unwrap()
+    }
+
+    #[test]
+    fn split_plain_words() {
+        assert_eq!(words("  vote 3  --aye\t"), ["vote", "3", "--aye"]);
+        assert!(words("   ").is_empty());
+    }
+
+    #[test]
+    fn split_quoted_words() {
+        assert_eq!(
+            words(r#"propose --remark "hello world" 'it''s' a"b c"d"#),
+            ["propose", "--remark", "hello world", "its", "ab cd"]
+        );
+        // the other quote is kept, empty quotes are a word
+        assert_eq!(words(r#""it's" '"' '' """#), ["it's", "\"", "", ""]);
+    }
+
+    #[test]
+    fn split_escaped_words() {
+        assert_eq!(
+            words(r#"hello\ world \"quoted\" "a \"b\" \\" 'c \d'"#),
+            ["hello world", "\"quoted\"", "a \"b\" \\", "c \\d"]
+        );
+        assert!(split_words("trailing \\").is_err());
+    }
+
+    #[test]
+    fn unterminated_quotes_fail() {
+        let err = split_words("propose --remark \"hello").unwrap_err();
+        assert_eq!(err.to_string(), "unterminated \" quote");
+        let err = split_words("propose --remark 'hello").unwrap_err();
+        assert_eq!(err.to_string(), "unterminated ' quote");
+    }
+}
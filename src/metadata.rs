@@ -0,0 +1,202 @@
+//! Runtime metadata downloaded from the node, cached under the config dir.
+//!
+//! The metadata is stored as `metadata/<genesis hash>-<spec version>.scale`, so each runtime
+//! upgrade of each chain gets its own file. Events and dispatch errors are always decoded with
+//! the node metadata, the compiled runtimes only back the statically generated calls and storage
+//! queries, so a drift between the two is reported on connect.
+//!
+//! On connect, the cached metadata of the running runtime is used instead of downloading it again,
+//! the genesis hash and the spec version being cheap to query. Without a connection, `--offline`
+//! uses the latest cached metadata of the chain selected by `--chain`, or a `--metadata` file.
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use codec::{Decode, Encode};
+use subxt::backend::legacy::LegacyRpcMethods;
+use subxt::backend::rpc::RpcClient;
+use subxt::backend::RuntimeVersion;
+use subxt::dynamic::{At, Value};
+use subxt::ext::scale_decode::DecodeAsType;
+use subxt::utils::H256;
+use subxt::{Metadata, OnlineClient};
+
+use crate::chain::ChainConfig;
+use crate::config::Config;
+use crate::runtime::Runtime;
+use crate::units::parse_h256;
+
+/// Location of the cached metadata of a runtime
+pub fn cache_path(genesis_hash: H256, spec_version: u32) -> Option<PathBuf> {
+    Some(
+        Config::dir()?
+            .join("metadata")
+            .join(format!("{genesis_hash:?}-{spec_version}.scale")),
+    )
+}
+
+/// Metadata to use without a connection
+pub struct OfflineMetadata {
+    /// Where the metadata comes from, e.g. its file
+    pub source: String,
+    pub metadata: Metadata,
+    /// Genesis hash of the chain of the metadata, unknown for the bundled runtime and the files
+    /// given outside the cache
+    pub genesis_hash: Option<H256>,
+}
+
+/// Metadata to use without a connection: the given file, the latest cached metadata of the chain
+/// of `genesis_hash`, or of the only chain with cached metadata, or else the bundled kitchensink
+/// metadata. Fails when the metadata of several chains is cached and none is selected, rather
+/// than encoding calls for the wrong runtime
+pub fn offline(file: Option<&Path>, genesis_hash: Option<H256>) -> Result<OfflineMetadata> {
+    if let Some(path) = file {
+        let cached = parse_cache_name(path).map(|(genesis_hash, _)| genesis_hash);
+        return Ok(OfflineMetadata {
+            source: path.display().to_string(),
+            metadata: read(path)?,
+            genesis_hash: genesis_hash.or(cached),
+        });
+    }
+
+    let cached = Config::dir()
+        .and_then(|dir| std::fs::read_dir(dir.join("metadata")).ok())
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            let (genesis_hash, spec_version) = parse_cache_name(&path)?;
+            Some((genesis_hash, spec_version, path))
+        })
+        .collect::<Vec<_>>();
+    let chains = cached
+        .iter()
+        .map(|(genesis_hash, ..)| *genesis_hash)
+        .collect::<BTreeSet<_>>();
+    let genesis_hash = match genesis_hash {
+        Some(genesis_hash) if !chains.contains(&genesis_hash) => anyhow::bail!(
+            "no metadata cached for the chain {genesis_hash:?}, connect to it once or pass --metadata"
+        ),
+        Some(genesis_hash) => genesis_hash,
+        None if chains.is_empty() => {
+            return Ok(OfflineMetadata {
+                source: "the bundled kitchensink runtime".to_string(),
+                metadata: Runtime::Kitchensink.metadata()?,
+                genesis_hash: None,
+            })
+        }
+        None if chains.len() == 1 => chains.into_iter().next().unwrap(),
+        None => anyhow::bail!(
+            "metadata of {} chains is cached ({}), select one with --chain or --metadata",
+            chains.len(),
+            chains
+                .iter()
+                .map(|hash| format!("{hash:?}"))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    };
+
+    // the latest runtime of the chain
+    let (_, _, path) = cached
+        .into_iter()
+        .filter(|(hash, ..)| *hash == genesis_hash)
+        .max_by_key(|(_, spec_version, _)| *spec_version)
+        .expect("the chain has cached metadata");
+    Ok(OfflineMetadata {
+        source: path.display().to_string(),
+        metadata: read(&path)?,
+        genesis_hash: Some(genesis_hash),
+    })
+}
+
+// Read a metadata file
+fn read(path: &Path) -> Result<Metadata> {
+    let bytes =
+        std::fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+    Metadata::decode(&mut &bytes[..])
+        .with_context(|| format!("invalid metadata in {}", path.display()))
+}
+
+// Genesis hash and spec version of a file named like the cached metadata, see `cache_path`
+fn parse_cache_name(path: &Path) -> Option<(H256, u32)> {
+    let name = path.file_name()?.to_str()?.strip_suffix(".scale")?;
+    let (genesis_hash, spec_version) = name.split_once('-')?;
+    Some((parse_h256(genesis_hash).ok()?, spec_version.parse().ok()?))
+}
+
+/// Create the client of a chain, from the cached metadata of its runtime when there is one
+pub async fn client<C: ChainConfig>(rpc: RpcClient) -> Result<OnlineClient<C>> {
+    let methods = LegacyRpcMethods::<C>::new(rpc.clone());
+    let (genesis_hash, version) = tokio::try_join!(
+        methods.genesis_hash(),
+        methods.state_get_runtime_version(None)
+    )?;
+    let Some(path) = cache_path(genesis_hash, version.spec_version).filter(|path| path.exists())
+    else {
+        return Ok(OnlineClient::from_rpc_client(rpc).await?);
+    };
+
+    let cached = std::fs::read(&path)
+        .map_err(anyhow::Error::from)
+        .and_then(|bytes| Ok(Metadata::decode(&mut &bytes[..])?));
+    match cached {
+        Ok(metadata) => {
+            tracing::debug!(path = %path.display(), "using the cached runtime metadata");
+            let runtime_version = RuntimeVersion {
+                spec_version: version.spec_version,
+                transaction_version: version.transaction_version,
+            };
+            Ok(OnlineClient::from_rpc_client_with(
+                genesis_hash,
+                runtime_version,
+                metadata,
+                rpc,
+            )?)
+        }
+        Err(err) => {
+            // downloaded again and cached by `store`
+            tracing::warn!(path = %path.display(), "invalid cached metadata: {err:#}");
+            let _ = std::fs::remove_file(&path);
+            Ok(OnlineClient::from_rpc_client(rpc).await?)
+        }
+    }
+}
+
+/// Cache the metadata of the connected runtime, unless it is already cached
+pub fn store(api: &OnlineClient<impl ChainConfig>) -> Result<()> {
+    let spec_version = api.runtime_version().spec_version;
+    let Some(path) = cache_path(api.genesis_hash(), spec_version) else {
+        return Ok(());
+    };
+    if path.exists() {
+        return Ok(());
+    }
+
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("failed to create {}", dir.display()))?;
+    }
+    tracing::debug!(path = %path.display(), "caching the runtime metadata");
+    std::fs::write(&path, api.metadata().encode())
+        .with_context(|| format!("failed to write {}", path.display()))
+}
+
+/// Read the spec version from the `System::Version` constant of the metadata
+pub fn spec_version(metadata: &Metadata) -> Option<u32> {
+    Some(runtime_version(metadata)?.spec_version)
+}
+
+/// Read the spec and transaction versions from the `System::Version` constant of the metadata
+pub fn runtime_version(metadata: &Metadata) -> Option<RuntimeVersion> {
+    let constant = metadata
+        .pallet_by_name("System")?
+        .constant_by_name("Version")?;
+    let version =
+        Value::decode_as_type(&mut constant.value(), constant.ty(), metadata.types()).ok()?;
+    let field = |name| version.at(name)?.as_u128()?.try_into().ok();
+    Some(RuntimeVersion {
+        spec_version: field("spec_version")?,
+        transaction_version: field("transaction_version")?,
+    })
+}
@@ -0,0 +1,37 @@
+//! Desktop or chat notifications of the daemons, such as the votes of `auto-vote`, sent by running
+//! the command of the `notify` profile setting with the message as its last argument:
+//!
+//! ```toml
+//! [profiles.default]
+//! notify = ["notify-send", "democracy-cli"]
+//! ```
+use std::process::Stdio;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use tokio::process::Command;
+
+/// Time the notification command has to complete before it is killed, so that a hanging command
+/// doesn't hold the daemon
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Run the notification command with a message, failing when it exits with an error or times out
+pub async fn send(command: &[String], message: &str) -> Result<()> {
+    let (program, args) = command.split_first().context("empty notify command")?;
+    let mut child = Command::new(program)
+        .args(args)
+        .arg(message)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()
+        .with_context(|| format!("failed to run {program}"))?;
+    let status = tokio::time::timeout(TIMEOUT, child.wait())
+        .await
+        .map_err(|_| anyhow::anyhow!("{program} timed out after {}s", TIMEOUT.as_secs()))?
+        .with_context(|| format!("failed to wait for {program}"))?;
+    if !status.success() {
+        anyhow::bail!("{program} failed with {status}");
+    }
+    Ok(())
+}
@@ -0,0 +1,492 @@
+//! Output of the commands, as human readable lines tagged with the signing user or as JSON lines
+//! for scripting around the tool.
+//!
+//! In JSON mode every line written to stdout is an object with the `user` and a `kind`:
+//! `message` for progress messages, or the kind of record such as `balance`, `referendum` or
+//! `extrinsic`. In CSV mode the tables of the list commands are written to stdout as CSV, for
+//! spreadsheets, and the other lines to stderr. Diagnostics and prompts are written to stderr in
+//! all modes.
+//!
+//! Text lines are colored by level when writing to a terminal, unless `--no-color` is passed or
+//! `NO_COLOR` is set. Tables longer than the terminal are shown through `$PAGER`, `less` by
+//! default, unless `--no-pager` is passed.
+//!
+//! An output created with [`Output::capture`] keeps the lines it would write to stdout instead,
+//! for the snapshot tests of the rendering.
+use std::fmt::Display;
+use std::io::{IsTerminal, Write};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anstyle::{AnsiColor, Style};
+use anyhow::Result;
+use comfy_table::{presets, TableComponent};
+use indicatif::{ProgressBar, ProgressStyle};
+use serde_json::{json, Map, Value as Json};
+use subxt::blocks::ExtrinsicEvents;
+use subxt::events::EventDetails;
+
+use crate::chain::ChainConfig;
+
+/// Output formats
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Format {
+    /// Human readable lines
+    Text,
+    /// One JSON object per line
+    Json,
+    /// Tables as CSV, other lines as text on stderr
+    Csv,
+}
+
+// Whether text lines are colored, see `init`
+static COLOR: AtomicBool = AtomicBool::new(false);
+
+// Whether warnings are hidden, see `init`
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+// Whether long tables are shown through a pager, see `init`
+static PAGER: AtomicBool = AtomicBool::new(false);
+
+/// Enable colors and the pager when stdout is a terminal, unless disabled by `--no-color` or
+/// `NO_COLOR` and `--no-pager`, and hide the warnings when `quiet`
+pub fn init(no_color: bool, no_pager: bool, quiet: bool) {
+    let no_color = no_color || std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+    let terminal = std::io::stdout().is_terminal();
+    COLOR.store(!no_color && terminal, Ordering::Relaxed);
+    PAGER.store(!no_pager && terminal, Ordering::Relaxed);
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+/// Level of a line, setting its color
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Info,
+    Success,
+    Warning,
+    Error,
+}
+
+impl Level {
+    fn name(self) -> &'static str {
+        match self {
+            Level::Info => "info",
+            Level::Success => "success",
+            Level::Warning => "warning",
+            Level::Error => "error",
+        }
+    }
+
+    fn style(self) -> Style {
+        match self {
+            Level::Info => Style::new(),
+            Level::Success => AnsiColor::Green.on_default(),
+            Level::Warning => AnsiColor::Yellow.on_default(),
+            Level::Error => AnsiColor::Red.on_default().bold(),
+        }
+    }
+}
+
+/// Color a text with the style of the level, when colors are enabled
+pub fn paint(text: &str, level: Level) -> String {
+    if !COLOR.load(Ordering::Relaxed) || level == Level::Info {
+        return text.to_string();
+    }
+
+    let style = level.style();
+    format!("{}{text}{}", style.render(), style.render_reset())
+}
+
+/// Print a warning to stderr, unless `--quiet`
+pub fn warn(text: &str) {
+    if !QUIET.load(Ordering::Relaxed) {
+        eprintln!("{}", paint(text, Level::Warning));
+    }
+}
+
+/// Printer of the messages and records of a user
+pub struct Output {
+    format: Format,
+    user: String,
+    /// Columns of the tables to print, all when empty
+    columns: Vec<String>,
+    /// Lines written to stdout, kept instead of printed when capturing
+    captured: Option<Arc<Mutex<Vec<String>>>>,
+}
+
+impl Output {
+    pub fn new(format: Format, user: impl Display, columns: Vec<String>) -> Self {
+        Self {
+            format,
+            user: user.to_string(),
+            columns,
+            captured: None,
+        }
+    }
+
+    /// Printer keeping the lines it would write to stdout, see [`Output::captured`]
+    pub fn capture(format: Format, user: impl Display, columns: Vec<String>) -> Self {
+        Self {
+            captured: Some(Arc::default()),
+            ..Self::new(format, user, columns)
+        }
+    }
+
+    /// Printer with the same format and columns, for the messages of another user. The lines of
+    /// a capturing printer are kept along with its own
+    pub fn for_user(&self, user: impl Display) -> Self {
+        Self {
+            captured: self.captured.clone(),
+            ..Self::new(self.format, user, self.columns.clone())
+        }
+    }
+
+    /// Lines written to stdout so far by a capturing printer, empty otherwise
+    pub fn captured(&self) -> Vec<String> {
+        self.captured
+            .as_ref()
+            .map_or(Vec::new(), |captured| captured.lock().unwrap().clone())
+    }
+
+    /// Whether records are printed as JSON
+    pub fn is_json(&self) -> bool {
+        self.format == Format::Json
+    }
+
+    /// Whether lines are printed as human readable text
+    pub fn is_text(&self) -> bool {
+        self.format == Format::Text
+    }
+
+    /// Print a progress message
+    pub fn message(&self, text: &str) {
+        self.line(Level::Info, text);
+    }
+
+    /// Print a message reporting a successful operation
+    pub fn success(&self, text: &str) {
+        self.line(Level::Success, text);
+    }
+
+    /// Print a message the user should pay attention to
+    pub fn warning(&self, text: &str) {
+        self.line(Level::Warning, text);
+    }
+
+    /// Print a message with the given level
+    pub fn line(&self, level: Level, text: &str) {
+        match self.format {
+            Format::Text => self.print(format!("[{}] {}", self.user, paint(text, level))),
+            Format::Csv => eprintln!("[{}] {}", self.user, paint(text, level)),
+            Format::Json => {
+                self.print_json("message", json!({ "level": level.name(), "message": text }))
+            }
+        }
+    }
+
+    /// Print a record, as its JSON fields or as the given text line
+    pub fn record(&self, kind: &str, fields: Json, text: String) {
+        match self.format {
+            Format::Text | Format::Csv => self.message(&text),
+            Format::Json => self.print_json(kind, fields),
+        }
+    }
+
+    /// Print a record only in JSON mode, for data the text output already describes
+    pub fn record_json(&self, kind: &str, fields: Json) {
+        if self.is_json() {
+            self.print_json(kind, fields);
+        }
+    }
+
+    /// Print a table with aligned columns, only in text mode as its rows are printed as records
+    /// in JSON mode
+    pub fn table(&self, table: &Table) -> Result<()> {
+        match self.format {
+            Format::Text => {
+                let lines = table
+                    .render(&self.columns)?
+                    .into_iter()
+                    .map(|line| format!("[{}] {line}", self.user))
+                    .collect::<Vec<_>>();
+                match &self.captured {
+                    Some(captured) => captured.lock().unwrap().extend(lines),
+                    None => page(&lines),
+                }
+            }
+            Format::Csv => {
+                for line in table.render_csv(&self.columns)? {
+                    self.print(line);
+                }
+            }
+            Format::Json => {}
+        }
+        Ok(())
+    }
+
+    fn print_json(&self, kind: &str, fields: Json) {
+        let mut object = Map::new();
+        object.insert("kind".to_string(), kind.into());
+        object.insert("user".to_string(), self.user.as_str().into());
+        match fields {
+            Json::Object(fields) => object.extend(fields),
+            fields => {
+                object.insert("value".to_string(), fields);
+            }
+        }
+        self.print(Json::Object(object).to_string());
+    }
+
+    // Write a line to stdout, or keep it when capturing
+    fn print(&self, line: String) {
+        match &self.captured {
+            Some(captured) => captured.lock().unwrap().push(line),
+            None => println!("{line}"),
+        }
+    }
+}
+
+/// Print lines to stdout, through the pager when they don't fit in the terminal, like git does
+fn page(lines: &[String]) {
+    let fits = terminal_height().is_none_or(|height| lines.len() < height);
+    if !PAGER.load(Ordering::Relaxed) || fits {
+        for line in lines {
+            println!("{line}");
+        }
+        return;
+    }
+
+    // `$PAGER` may hold arguments, let the shell split it
+    let pager = std::env::var("PAGER")
+        .ok()
+        .filter(|pager| !pager.is_empty())
+        .unwrap_or_else(|| "less".to_string());
+    let child = Command::new("sh")
+        .args(["-c", &pager])
+        .env(
+            "LESS",
+            std::env::var("LESS").unwrap_or_else(|_| "FRX".to_string()),
+        )
+        .stdin(Stdio::piped())
+        .spawn();
+    let mut child = match child {
+        Ok(child) => child,
+        Err(err) => {
+            warn(&format!("failed to start the pager {pager}: {err}"));
+            PAGER.store(false, Ordering::Relaxed);
+            return page(lines);
+        }
+    };
+
+    if let Some(mut stdin) = child.stdin.take() {
+        for line in lines {
+            // the pager was quit before reading everything
+            if writeln!(stdin, "{line}").is_err() {
+                break;
+            }
+        }
+    }
+    let _ = child.wait();
+}
+
+// Number of rows of the terminal attached to stdout
+fn terminal_height() -> Option<usize> {
+    let size = rustix::termios::tcgetwinsize(std::io::stdout()).ok()?;
+    Some(size.ws_row as usize).filter(|rows| *rows > 0)
+}
+
+// Frames of the spinner animation, the last one drawn once finished
+const SPINNER_FRAMES: &str = "⠋⠙⠹⠸⠼⠴⠦⠧⠇⠏✓";
+
+// Interval between the frames of the spinner
+const SPINNER_TICK: Duration = Duration::from_millis(100);
+
+/// Spinner animated on stderr during long waits, with the finalized block height and the elapsed
+/// time. Only drawn when stderr is a terminal and `--quiet` isn't set.
+pub struct Spinner {
+    bar: ProgressBar,
+}
+
+impl Spinner {
+    pub fn new(message: &str) -> Self {
+        let enabled = std::io::stderr().is_terminal() && !QUIET.load(Ordering::Relaxed);
+        let bar = if enabled {
+            ProgressBar::new_spinner()
+        } else {
+            ProgressBar::hidden()
+        };
+        let style = ProgressStyle::with_template("{spinner} {msg} ({prefix}{elapsed})")
+            .expect("valid spinner template")
+            .tick_chars(SPINNER_FRAMES);
+        bar.set_style(style);
+        bar.set_message(message.to_string());
+        bar.enable_steady_tick(SPINNER_TICK);
+        Self { bar }
+    }
+
+    /// Whether the spinner is drawn
+    pub fn is_enabled(&self) -> bool {
+        !self.bar.is_hidden()
+    }
+
+    /// Replace the message, e.g. to report the progress of the wait
+    pub fn set_message(&self, message: &str) {
+        self.bar.set_message(message.to_string());
+    }
+
+    /// Update the finalized block height
+    pub fn set_block(&self, block: u32) {
+        self.bar.set_prefix(format!("block #{block}, "));
+    }
+
+    /// Clear the spinner, returning the time elapsed since it started
+    pub fn finish(self) -> Duration {
+        self.bar.finish_and_clear();
+        self.bar.elapsed()
+    }
+
+    /// Replace the spinner by a summary line, returning the time elapsed since it started
+    pub fn finish_with_message(self, message: &str) -> Duration {
+        let elapsed = self.bar.elapsed();
+        let style = ProgressStyle::with_template("{spinner} {msg}")
+            .expect("valid spinner template")
+            .tick_chars(SPINNER_FRAMES);
+        self.bar.set_style(style);
+        self.bar.finish_with_message(format!(
+            "{message} in {}",
+            crate::humanize_duration(elapsed.as_millis() as u64)
+        ));
+        elapsed
+    }
+}
+
+// Bars of the sparklines, from the lowest to the highest value
+const SPARK_BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Render values as a sparkline, scaled so that `max` is the highest bar
+pub fn sparkline(values: &[u128], max: u128) -> String {
+    let top = SPARK_BARS.len() - 1;
+    values
+        .iter()
+        .map(|value| {
+            let level = value.saturating_mul(top as u128).checked_div(max);
+            SPARK_BARS[level.map_or(0, |level| (level as usize).min(top))]
+        })
+        .collect()
+}
+
+/// Rows of a list command, with named columns
+pub struct Table {
+    headers: Vec<&'static str>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    pub fn new(headers: &[&'static str]) -> Self {
+        Self {
+            headers: headers.to_vec(),
+            rows: Vec::new(),
+        }
+    }
+
+    /// Add a row, with a cell per column
+    pub fn add_row(&mut self, row: Vec<String>) {
+        debug_assert_eq!(row.len(), self.headers.len());
+        self.rows.push(row);
+    }
+
+    // Indices of the selected columns, all when empty
+    fn select(&self, columns: &[String]) -> Result<Vec<usize>> {
+        if columns.is_empty() {
+            return Ok((0..self.headers.len()).collect());
+        }
+
+        columns
+            .iter()
+            .map(|column| {
+                self.headers
+                    .iter()
+                    .position(|header| header.eq_ignore_ascii_case(column))
+                    .ok_or_else(|| {
+                        anyhow::anyhow!(
+                            "unknown column {column}, available columns: {}",
+                            self.headers.join(", ")
+                        )
+                    })
+            })
+            .collect()
+    }
+
+    /// Render the selected columns, all when empty, as CSV lines
+    pub fn render_csv(&self, columns: &[String]) -> Result<Vec<String>> {
+        let selected = self.select(columns)?;
+        let line = |cells: Vec<&str>| -> String {
+            cells
+                .into_iter()
+                .map(csv_field)
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+
+        let header = selected.iter().map(|i| self.headers[*i]).collect();
+        let mut lines = vec![line(header)];
+        lines.extend(
+            self.rows
+                .iter()
+                .map(|row| line(selected.iter().map(|i| row[*i].as_str()).collect())),
+        );
+        Ok(lines)
+    }
+
+    /// Render the selected columns, all when empty, as aligned lines
+    pub fn render(&self, columns: &[String]) -> Result<Vec<String>> {
+        let selected = self.select(columns)?;
+        let mut table = comfy_table::Table::new();
+        // no borders, a rule below the header and two spaces between the columns
+        table
+            .load_preset(presets::NOTHING)
+            .set_style(TableComponent::HeaderLines, '-')
+            .set_style(TableComponent::MiddleHeaderIntersections, ' ')
+            .set_style(TableComponent::VerticalLines, ' ')
+            .set_header(selected.iter().map(|i| self.headers[*i]));
+        for row in &self.rows {
+            table.add_row(selected.iter().map(|i| row[*i].as_str()));
+        }
+        for column in table.column_iter_mut() {
+            column.set_padding((0, 1));
+        }
+        Ok(table
+            .lines()
+            .map(|line| line.trim_end().to_string())
+            .collect())
+    }
+}
+
+// Quote a CSV field when it contains a separator, a quote or a line break, see RFC 4180
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Decode the events of an extrinsic with the node metadata
+pub fn events<C: ChainConfig>(events: &ExtrinsicEvents<C>) -> Result<Json> {
+    let mut decoded = Vec::new();
+    for event in events.iter() {
+        decoded.push(event_fields(&event?)?);
+    }
+    Ok(Json::Array(decoded))
+}
+
+/// Decode an event with the node metadata, as its pallet, name and fields
+pub fn event_fields<C: ChainConfig>(event: &EventDetails<C>) -> Result<Json> {
+    Ok(json!({
+        "pallet": event.pallet_name(),
+        "event": event.variant_name(),
+        "fields": event.field_values()?,
+    }))
+}
@@ -0,0 +1,57 @@
+//! External subcommands, git-style: `democracy-cli <name> [args]` runs the `democracy-cli-<name>`
+//! executable found in `PATH` with the remaining arguments, so chain-specific governance
+//! commands can be added without forking the tool.
+//!
+//! The plugin inherits the environment, with the connection settings resolved from the command
+//! line and the profile exported as the `DEMOCRACY_*` variables the CLI itself reads.
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::{anyhow, Context, Result};
+
+use crate::error::CliError;
+
+/// Prefix of the plugin executables
+pub const PREFIX: &str = "democracy-cli-";
+
+/// Path of the executable of a plugin, the first match in `PATH`
+pub fn find(name: &str) -> Option<PathBuf> {
+    let file = format!("{PREFIX}{name}{}", std::env::consts::EXE_SUFFIX);
+    std::env::split_paths(&std::env::var_os("PATH")?)
+        .map(|dir| dir.join(&file))
+        .find(|path| is_executable(path))
+}
+
+/// Run a plugin with its arguments and the given environment, returning its exit code
+pub fn run(args: &[OsString], env: &[(&str, String)]) -> Result<i32> {
+    let (name, args) = args.split_first().context("missing plugin name")?;
+    let name = name.to_string_lossy();
+    let path = find(&name).ok_or_else(|| CliError::Validation {
+        what: "subcommand".into(),
+        source: anyhow!("unrecognized subcommand `{name}` and no `{PREFIX}{name}` in PATH"),
+    })?;
+
+    tracing::debug!(path = %path.display(), "running the plugin");
+    let status = Command::new(&path)
+        .args(args)
+        .envs(env.iter().cloned())
+        .status()
+        .with_context(|| format!("failed to run {}", path.display()))?;
+
+    // A plugin killed by a signal has no exit code
+    Ok(status.code().unwrap_or(1))
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata()
+        .map(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
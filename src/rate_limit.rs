@@ -0,0 +1,172 @@
+//! Client side rate limiting, so scans against public endpoints don't get the user banned.
+//!
+//! Requests are spaced to stay under the configured requests per second, and requests rejected
+//! by the endpoint's rate limiter (HTTP 429 and similar) are retried with an exponential backoff.
+use std::sync::Mutex;
+use std::time::Duration;
+
+use jsonrpsee::types::error::SERVER_IS_BUSY_CODE;
+use subxt::backend::rpc::{RawRpcFuture, RawRpcSubscription, RawValue, RpcClient, RpcClientT};
+use subxt::error::RpcError;
+use tokio::time::Instant;
+
+use crate::output;
+
+// Number of retries of a rate limited request before giving up
+const MAX_RETRIES: u32 = 5;
+
+// Delay before the first retry, doubled on each retry
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Wrap a client to send at most `rps` requests per second, unlimited when `None`
+pub fn limit(client: RpcClient, rps: Option<u32>) -> RpcClient {
+    let interval = rps
+        .filter(|rps| *rps > 0)
+        .map(|rps| Duration::from_secs(1) / rps);
+
+    RpcClient::new(RateLimitedRpc {
+        client,
+        interval,
+        next_slot: Mutex::new(Instant::now()),
+    })
+}
+
+struct RateLimitedRpc {
+    client: RpcClient,
+    interval: Option<Duration>,
+    next_slot: Mutex<Instant>,
+}
+
+// JSON-RPC error codes of the rate limiters: the status of the HTTP gateways, the code used by
+// the public endpoints and the busy server code of jsonrpsee
+const RATE_LIMIT_CODES: [i32; 3] = [429, -32029, SERVER_IS_BUSY_CODE];
+
+// Whether the error comes from the endpoint rate limiting us, from the code or the message of its
+// JSON-RPC error, not from the data it carries
+fn is_rate_limited(err: &RpcError) -> bool {
+    let RpcError::ClientError(err) = err else {
+        return false;
+    };
+    let Some(jsonrpsee::core::Error::Call(call)) = err.downcast_ref() else {
+        return false;
+    };
+    let message = call.message().to_lowercase();
+    RATE_LIMIT_CODES.contains(&call.code())
+        || message.starts_with("too many requests")
+        || message.starts_with("rate limit")
+}
+
+impl RateLimitedRpc {
+    // Wait for the next request slot
+    async fn throttle(&self) {
+        let Some(interval) = self.interval else {
+            return;
+        };
+
+        let slot = {
+            let mut next_slot = self.next_slot.lock().unwrap();
+            let slot = (*next_slot).max(Instant::now());
+            *next_slot = slot + interval;
+            slot
+        };
+        tracing::trace!(delay = ?slot.saturating_duration_since(Instant::now()), "throttled");
+        tokio::time::sleep_until(slot).await;
+    }
+}
+
+impl RpcClientT for RateLimitedRpc {
+    fn request_raw<'a>(
+        &'a self,
+        method: &'a str,
+        params: Option<Box<RawValue>>,
+    ) -> RawRpcFuture<'a, Box<RawValue>> {
+        Box::pin(async move {
+            tracing::trace!(
+                method,
+                params = params.as_deref().map(RawValue::get),
+                "request"
+            );
+            let mut backoff = INITIAL_BACKOFF;
+            for _ in 0..MAX_RETRIES {
+                self.throttle().await;
+                match self.client.request_raw(method, params.clone()).await {
+                    Err(err) if is_rate_limited(&err) => {
+                        output::warn(&format!("rate limited, retrying in {}s", backoff.as_secs()));
+                        tokio::time::sleep(backoff).await;
+                        backoff *= 2;
+                    }
+                    result => {
+                        match &result {
+                            Ok(response) => tracing::trace!(method, response = response.get()),
+                            Err(err) => tracing::debug!(method, %err, "request failed"),
+                        }
+                        return result;
+                    }
+                }
+            }
+
+            self.throttle().await;
+            self.client.request_raw(method, params).await
+        })
+    }
+
+    fn subscribe_raw<'a>(
+        &'a self,
+        sub: &'a str,
+        params: Option<Box<RawValue>>,
+        unsub: &'a str,
+    ) -> RawRpcFuture<'a, RawRpcSubscription> {
+        Box::pin(async move {
+            tracing::debug!(
+                sub,
+                params = params.as_deref().map(RawValue::get),
+                "subscribe"
+            );
+            self.throttle().await;
+            self.client.subscribe_raw(sub, params, unsub).await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonrpsee::types::error::{ErrorObject, INTERNAL_ERROR_CODE};
+
+    // Error of a JSON-RPC call, as returned by the jsonrpsee client
+    fn call_error(code: i32, message: &str) -> RpcError {
+        let err = jsonrpsee::core::Error::Call(ErrorObject::owned(code, message, None::<()>));
+        RpcError::ClientError(Box::new(err))
+    }
+
+    #[test]
+    fn rate_limit_codes() {
+        assert!(is_rate_limited(&call_error(429, "")));
+        assert!(is_rate_limited(&call_error(-32029, "")));
+        assert!(is_rate_limited(&call_error(
+            SERVER_IS_BUSY_CODE,
+            "Server is busy"
+        )));
+        assert!(!is_rate_limited(&call_error(INTERNAL_ERROR_CODE, "")));
+    }
+
+    #[test]
+    fn rate_limit_messages() {
+        assert!(is_rate_limited(&call_error(-32000, "Too Many Requests")));
+        assert!(is_rate_limited(&call_error(-32000, "rate limit exceeded")));
+        // only the start of the message is matched, not data quoted in it
+        assert!(!is_rate_limited(&call_error(
+            -32000,
+            "invalid key: rate limit"
+        )));
+        assert!(!is_rate_limited(&call_error(-32000, "Invalid params")));
+    }
+
+    #[test]
+    fn other_errors_are_not_rate_limited() {
+        let err = jsonrpsee::core::Error::RequestTimeout;
+        assert!(!is_rate_limited(&RpcError::ClientError(Box::new(err))));
+        let err = RpcError::SubscriptionDropped;
+        assert!(!is_rate_limited(&err));
+    }
+}
@@ -0,0 +1,411 @@
+//! Summary of the governance posture of the signing account: its balance locks and their expiry,
+//! its votes and delegations, the deposits it reserved and the referenda awaiting its action.
+use std::collections::BTreeSet;
+
+use anyhow::Result;
+use serde_json::json;
+use subxt::ext::futures::TryStreamExt;
+use subxt::utils::AccountId32;
+
+use crate::chain::ChainConfig;
+use crate::kitchensink;
+use crate::kitchensink::runtime_types::pallet_conviction_voting::vote::Voting;
+use crate::kitchensink::runtime_types::pallet_democracy::types::ReferendumInfo as DemocracyReferendumInfo;
+use crate::kitchensink::runtime_types::pallet_democracy::vote::Voting as DemocracyVoting;
+use crate::{
+    referendum_phase, storage_key_class, storage_key_index, Governance, Program, STORAGE_PAGE_SIZE,
+};
+
+/// Print the report of the signing account
+pub async fn run<C: ChainConfig>(program: &Program<C>) -> Result<()> {
+    let account = program.account_id();
+    let now = program.snapshot().await?.number;
+    let mut actions = Vec::new();
+
+    section(program, "locks");
+    locks(program, &account).await?;
+
+    section(program, "votes and delegations");
+    match program.governance()? {
+        Governance::OpenGov => open_gov_votes(program, &account, now, &mut actions).await?,
+        Governance::Democracy => democracy_votes(program, &account, now, &mut actions).await?,
+    }
+
+    section(program, "deposits");
+    deposits(program, &account, &mut actions).await?;
+
+    section(program, "awaiting action");
+    if actions.is_empty() {
+        none(program);
+    }
+    for action in actions {
+        program
+            .output
+            .record("action", json!({ "action": action }), format!("  {action}"));
+    }
+
+    Ok(())
+}
+
+// Print the title of a section, JSON records carry their kind instead
+fn section<C: ChainConfig>(program: &Program<C>, title: &str) {
+    if !program.output.is_json() {
+        program.output.message(&format!("{title}:"));
+    }
+}
+
+// Print that a section is empty
+fn none<C: ChainConfig>(program: &Program<C>) {
+    if !program.output.is_json() {
+        program.output.message("  none");
+    }
+}
+
+// The balance locks of the account, such as the voting locks
+async fn locks<C: ChainConfig>(program: &Program<C>, account: &AccountId32) -> Result<()> {
+    let api = program.storage().await?;
+    let query = kitchensink::storage().balances().locks(account);
+    let locks = api.fetch_or_default(&query).await?.0;
+    if locks.is_empty() {
+        none(program);
+    }
+
+    for lock in locks {
+        let id = String::from_utf8_lossy(&lock.id).trim_end().to_string();
+        program.output.record(
+            "lock",
+            json!({ "id": id, "amount": lock.amount }),
+            format!("  {id}: {}", program.balance(lock.amount)),
+        );
+    }
+    Ok(())
+}
+
+// Report a lock kept after removing votes or undelegating, which expires at `block`
+fn prior_lock<C: ChainConfig>(
+    program: &Program<C>,
+    class: Option<u16>,
+    (block, amount): (u32, u128),
+    now: u32,
+    actions: &mut Vec<String>,
+) {
+    if amount == 0 {
+        return;
+    }
+
+    let scope = class.map_or(String::new(), |class| format!(" of class {class}"));
+    program.output.record(
+        "prior_lock",
+        json!({ "class": class, "amount": amount, "until": block }),
+        format!(
+            "  prior lock{scope}: {} until {}",
+            program.balance(amount),
+            program.describe_block(block, now)
+        ),
+    );
+    if block <= now {
+        actions.push(format!(
+            "unlock the expired lock{scope} of {}",
+            program.balance(amount)
+        ));
+    }
+}
+
+// Votes and delegations per class, and the ongoing referenda without a vote
+async fn open_gov_votes<C: ChainConfig>(
+    program: &Program<C>,
+    account: &AccountId32,
+    now: u32,
+    actions: &mut Vec<String>,
+) -> Result<()> {
+    let ongoing = program.cached_ongoing_referenda().await?;
+    let ongoing_indices = ongoing
+        .values()
+        .flatten()
+        .map(|(index, _)| *index)
+        .collect::<BTreeSet<_>>();
+
+    let query = kitchensink::storage()
+        .conviction_voting()
+        .voting_for_iter1(account);
+
+    let mut voted = BTreeSet::new();
+    let mut delegated = BTreeSet::new();
+    let entries = program.stream_storage(&query, STORAGE_PAGE_SIZE);
+    tokio::pin!(entries);
+    while let Some((key, voting)) = entries.try_next().await? {
+        let class = storage_key_class(&key);
+        let (delegations, prior) = match voting {
+            Voting::Casting(casting) => {
+                for (index, vote) in casting.votes.0 {
+                    voted.insert(index);
+                    let ongoing = ongoing_indices.contains(&index);
+                    program.output.record(
+                        "vote",
+                        json!({
+                            "class": class,
+                            "index": index,
+                            "vote": format!("{vote:?}"),
+                            "ongoing": ongoing,
+                        }),
+                        format!(
+                            "  class {class}: #{index} {vote:?}{}",
+                            if ongoing { "" } else { " (finished)" }
+                        ),
+                    );
+                    if !ongoing {
+                        actions.push(format!(
+                            "remove the vote on the finished referendum #{index} of class {class}"
+                        ));
+                    }
+                }
+                (casting.delegations, casting.prior)
+            }
+            Voting::Delegating(delegating) => {
+                delegated.insert(class);
+                program.output.record(
+                    "delegation",
+                    json!({
+                        "class": class,
+                        "target": program.address(&delegating.target),
+                        "balance": delegating.balance,
+                        "conviction": format!("{:?}", delegating.conviction),
+                    }),
+                    format!(
+                        "  class {class}: delegating {} to {} with {:?}",
+                        program.balance(delegating.balance),
+                        program.address(&delegating.target),
+                        delegating.conviction
+                    ),
+                );
+                (delegating.delegations, delegating.prior)
+            }
+            Voting::__Ignore(_) => continue,
+        };
+
+        if delegations.votes > 0 {
+            program.output.record(
+                "delegated",
+                json!({ "class": class, "votes": delegations.votes, "capital": delegations.capital }),
+                format!(
+                    "  class {class}: {} votes delegated by others, from {}",
+                    delegations.votes,
+                    program.balance(delegations.capital)
+                ),
+            );
+        }
+        prior_lock(program, Some(class), (prior.0, prior.1), now, actions);
+    }
+    if voted.is_empty() && delegated.is_empty() {
+        none(program);
+    }
+
+    for (track, referenda) in &ongoing {
+        for (index, status) in referenda {
+            if !voted.contains(index) && !delegated.contains(track) {
+                actions.push(format!(
+                    "vote on referendum #{index} of track {track} ({})",
+                    referendum_phase(status)
+                ));
+            }
+            if status.submission_deposit.who == *account && status.decision_deposit.is_none() {
+                actions.push(format!("place the decision deposit of referendum #{index}"));
+            }
+        }
+    }
+    Ok(())
+}
+
+// Votes and delegation of the democracy pallet, and the ongoing referenda without a vote
+async fn democracy_votes<C: ChainConfig>(
+    program: &Program<C>,
+    account: &AccountId32,
+    now: u32,
+    actions: &mut Vec<String>,
+) -> Result<()> {
+    let api = program.storage().await?;
+    let query = kitchensink::storage().democracy().referendum_info_of_iter();
+    let mut ongoing = BTreeSet::new();
+    for (key, info) in program.iter_cached_storage(&query).await? {
+        if let DemocracyReferendumInfo::Ongoing(_) = info {
+            ongoing.insert(storage_key_index(&key));
+        }
+    }
+
+    let query = kitchensink::storage().democracy().voting_of(account);
+    let (voted, delegations, prior) = match api.fetch(&query).await? {
+        Some(DemocracyVoting::Direct {
+            votes,
+            delegations,
+            prior,
+        }) => {
+            let mut voted = BTreeSet::new();
+            for (index, vote) in votes.0 {
+                voted.insert(index);
+                program.output.record(
+                    "vote",
+                    json!({ "index": index, "vote": format!("{vote:?}"), "ongoing": ongoing.contains(&index) }),
+                    format!("  #{index} {vote:?}"),
+                );
+            }
+            if voted.is_empty() {
+                none(program);
+            }
+            (
+                Some(voted),
+                (delegations.votes, delegations.capital),
+                (prior.0, prior.1),
+            )
+        }
+        Some(DemocracyVoting::Delegating {
+            balance,
+            target,
+            conviction,
+            delegations,
+            prior,
+        }) => {
+            program.output.record(
+                "delegation",
+                json!({
+                    "target": program.address(&target),
+                    "balance": balance,
+                    "conviction": format!("{conviction:?}"),
+                }),
+                format!(
+                    "  delegating {} to {} with {conviction:?}",
+                    program.balance(balance),
+                    program.address(&target)
+                ),
+            );
+            (
+                None,
+                (delegations.votes, delegations.capital),
+                (prior.0, prior.1),
+            )
+        }
+        None => {
+            none(program);
+            (Some(BTreeSet::new()), (0, 0), (0, 0))
+        }
+    };
+
+    let (votes, capital) = delegations;
+    if votes > 0 {
+        program.output.record(
+            "delegated",
+            json!({ "votes": votes, "capital": capital }),
+            format!(
+                "  {votes} votes delegated by others, from {}",
+                program.balance(capital)
+            ),
+        );
+    }
+    prior_lock(program, None, prior, now, actions);
+
+    // delegators vote through their delegate
+    if let Some(voted) = voted {
+        for index in ongoing.difference(&voted) {
+            actions.push(format!("vote on referendum #{index}"));
+        }
+    }
+    Ok(())
+}
+
+// Deposits reserved for preimages, proposals and referenda
+async fn deposits<C: ChainConfig>(
+    program: &Program<C>,
+    account: &AccountId32,
+    actions: &mut Vec<String>,
+) -> Result<()> {
+    let metadata = program.api.metadata();
+    let api = program.storage().await?;
+    let mut count = 0;
+    let mut record = |fields: serde_json::Value, text: String| {
+        count += 1;
+        program
+            .output
+            .record("deposit", fields, format!("  {text}"));
+    };
+
+    if metadata.pallet_by_name("Preimage").is_some() {
+        for entry in program.preimages().await? {
+            if let Some((who, amount)) = entry.deposit {
+                if who == *account {
+                    record(
+                        json!({ "reason": "preimage", "hash": entry.hash, "amount": amount }),
+                        format!("preimage {:?}: {}", entry.hash, program.balance(amount)),
+                    );
+                }
+            }
+        }
+    }
+
+    if metadata.pallet_by_name("Democracy").is_some() {
+        let query = kitchensink::storage().democracy().public_props();
+        let indices = api
+            .fetch_or_default(&query)
+            .await?
+            .0
+            .into_iter()
+            .map(|(index, _, _)| index)
+            .collect::<Vec<_>>();
+        let queries = indices
+            .iter()
+            .map(|index| kitchensink::storage().democracy().deposit_of(index))
+            .collect::<Vec<_>>();
+        let deposits = program.fetch_many(&queries).await?;
+        for (index, deposit) in indices.into_iter().zip(deposits) {
+            let Some((backers, amount)) = deposit else {
+                continue;
+            };
+            // the proposer and every second reserve the deposit again
+            let times = backers.0.iter().filter(|who| *who == account).count() as u128;
+            if times > 0 {
+                record(
+                    json!({ "reason": "proposal", "index": index, "amount": amount * times }),
+                    format!("proposal #{index}: {}", program.balance(amount * times)),
+                );
+            }
+        }
+    }
+
+    if metadata.pallet_by_name("Referenda").is_some() {
+        for (index, status) in program
+            .cached_ongoing_referenda()
+            .await?
+            .into_values()
+            .flatten()
+        {
+            if status.submission_deposit.who == *account {
+                let amount = status.submission_deposit.amount;
+                record(
+                    json!({ "reason": "submission", "index": index, "amount": amount }),
+                    format!(
+                        "submission of referendum #{index}: {}",
+                        program.balance(amount)
+                    ),
+                );
+            }
+            if let Some(deposit) = status.decision_deposit.filter(|d| d.who == *account) {
+                record(
+                    json!({ "reason": "decision", "index": index, "amount": deposit.amount }),
+                    format!(
+                        "decision of referendum #{index}: {}",
+                        program.balance(deposit.amount)
+                    ),
+                );
+            }
+        }
+
+        for index in program.refundable_submission_deposits().await? {
+            actions.push(format!(
+                "refund the submission deposit of referendum #{index}"
+            ));
+        }
+    }
+
+    if count == 0 {
+        none(program);
+    }
+    Ok(())
+}
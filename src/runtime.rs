@@ -0,0 +1,127 @@
+//! Runtimes with statically generated types compiled into the binary.
+//!
+//! The kitchensink runtime is always available, and builds the calls and queries of every command.
+//! Others are enabled with cargo features, and only decode the account of `show-balance
+//! --runtime`. They expect their metadata next to `metadata.scale`, e.g. for polkadot:
+//!
+//! ```sh
+//! subxt metadata --url wss://rpc.polkadot.io > polkadot_metadata.scale
+//! cargo build --features polkadot
+//! ```
+use anyhow::Result;
+use codec::Decode;
+use subxt::storage::Storage;
+use subxt::utils::AccountId32;
+use subxt::{Metadata, OnlineClient};
+
+use crate::chain::ChainConfig;
+use crate::{kitchensink, metadata};
+
+#[cfg(all(feature = "polkadot", missing_polkadot_metadata))]
+compile_error!(
+    "the polkadot feature needs polkadot_metadata.scale, fetch it with \
+     `subxt metadata --url wss://rpc.polkadot.io > polkadot_metadata.scale`"
+);
+
+#[cfg(all(feature = "kusama", missing_kusama_metadata))]
+compile_error!(
+    "the kusama feature needs kusama_metadata.scale, fetch it with \
+     `subxt metadata --url wss://kusama-rpc.polkadot.io > kusama_metadata.scale`"
+);
+
+#[cfg(all(feature = "polkadot", not(missing_polkadot_metadata)))]
+#[subxt::subxt(runtime_metadata_path = "polkadot_metadata.scale")]
+pub mod polkadot {}
+
+#[cfg(all(feature = "kusama", not(missing_kusama_metadata)))]
+#[subxt::subxt(runtime_metadata_path = "kusama_metadata.scale")]
+pub mod kusama {}
+
+/// Runtimes compiled into the binary
+#[derive(PartialEq, Debug, Clone, Copy, clap::ValueEnum)]
+pub enum Runtime {
+    Kitchensink,
+    #[cfg(all(feature = "polkadot", not(missing_polkadot_metadata)))]
+    Polkadot,
+    #[cfg(all(feature = "kusama", not(missing_kusama_metadata)))]
+    Kusama,
+}
+
+impl Runtime {
+    /// All the compiled runtimes
+    pub fn all() -> &'static [Self] {
+        &[
+            Self::Kitchensink,
+            #[cfg(all(feature = "polkadot", not(missing_polkadot_metadata)))]
+            Self::Polkadot,
+            #[cfg(all(feature = "kusama", not(missing_kusama_metadata)))]
+            Self::Kusama,
+        ]
+    }
+
+    /// Whether the generated types of this runtime match the given metadata
+    pub fn is_codegen_valid_for(&self, metadata: &Metadata) -> bool {
+        match self {
+            Self::Kitchensink => kitchensink::is_codegen_valid_for(metadata),
+            #[cfg(all(feature = "polkadot", not(missing_polkadot_metadata)))]
+            Self::Polkadot => polkadot::is_codegen_valid_for(metadata),
+            #[cfg(all(feature = "kusama", not(missing_kusama_metadata)))]
+            Self::Kusama => kusama::is_codegen_valid_for(metadata),
+        }
+    }
+
+    /// The metadata the types of this runtime are generated from
+    fn bundled_metadata(&self) -> &'static [u8] {
+        match self {
+            Self::Kitchensink => include_bytes!("../metadata.scale"),
+            #[cfg(all(feature = "polkadot", not(missing_polkadot_metadata)))]
+            Self::Polkadot => include_bytes!("../polkadot_metadata.scale"),
+            #[cfg(all(feature = "kusama", not(missing_kusama_metadata)))]
+            Self::Kusama => include_bytes!("../kusama_metadata.scale"),
+        }
+    }
+
+    /// Decode the metadata the types of this runtime are generated from
+    pub fn metadata(&self) -> Result<Metadata> {
+        Ok(Metadata::decode(&mut self.bundled_metadata())?)
+    }
+
+    /// Spec version of the runtime the types are generated from
+    pub fn spec_version(&self) -> Option<u32> {
+        metadata::spec_version(&self.metadata().ok()?)
+    }
+
+    /// Find the compiled runtime matching the given metadata
+    pub fn detect(metadata: &Metadata) -> Option<Self> {
+        Self::all()
+            .iter()
+            .copied()
+            .find(|runtime| runtime.is_codegen_valid_for(metadata))
+    }
+
+    /// Fetch the account info of an account with the generated types of this runtime
+    pub async fn account_info<C: ChainConfig>(
+        &self,
+        storage: &Storage<C, OnlineClient<C>>,
+        account: &AccountId32,
+    ) -> Result<String> {
+        let info = match self {
+            Self::Kitchensink => {
+                let query = kitchensink::storage().system().account(account);
+                format!("{:?}", storage.fetch_or_default(&query).await?.data)
+            }
+            #[cfg(all(feature = "polkadot", not(missing_polkadot_metadata)))]
+            Self::Polkadot => {
+                let query = polkadot::storage().system().account(account);
+                format!("{:?}", storage.fetch_or_default(&query).await?.data)
+            }
+            #[cfg(all(feature = "kusama", not(missing_kusama_metadata)))]
+            Self::Kusama => {
+                let query = kusama::storage().system().account(account);
+                format!("{:?}", storage.fetch_or_default(&query).await?.data)
+            }
+        };
+
+        Ok(info)
+    }
+}
@@ -0,0 +1,465 @@
+//! Scenarios scripted in YAML: steps run in order, each signed by a dev user, whose results are
+//! captured in variables for the following steps.
+//!
+//! ```yaml
+//! steps:
+//!   - note-preimage: { remark: "hello" }
+//!     capture: preimage
+//!   - propose: { hash: "${preimage.hash}", len: "${preimage.len}" }
+//!     capture: proposal
+//!   - second: { index: "${proposal.index}" }
+//!     as: bob
+//!   - wait-for: { event: "Democracy::Started", timeout: 600 }
+//!     capture: referendum
+//!   - vote: { index: "${referendum.ref_index}", balance: "10 UNIT", conviction: 1x }
+//!     as: bob
+//!   - wait-for:
+//!       event: Democracy::Passed
+//!       where: { ref_index: "${referendum.ref_index}" }
+//! ```
+//!
+//! A string that is only a `${name.field}` reference is replaced by the captured value, keeping
+//! its type, references inside longer strings are interpolated. Wait-for steps capture the fields
+//! of the event and the number of its block. They only see the blocks finalized once the step
+//! starts: an event emitted by a previous step, or in a block finalized while it ran, is missed.
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+use serde_json::{json, Map, Value as Json};
+use subxt::ext::futures::StreamExt;
+
+use crate::call::CallDescription;
+use crate::chain::ChainConfig;
+use crate::error::CliError;
+use crate::kitchensink::runtime_types::frame_support::traits::preimages::Bounded;
+use crate::kitchensink::runtime_types::frame_support::traits::schedule::DispatchTime;
+use crate::units::{parse_h256, Balance, Conviction};
+use crate::{
+    dynamic, kitchensink, output, print, success, Governance, Program, User, DEFAULT_ENACT_AFTER,
+    PROPOSAL_DEPOSIT,
+};
+
+/// A scenario, its steps run in order
+#[derive(Deserialize, Debug)]
+pub struct Script {
+    pub steps: Vec<Step>,
+}
+
+/// A step of a scenario
+#[derive(Deserialize, Debug)]
+pub struct Step {
+    /// Dev user signing the step [default: the signing user of the command line]
+    #[serde(rename = "as")]
+    pub user: Option<User>,
+    /// Variable holding the result of the step
+    pub capture: Option<String>,
+    /// The action, before its variable references are resolved
+    #[serde(flatten)]
+    pub action: Map<String, Json>,
+}
+
+// Action of a step, once its variable references are resolved
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "kebab-case")]
+enum Action {
+    NotePreimage(Preimage),
+    Propose {
+        hash: String,
+        len: u32,
+    },
+    Second {
+        index: u32,
+    },
+    Vote {
+        index: u32,
+        balance: Json,
+        conviction: Option<String>,
+        #[serde(default = "aye")]
+        aye: bool,
+        /// Overwrite an existing vote of the user on the referendum
+        #[serde(default)]
+        replace: bool,
+    },
+    /// Wait for an event of a block finalized after the step starts
+    WaitFor {
+        /// `Pallet::Event`
+        event: String,
+        /// Fields the event must have
+        #[serde(default, rename = "where")]
+        filter: Map<String, Json>,
+        /// Seconds to wait for the event [default: --timeout]
+        timeout: Option<u64>,
+    },
+    Call(CallDescription),
+}
+
+// Content of a noted preimage
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "kebab-case")]
+enum Preimage {
+    Remark(String),
+    Hex(String),
+    Call(CallDescription),
+}
+
+// Votes are ayes unless `aye: false`
+fn aye() -> bool {
+    true
+}
+
+impl Script {
+    /// Read a script from a YAML file
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        serde_yaml::from_str(&content).with_context(|| format!("invalid script {}", path.display()))
+    }
+}
+
+/// Run the steps of a script file
+pub async fn run<C: ChainConfig>(program: &Program<C>, path: &Path) -> Result<()> {
+    let script = Script::from_file(path)?;
+    let count = script.steps.len();
+    let mut variables = Map::new();
+
+    for (number, step) in (1..).zip(script.steps) {
+        let invalid = |source: anyhow::Error| CliError::Validation {
+            what: format!("step {number}"),
+            source,
+        };
+        let action = resolve(&Json::Object(step.action), &variables).map_err(invalid)?;
+        let action: Action = serde_json::from_value(action).map_err(|err| invalid(err.into()))?;
+
+        let as_user;
+        let program = match step.user {
+            Some(user) => {
+                as_user = program.as_user(user);
+                &as_user
+            }
+            None => program,
+        };
+        print!(program, "step {number}/{count}: {}", action.name());
+        // each step reads the state left by the previous ones
+        program.unpin();
+        let value = run_step(program, action)
+            .await
+            .with_context(|| format!("step {number} failed"))?;
+
+        if let Some(name) = step.capture {
+            program.output.record(
+                "capture",
+                json!({ "name": name, "value": value }),
+                format!("{name} = {value}"),
+            );
+            variables.insert(name, value);
+        }
+    }
+
+    success!(program, "script completed, {count} steps");
+    Ok(())
+}
+
+impl Action {
+    // Name of the action, as written in the scripts
+    fn name(&self) -> &'static str {
+        match self {
+            Action::NotePreimage(_) => "note-preimage",
+            Action::Propose { .. } => "propose",
+            Action::Second { .. } => "second",
+            Action::Vote { .. } => "vote",
+            Action::WaitFor { .. } => "wait-for",
+            Action::Call(_) => "call",
+        }
+    }
+}
+
+// Run the action of a step, returning the value it captures
+async fn run_step<C: ChainConfig>(program: &Program<C>, action: Action) -> Result<Json> {
+    match action {
+        Action::NotePreimage(preimage) => {
+            program.require_pallet("Preimage")?;
+            let image = match preimage {
+                Preimage::Remark(remark) => {
+                    let call = dynamic::remark(remark.into_bytes());
+                    program.api.tx().call_data(&call)?
+                }
+                Preimage::Hex(hex) => hex::decode(hex.trim_start_matches("0x"))?,
+                Preimage::Call(description) => {
+                    program.api.tx().call_data(&description.payload()?)?
+                }
+            };
+            let (hash, len) = program.note_preimage(image).await?;
+            Ok(json!({ "hash": hash, "len": len }))
+        }
+        Action::Propose { hash, len } => {
+            let hash = parse_h256(&hash).map_err(|err| anyhow!(err))?;
+            if program.governance()? == Governance::OpenGov {
+                let enactment = DispatchTime::After(DEFAULT_ENACT_AFTER);
+                let submitted = program.submit_referendum(hash, len, enactment).await?;
+                return Ok(json!({ "index": submitted.index, "track": submitted.track }));
+            }
+
+            program.check_preimage(hash, len).await?;
+            let deposit = Some(("deposit", PROPOSAL_DEPOSIT));
+            let events = if program.dynamic {
+                let tx = dynamic::propose(hash, len, PROPOSAL_DEPOSIT);
+                program.confirm_transaction(&tx, deposit).await?;
+                program.submit_and_watch(&tx).await?
+            } else {
+                let proposal = Bounded::Lookup { hash, len };
+                let tx = kitchensink::tx()
+                    .democracy()
+                    .propose(proposal, PROPOSAL_DEPOSIT);
+                program.confirm_transaction(&tx, deposit).await?;
+                program.submit_and_watch(&tx).await?
+            };
+            let proposed = events
+                .find_first::<kitchensink::democracy::events::Proposed>()?
+                .ok_or_else(|| CliError::MissingEvent("Democracy::Proposed".into()))?;
+            Ok(json!({ "index": proposed.proposal_index }))
+        }
+        Action::Second { index } => {
+            program.require_pallet("Democracy")?;
+            let tx = kitchensink::tx().democracy().second(index);
+            program.confirm_transaction(&tx, None).await?;
+            let events = program.submit_and_watch(&tx).await?;
+            events
+                .find_first::<kitchensink::democracy::events::Seconded>()?
+                .ok_or_else(|| CliError::MissingEvent("Democracy::Seconded".into()))?;
+            Ok(json!({ "index": index }))
+        }
+        Action::Vote {
+            index,
+            balance,
+            conviction,
+            aye,
+            replace,
+        } => {
+            let balance = match balance {
+                Json::String(balance) => balance,
+                balance => balance.to_string(),
+            };
+            let balance = program.planck(&Balance::parse(&balance)?)?;
+            let conviction = Conviction::parse_or_default(conviction.as_deref())?;
+            program.check_ongoing(index).await?;
+            program.check_existing_vote(index, replace).await?;
+            program.vote(index, aye, conviction, balance).await?;
+            Ok(json!({ "index": index }))
+        }
+        Action::WaitFor {
+            event,
+            filter,
+            timeout,
+        } => {
+            let timeout = timeout.map(Duration::from_secs).or(program.timeout);
+            wait_for(program, &event, &filter, timeout).await
+        }
+        Action::Call(description) => {
+            let tx = description.payload()?;
+            program.confirm_transaction(&tx, None).await?;
+            let events = program.submit_and_watch(&tx).await?;
+            output::events(&events)
+        }
+    }
+}
+
+// Wait for an event with the given fields, returning its fields and block number
+async fn wait_for<C: ChainConfig>(
+    program: &Program<C>,
+    name: &str,
+    filter: &Map<String, Json>,
+    timeout: Option<Duration>,
+) -> Result<Json> {
+    let (pallet, variant) = name
+        .split_once("::")
+        .ok_or_else(|| anyhow!("invalid event `{name}`, expected `Pallet::Event`"))?;
+
+    let find = async {
+        let mut blocks = program.finalized_blocks().boxed();
+        while let Some(block) = blocks.next().await {
+            let block = block?;
+            for event in block.events.iter() {
+                let event = event?;
+                if event.pallet_name() != pallet || event.variant_name() != variant {
+                    continue;
+                }
+                let fields = output::event_fields(&event)?["fields"].take();
+                if filter
+                    .iter()
+                    .all(|(key, value)| has_value(&fields[key], value))
+                {
+                    return Ok((block.number, fields));
+                }
+            }
+        }
+        Err(anyhow::Error::from(CliError::Connection(anyhow!(
+            "the block subscription ended before {name}"
+        ))))
+    };
+    let wait = async {
+        match timeout {
+            Some(timeout) => {
+                tokio::time::timeout(timeout, find)
+                    .await
+                    .map_err(|_| CliError::Timeout {
+                        event: name.to_string(),
+                        secs: timeout.as_secs(),
+                    })?
+            }
+            None => find.await,
+        }
+    };
+    let (found, _) = program
+        .with_spinner(&format!("waiting for {name}"), wait)
+        .await;
+    let (block, fields) = found?;
+    print!(program, "{name} found in block {block}");
+
+    Ok(match fields {
+        Json::Object(mut fields) => {
+            fields.insert("block".to_string(), block.into());
+            Json::Object(fields)
+        }
+        fields => json!({ "block": block, "fields": fields }),
+    })
+}
+
+// Whether an event field has the expected value, numbers matching their string form
+fn has_value(field: &Json, expected: &Json) -> bool {
+    let text = |value: &Json| match value {
+        Json::String(value) => value.clone(),
+        value => value.to_string(),
+    };
+    field == expected || text(field) == text(expected)
+}
+
+// Replace the variable references in the strings of a value
+fn resolve(value: &Json, variables: &Map<String, Json>) -> Result<Json> {
+    Ok(match value {
+        // a single reference keeps the type of the captured value
+        Json::String(text) if text.starts_with("${") && text.find('}') == Some(text.len() - 1) => {
+            lookup(&text[2..text.len() - 1], variables)?
+        }
+        Json::String(text) => Json::String(interpolate(text, variables)?),
+        Json::Array(values) => Json::Array(
+            values
+                .iter()
+                .map(|value| resolve(value, variables))
+                .collect::<Result<_>>()?,
+        ),
+        Json::Object(fields) => Json::Object(
+            fields
+                .iter()
+                .map(|(key, value)| Ok((key.clone(), resolve(value, variables)?)))
+                .collect::<Result<_>>()?,
+        ),
+        value => value.clone(),
+    })
+}
+
+// Replace the `${path}` references inside a string by their values
+fn interpolate(text: &str, variables: &Map<String, Json>) -> Result<String> {
+    let mut resolved = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("${") {
+        let Some(len) = rest[start..].find('}') else {
+            break;
+        };
+        resolved.push_str(&rest[..start]);
+        match lookup(&rest[start + 2..start + len], variables)? {
+            Json::String(value) => resolved.push_str(&value),
+            value => resolved.push_str(&value.to_string()),
+        }
+        rest = &rest[start + len + 1..];
+    }
+    resolved.push_str(rest);
+    Ok(resolved)
+}
+
+// Value at a `name.field.subfield` path of the captured variables
+fn lookup(path: &str, variables: &Map<String, Json>) -> Result<Json> {
+    let mut segments = path.split('.');
+    let name = segments.next().unwrap_or_default();
+    let mut value = variables
+        .get(name)
+        .ok_or_else(|| anyhow!("undefined variable `{name}`"))?;
+    for segment in segments {
+        value = match value {
+            Json::Array(values) => segment.parse::<usize>().ok().and_then(|i| values.get(i)),
+            value => value.get(segment),
+        }
+        .ok_or_else(|| anyhow!("no `{segment}` in `{path}`"))?;
+    }
+    Ok(value.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variables() -> Map<String, Json> {
+        let Json::Object(variables) = json!({
+            "preimage": { "hash": "0x01", "len": 5 },
+            "referendum": { "ref_index": 3, "votes": [{ "who": "bob" }, { "who": "charlie" }] },
+        }) else {
+            unreachable!()
+        };
+        variables
+    }
+
+    #[test]
+    fn single_references_keep_their_type() {
+        let variables = variables();
+        let value = json!({ "len": "${preimage.len}", "votes": "${referendum.votes}" });
+        assert_eq!(
+            resolve(&value, &variables).unwrap(),
+            json!({ "len": 5, "votes": [{ "who": "bob" }, { "who": "charlie" }] })
+        );
+    }
+
+    #[test]
+    fn references_are_interpolated_in_strings() {
+        let variables = variables();
+        let value = json!(["hash ${preimage.hash} of ${preimage.len} bytes", { "ref": "#${referendum.ref_index}" }]);
+        assert_eq!(
+            resolve(&value, &variables).unwrap(),
+            json!(["hash 0x01 of 5 bytes", { "ref": "#3" }])
+        );
+        // two references are interpolated rather than looked up as one
+        assert_eq!(
+            resolve(&json!("${preimage.len}${referendum.ref_index}"), &variables).unwrap(),
+            json!("53")
+        );
+    }
+
+    #[test]
+    fn lookups_index_arrays() {
+        let variables = variables();
+        assert_eq!(
+            lookup("referendum.votes.1.who", &variables).unwrap(),
+            json!("charlie")
+        );
+        assert!(lookup("referendum.votes.2", &variables).is_err());
+        assert!(lookup("referendum.votes.who", &variables).is_err());
+    }
+
+    #[test]
+    fn unterminated_references_are_kept() {
+        let variables = variables();
+        assert_eq!(
+            interpolate("${preimage.len} and ${preimage", &variables).unwrap(),
+            "5 and ${preimage"
+        );
+    }
+
+    #[test]
+    fn undefined_variables_fail() {
+        let variables = variables();
+        let err = resolve(&json!("${proposal.index}"), &variables).unwrap_err();
+        assert_eq!(err.to_string(), "undefined variable `proposal`");
+        let err = resolve(&json!("at ${preimage.size}"), &variables).unwrap_err();
+        assert_eq!(err.to_string(), "no `size` in `preimage.size`");
+    }
+}
@@ -0,0 +1,62 @@
+//! Graceful shutdown on ctrl-c.
+//!
+//! Once [`install`]ed, the first ctrl-c cancels the running command at its next await point
+//! instead of killing the process in the middle of a write: the long-running commands watch
+//! [`requested`] to save their checkpoint and return, the others are dropped and fail with
+//! [`CliError::Interrupted`]. The output is flushed and the connection closed as the program is
+//! dropped. A second ctrl-c exits immediately.
+use std::sync::OnceLock;
+
+use tokio_util::sync::CancellationToken;
+
+use crate::error::CliError;
+use crate::output;
+
+// Exit code of a process killed by SIGINT, 128 + 2
+const FORCED_EXIT_CODE: i32 = 130;
+
+// Cancelled on the first ctrl-c
+static TOKEN: OnceLock<CancellationToken> = OnceLock::new();
+
+// Token of the process, created on first use
+fn token() -> &'static CancellationToken {
+    TOKEN.get_or_init(CancellationToken::new)
+}
+
+/// Handle ctrl-c, must be called from the tokio runtime
+pub fn install() {
+    tokio::spawn(async {
+        if tokio::signal::ctrl_c().await.is_err() {
+            return;
+        }
+        output::warn("interrupted, shutting down, press ctrl-c again to exit now");
+        token().cancel();
+
+        if tokio::signal::ctrl_c().await.is_ok() {
+            std::process::exit(FORCED_EXIT_CODE);
+        }
+    });
+}
+
+/// Whether ctrl-c was pressed
+pub fn is_requested() -> bool {
+    token().is_cancelled()
+}
+
+/// Wait for ctrl-c
+pub async fn requested() {
+    token().cancelled().await
+}
+
+/// Run a future until ctrl-c, failing with [`CliError::Interrupted`] if it didn't complete.
+/// The future comes first when both are ready, so a command stopping on [`requested`] returns
+/// its own result
+pub async fn or_interrupted<T>(
+    future: impl std::future::Future<Output = anyhow::Result<T>>,
+) -> anyhow::Result<T> {
+    tokio::select! {
+        biased;
+        result = future => result,
+        () = requested() => Err(CliError::Interrupted.into()),
+    }
+}
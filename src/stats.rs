@@ -0,0 +1,155 @@
+//! Statistics of the connection to the node, printed by the `ping` command.
+//!
+//! Every request and subscription going through the RPC client is counted along with its round
+//! trip, so a long-lived session, e.g. the REPL or a script, can tell how busy and how fast its
+//! connection has been since it was opened.
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use serde_json::json;
+use subxt::backend::rpc::{
+    rpc_params, RawRpcFuture, RawRpcSubscription, RawValue, RpcClient, RpcClientT,
+};
+
+use crate::chain::ChainConfig;
+use crate::Program;
+
+/// Counters of the connection, shared by the clones of the program
+#[derive(Clone)]
+pub struct ConnectionStats(Arc<Counters>);
+
+struct Counters {
+    opened: Instant,
+    requests: AtomicU64,
+    subscriptions: AtomicU64,
+    failures: AtomicU64,
+    /// Total round trip of the requests, in microseconds
+    latency: AtomicU64,
+}
+
+/// Wrap a client to count its requests and subscriptions
+pub fn track(client: RpcClient) -> (RpcClient, ConnectionStats) {
+    let stats = ConnectionStats(Arc::new(Counters {
+        opened: Instant::now(),
+        requests: AtomicU64::new(0),
+        subscriptions: AtomicU64::new(0),
+        failures: AtomicU64::new(0),
+        latency: AtomicU64::new(0),
+    }));
+    let client = RpcClient::new(TrackedRpc {
+        client,
+        stats: stats.clone(),
+    });
+    (client, stats)
+}
+
+impl ConnectionStats {
+    /// Time since the connection was opened
+    pub fn uptime(&self) -> Duration {
+        self.0.opened.elapsed()
+    }
+
+    /// Number of requests sent
+    pub fn requests(&self) -> u64 {
+        self.0.requests.load(Ordering::Relaxed)
+    }
+
+    /// Number of subscriptions opened
+    pub fn subscriptions(&self) -> u64 {
+        self.0.subscriptions.load(Ordering::Relaxed)
+    }
+
+    /// Number of failed requests and subscriptions
+    pub fn failures(&self) -> u64 {
+        self.0.failures.load(Ordering::Relaxed)
+    }
+
+    /// Mean round trip of the requests, `None` before the first one
+    pub fn mean_latency(&self) -> Option<Duration> {
+        let requests = self.requests();
+        (requests > 0)
+            .then(|| Duration::from_micros(self.0.latency.load(Ordering::Relaxed) / requests))
+    }
+
+    // Record a completed request
+    fn request(&self, elapsed: Duration, failed: bool) {
+        let counters = &self.0;
+        counters.requests.fetch_add(1, Ordering::Relaxed);
+        counters
+            .latency
+            .fetch_add(elapsed.as_micros() as u64, Ordering::Relaxed);
+        if failed {
+            counters.failures.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Measure the round trip of a request to the node and print the statistics of the connection
+pub async fn ping<C: ChainConfig>(program: &Program<C>) -> Result<()> {
+    let start = Instant::now();
+    let _: serde_json::Value = program.rpc.request("system_health", rpc_params![]).await?;
+    let round_trip = start.elapsed();
+
+    let stats = &program.stats;
+    let mean = stats.mean_latency().unwrap_or_default();
+    program.output.record(
+        "ping",
+        json!({
+            "round_trip_ms": round_trip.as_millis(),
+            "uptime_secs": stats.uptime().as_secs(),
+            "requests": stats.requests(),
+            "subscriptions": stats.subscriptions(),
+            "failures": stats.failures(),
+            "mean_latency_ms": mean.as_millis(),
+        }),
+        format!(
+            "pong in {}ms, connected for {}s: {} requests (mean {}ms), {} subscriptions, {} failures",
+            round_trip.as_millis(),
+            stats.uptime().as_secs(),
+            stats.requests(),
+            mean.as_millis(),
+            stats.subscriptions(),
+            stats.failures()
+        ),
+    );
+    Ok(())
+}
+
+struct TrackedRpc {
+    client: RpcClient,
+    stats: ConnectionStats,
+}
+
+impl RpcClientT for TrackedRpc {
+    fn request_raw<'a>(
+        &'a self,
+        method: &'a str,
+        params: Option<Box<RawValue>>,
+    ) -> RawRpcFuture<'a, Box<RawValue>> {
+        Box::pin(async move {
+            let start = Instant::now();
+            let result = self.client.request_raw(method, params).await;
+            self.stats.request(start.elapsed(), result.is_err());
+            result
+        })
+    }
+
+    fn subscribe_raw<'a>(
+        &'a self,
+        sub: &'a str,
+        params: Option<Box<RawValue>>,
+        unsub: &'a str,
+    ) -> RawRpcFuture<'a, RawRpcSubscription> {
+        Box::pin(async move {
+            let result = self.client.subscribe_raw(sub, params, unsub).await;
+            let counter = match result {
+                Ok(_) => &self.stats.0.subscriptions,
+                Err(_) => &self.stats.0.failures,
+            };
+            counter.fetch_add(1, Ordering::Relaxed);
+            result
+        })
+    }
+}
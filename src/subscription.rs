@@ -0,0 +1,162 @@
+//! Finalized block subscription surviving dropped connections.
+//!
+//! When the subscription fails or ends, it is opened again with an exponential backoff, and the
+//! blocks finalized during the gap are fetched by number before the new ones, so the consumers
+//! see every finalized block exactly once and in order. The same mechanism resumes a stream
+//! after a given block, e.g. the checkpoint of a previous run.
+//!
+//! Only the number and hash of the blocks are yielded, the consumers fetch what they need, such
+//! as the `System::Events` storage, rather than whole blocks.
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use anyhow::Result;
+use subxt::backend::legacy::LegacyRpcMethods;
+use subxt::backend::rpc::RpcClient;
+use subxt::ext::futures::{stream, Stream, StreamExt, TryStreamExt};
+use subxt::utils::H256;
+use subxt::OnlineClient;
+
+use crate::chain::ChainConfig;
+use crate::error::CliError;
+use crate::output;
+
+/// Finalized block of the chain
+#[derive(Debug, Clone, Copy)]
+pub struct FinalizedBlock {
+    pub number: u32,
+    pub hash: H256,
+}
+
+// Attempts to subscribe again before giving up, waiting 1s, 2s, 4s... in between
+const MAX_RETRIES: u32 = 6;
+
+// State of the subscription between two blocks
+struct Subscription<C: ChainConfig> {
+    api: OnlineClient<C>,
+    rpc: LegacyRpcMethods<C>,
+    blocks: Option<stream::BoxStream<'static, Result<FinalizedBlock, subxt::Error>>>,
+    /// Number of the last yielded block
+    last: Option<u32>,
+    /// Numbers of the missed blocks to fetch before `next`
+    missed: VecDeque<u32>,
+    /// Block received after a gap, yielded once the missed blocks are
+    next: Option<FinalizedBlock>,
+    /// Failed attempts to subscribe since the last block
+    retries: u32,
+    /// Whether the subscription was lost, to back off before subscribing again
+    lost: bool,
+}
+
+/// Subscribe to the finalized blocks, resubscribing and backfilling the missed blocks when the
+/// subscription drops. Starts with the blocks finalized after `after` when given
+pub fn finalized_blocks<C: ChainConfig>(
+    api: &OnlineClient<C>,
+    rpc: &RpcClient,
+    after: Option<u32>,
+) -> impl Stream<Item = Result<FinalizedBlock>> + Send + 'static {
+    let state = Subscription {
+        api: api.clone(),
+        rpc: LegacyRpcMethods::new(rpc.clone()),
+        blocks: None,
+        last: after,
+        missed: VecDeque::new(),
+        next: None,
+        retries: 0,
+        lost: false,
+    };
+
+    stream::unfold(Some(state), |state| async move {
+        let mut state = state?;
+        match state.next_block().await {
+            Ok(block) => Some((Ok(block), Some(state))),
+            // the subscription couldn't be restored, end the stream after the error
+            Err(err) => Some((Err(err), None)),
+        }
+    })
+}
+
+impl<C: ChainConfig> Subscription<C> {
+    // Next block in order, from the missed blocks or the subscription
+    async fn next_block(&mut self) -> Result<FinalizedBlock> {
+        loop {
+            if let Some(number) = self.missed.pop_front() {
+                let Some(hash) = self.rpc.chain_get_block_hash(Some(number.into())).await? else {
+                    anyhow::bail!("block {number} not found");
+                };
+                return Ok(self.yielded(FinalizedBlock { number, hash }));
+            }
+            if let Some(block) = self.next.take() {
+                return Ok(self.yielded(block));
+            }
+
+            let blocks = match &mut self.blocks {
+                Some(blocks) => blocks,
+                None => {
+                    self.subscribe().await?;
+                    continue;
+                }
+            };
+
+            match blocks.next().await {
+                Some(Ok(block)) => {
+                    let number = block.number;
+                    match self.last {
+                        // a new subscription starts at the latest finalized block again
+                        Some(last) if number <= last => continue,
+                        Some(last) if number > last + 1 => {
+                            tracing::debug!(from = last + 1, to = number - 1, "backfilling");
+                            self.missed.extend(last + 1..number);
+                            self.next = Some(block);
+                        }
+                        _ => return Ok(self.yielded(block)),
+                    }
+                }
+                Some(Err(err)) => {
+                    output::warn(&format!("block subscription failed, resubscribing: {err}"));
+                    self.blocks = None;
+                    self.lost = true;
+                }
+                None => {
+                    output::warn("block subscription ended, resubscribing");
+                    self.blocks = None;
+                    self.lost = true;
+                }
+            }
+        }
+    }
+
+    // Open the subscription, after a backoff when it was lost
+    async fn subscribe(&mut self) -> Result<()> {
+        loop {
+            if self.lost || self.retries > 0 {
+                let backoff = Duration::from_secs(1 << self.retries);
+                tokio::time::sleep(backoff).await;
+            }
+
+            match self.api.blocks().subscribe_finalized().await {
+                Ok(blocks) => {
+                    let blocks = blocks.map_ok(|block| FinalizedBlock {
+                        number: block.number(),
+                        hash: block.hash(),
+                    });
+                    self.blocks = Some(blocks.boxed());
+                    self.lost = false;
+                    return Ok(());
+                }
+                Err(err) if self.retries < MAX_RETRIES => {
+                    self.retries += 1;
+                    tracing::debug!(%err, retries = self.retries, "failed to subscribe");
+                }
+                Err(err) => return Err(CliError::Connection(err.into()).into()),
+            }
+        }
+    }
+
+    // Record a block as the last yielded one
+    fn yielded(&mut self, block: FinalizedBlock) -> FinalizedBlock {
+        self.last = Some(block.number);
+        self.retries = 0;
+        block
+    }
+}
@@ -0,0 +1,239 @@
+//! Websocket transport for private setups: connecting through a SOCKS5 proxy (e.g. Tor) and
+//! trusting custom certificates for self-signed `wss` endpoints.
+//!
+//! The proxy resolves host names itself, so they don't leak through local DNS queries. Long-lived
+//! sessions, such as the REPL, send websocket pings so idle connections aren't dropped.
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use anyhow::{Context, Result};
+use jsonrpsee::core::async_trait;
+use jsonrpsee::core::client::{
+    ClientBuilder, ReceivedMessage, TransportReceiverT, TransportSenderT,
+};
+use rustls::client::{ServerCertVerified, ServerCertVerifier};
+use rustls::{Certificate, ClientConfig, RootCertStore, ServerName};
+use soketto::connection::{Receiver as WsReceiver, Sender as WsSender};
+use soketto::handshake::{Client, ServerResponse};
+use soketto::Data;
+use subxt::backend::rpc::RpcClient;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio_rustls::TlsConnector;
+use tokio_util::compat::{Compat, TokioAsyncReadCompatExt};
+use url::Url;
+
+/// Options of the websocket transport
+#[derive(Debug, Clone, Default)]
+pub struct Transport {
+    /// Address of a SOCKS5 proxy to connect through
+    pub socks5: Option<String>,
+    /// PEM file of additional trusted certificates
+    pub ca_cert: Option<PathBuf>,
+    /// Accept any certificate, for self-signed endpoints
+    pub insecure: bool,
+    /// Interval of the pings keeping an idle connection open
+    pub keep_alive: Option<Duration>,
+}
+
+impl Transport {
+    /// Whether the default transport can be used instead
+    pub fn is_default(&self) -> bool {
+        self.socks5.is_none()
+            && self.ca_cert.is_none()
+            && !self.insecure
+            && self.keep_alive.is_none()
+    }
+
+    /// Connect to a `ws://` or `wss://` url
+    pub async fn connect(&self, url: &str) -> Result<RpcClient> {
+        let url = Url::parse(url).with_context(|| format!("invalid url {url}"))?;
+        let host = url.host_str().context("url without host")?.to_string();
+        let port = url.port_or_known_default().context("url without port")?;
+
+        let tcp = match &self.socks5 {
+            Some(proxy) => socks5_connect(proxy, &host, port).await?,
+            None => TcpStream::connect((host.as_str(), port)).await?,
+        };
+        let stream: Box<dyn Io> = match url.scheme() {
+            "ws" => Box::new(tcp),
+            "wss" => {
+                let connector = TlsConnector::from(Arc::new(self.tls_config()?));
+                let server_name = ServerName::try_from(host.as_str())?;
+                Box::new(connector.connect(server_name, tcp).await?)
+            }
+            scheme => anyhow::bail!("unsupported url scheme {scheme}"),
+        };
+
+        let resource = match url.query() {
+            Some(query) => format!("{}?{query}", url.path()),
+            None => url.path().to_string(),
+        };
+        let host_header = match url.port() {
+            Some(port) => format!("{host}:{port}"),
+            None => host,
+        };
+        let mut client = Client::new(stream.compat(), &host_header, &resource);
+        match client.handshake().await? {
+            ServerResponse::Accepted { .. } => {}
+            ServerResponse::Redirect { status_code, .. }
+            | ServerResponse::Rejected { status_code } => {
+                anyhow::bail!("websocket handshake rejected with status {status_code}")
+            }
+        }
+
+        let (sender, receiver) = client.into_builder().finish();
+        let mut builder = ClientBuilder::default();
+        if let Some(interval) = self.keep_alive {
+            builder = builder.ping_interval(interval);
+        }
+        let client = builder.build_with_tokio(Sender(sender), Receiver(receiver));
+        Ok(RpcClient::new(client))
+    }
+
+    // TLS configuration trusting the system roots and the custom certificates
+    fn tls_config(&self) -> Result<ClientConfig> {
+        let mut roots = RootCertStore::empty();
+        for cert in rustls_native_certs::load_native_certs()? {
+            // skip the system certificates rustls can't parse
+            let _ = roots.add(&Certificate(cert.0));
+        }
+        if let Some(path) = &self.ca_cert {
+            for cert in read_certs(path)? {
+                roots.add(&Certificate(cert))?;
+            }
+        }
+
+        let mut config = ClientConfig::builder()
+            .with_safe_defaults()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        if self.insecure {
+            config
+                .dangerous()
+                .set_certificate_verifier(Arc::new(AcceptAnyCertificate));
+        }
+
+        Ok(config)
+    }
+}
+
+// Read the certificates of a PEM file
+fn read_certs(path: &Path) -> Result<Vec<Vec<u8>>> {
+    let file =
+        std::fs::File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+    rustls_pemfile::certs(&mut io::BufReader::new(file))
+        .with_context(|| format!("invalid certificates in {}", path.display()))
+}
+
+// Open a TCP connection to `host:port` through a SOCKS5 proxy, see RFC 1928
+async fn socks5_connect(proxy: &str, host: &str, port: u16) -> Result<TcpStream> {
+    let mut stream = TcpStream::connect(proxy)
+        .await
+        .with_context(|| format!("failed to connect to proxy {proxy}"))?;
+
+    // version 5, a single authentication method: none
+    stream.write_all(&[5, 1, 0]).await?;
+    let mut reply = [0u8; 2];
+    stream.read_exact(&mut reply).await?;
+    if reply != [5, 0] {
+        anyhow::bail!("proxy {proxy} requires an unsupported authentication method");
+    }
+
+    // connect to a domain name, resolved by the proxy
+    let host_len = u8::try_from(host.len()).context("host name too long")?;
+    let mut request = vec![5, 1, 0, 3, host_len];
+    request.extend(host.as_bytes());
+    request.extend(port.to_be_bytes());
+    stream.write_all(&request).await?;
+
+    let mut reply = [0u8; 4];
+    stream.read_exact(&mut reply).await?;
+    if reply[1] != 0 {
+        anyhow::bail!(
+            "proxy {proxy} refused to connect to {host}:{port} (code {})",
+            reply[1]
+        );
+    }
+
+    // skip the bound address and port
+    let address_len = match reply[3] {
+        1 => 4,
+        4 => 16,
+        3 => stream.read_u8().await? as usize,
+        kind => anyhow::bail!("invalid proxy address type {kind}"),
+    };
+    let mut bound = vec![0u8; address_len + 2];
+    stream.read_exact(&mut bound).await?;
+
+    Ok(stream)
+}
+
+// A plain or TLS stream
+trait Io: AsyncRead + AsyncWrite + Unpin + Send {}
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Io for T {}
+
+// Certificate verifier accepting any certificate, used with `--insecure`
+struct AcceptAnyCertificate;
+
+impl ServerCertVerifier for AcceptAnyCertificate {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &Certificate,
+        _intermediates: &[Certificate],
+        _server_name: &ServerName,
+        _scts: &mut dyn Iterator<Item = &[u8]>,
+        _ocsp_response: &[u8],
+        _now: SystemTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+}
+
+// Sending half of the websocket
+struct Sender(WsSender<Compat<Box<dyn Io>>>);
+
+#[async_trait]
+impl TransportSenderT for Sender {
+    type Error = io::Error;
+
+    async fn send(&mut self, msg: String) -> io::Result<()> {
+        self.0.send_text(msg).await.map_err(io::Error::other)?;
+        self.0.flush().await.map_err(io::Error::other)
+    }
+
+    async fn send_ping(&mut self) -> io::Result<()> {
+        let data = soketto::data::ByteSlice125::try_from(&[][..]).map_err(io::Error::other)?;
+        self.0.send_ping(data).await.map_err(io::Error::other)?;
+        self.0.flush().await.map_err(io::Error::other)
+    }
+
+    async fn close(&mut self) -> io::Result<()> {
+        self.0.close().await.map_err(io::Error::other)
+    }
+}
+
+// Receiving half of the websocket
+struct Receiver(WsReceiver<Compat<Box<dyn Io>>>);
+
+#[async_trait]
+impl TransportReceiverT for Receiver {
+    type Error = io::Error;
+
+    async fn receive(&mut self) -> io::Result<ReceivedMessage> {
+        let mut message = Vec::new();
+        match self
+            .0
+            .receive_data(&mut message)
+            .await
+            .map_err(io::Error::other)?
+        {
+            Data::Text(_) => String::from_utf8(message)
+                .map(ReceivedMessage::Text)
+                .map_err(io::Error::other),
+            Data::Binary(_) => Ok(ReceivedMessage::Bytes(message)),
+        }
+    }
+}
@@ -0,0 +1,311 @@
+//! Typed values of the command line arguments: balances in planck or token units, named
+//! convictions, hashes and block numbers.
+use std::fmt::Display;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Context, Result};
+use subxt::utils::{AccountId32, H256};
+
+use crate::chain::ChainProperties;
+use crate::User;
+
+/// Amount of the native token, in planck when given as an integer, e.g. `1000000000000`, or in
+/// token units when given with a decimal point or the token symbol, e.g. `1.5` or `2 DOT`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Balance {
+    Planck(u128),
+    Tokens {
+        /// Decimal amount, digits with at most one decimal point
+        amount: String,
+        symbol: Option<String>,
+    },
+}
+
+impl FromStr for Balance {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().replace('_', "");
+        let split = s.find(|c: char| !c.is_ascii_digit() && c != '.');
+        let (amount, symbol) = match split {
+            Some(index) => (s[..index].trim_end(), Some(s[index..].trim())),
+            None => (s.as_str(), None),
+        };
+
+        let digits = amount.replace('.', "");
+        if digits.is_empty() || amount.matches('.').count() > 1 {
+            return Err(format!("invalid amount `{s}`"));
+        }
+        if let Some(symbol) = symbol.filter(|symbol| !symbol.chars().all(char::is_alphabetic)) {
+            return Err(format!("invalid token symbol `{symbol}`"));
+        }
+
+        if symbol.is_none() && !amount.contains('.') {
+            return amount
+                .parse()
+                .map(Balance::Planck)
+                .map_err(|err| format!("invalid amount `{s}`: {err}"));
+        }
+        Ok(Balance::Tokens {
+            amount: amount.to_string(),
+            symbol: symbol.map(str::to_string),
+        })
+    }
+}
+
+impl Balance {
+    /// Balance of a config or script value, see [`Balance::from_str`]
+    pub fn parse(s: &str) -> Result<Self> {
+        s.parse().map_err(|err| anyhow!("invalid balance: {err}"))
+    }
+
+    /// Amount in planck, converting the token units with the decimals of the chain
+    pub fn planck(&self, properties: &ChainProperties) -> Result<u128> {
+        let (amount, symbol) = match self {
+            Balance::Planck(planck) => return Ok(*planck),
+            Balance::Tokens { amount, symbol } => (amount, symbol),
+        };
+        if let Some(symbol) = symbol.as_ref() {
+            if !symbol.eq_ignore_ascii_case(&properties.symbol) {
+                anyhow::bail!("the chain token is {}, not {symbol}", properties.symbol);
+            }
+        }
+
+        let decimals = properties.decimals as usize;
+        let (units, fraction) = amount.split_once('.').unwrap_or((amount, ""));
+        if fraction.len() > decimals {
+            anyhow::bail!("{amount} has more than the {decimals} decimals of the token");
+        }
+        format!("{units}{fraction:0<decimals$}")
+            .parse::<u128>()
+            .with_context(|| format!("{amount} {} is too large", properties.symbol))
+    }
+}
+
+/// Conviction of a vote, multiplying its weight for a longer lock of the balance
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum Conviction {
+    /// 0.1x votes, no lock
+    #[default]
+    #[value(name = "none", alias = "0")]
+    None,
+    /// 1x votes, locked for 1 lock period
+    #[value(name = "1x", alias = "1")]
+    Locked1x,
+    /// 2x votes, locked for 2 lock periods
+    #[value(name = "2x", alias = "2")]
+    Locked2x,
+    /// 3x votes, locked for 4 lock periods
+    #[value(name = "3x", alias = "3")]
+    Locked3x,
+    /// 4x votes, locked for 8 lock periods
+    #[value(name = "4x", alias = "4")]
+    Locked4x,
+    /// 5x votes, locked for 16 lock periods
+    #[value(name = "5x", alias = "5")]
+    Locked5x,
+    /// 6x votes, locked for 32 lock periods
+    #[value(name = "6x", alias = "6")]
+    Locked6x,
+}
+
+impl Display for Conviction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use clap::ValueEnum;
+        match self.to_possible_value() {
+            Some(value) => f.write_str(value.get_name()),
+            None => write!(f, "{self:?}"),
+        }
+    }
+}
+
+impl Conviction {
+    /// Conviction of its name or index, e.g. `3x` or `3`, from `none` or `0` to `6x` or `6`
+    pub fn parse(s: &str) -> Result<Self, String> {
+        use clap::ValueEnum;
+        let s = s.trim();
+        Self::from_str(s, true).map_err(|_| match s.parse::<u64>() {
+            Ok(index) => format!("invalid conviction {index}, it must be between 0 and 6"),
+            Err(_) => format!(
+                "invalid conviction `{s}`, expected none, 1x, 2x, 3x, 4x, 5x or 6x, or 0 to 6"
+            ),
+        })
+    }
+
+    /// Conviction of an optional config or script value, [`Conviction::None`] when missing
+    pub fn parse_or_default(s: Option<&str>) -> Result<Self> {
+        match s {
+            Some(s) => Self::parse(s).map_err(|err| anyhow!(err)),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// Encoded standard vote, the conviction in the low bits and the aye flag in the high bit
+    pub fn vote(self, aye: bool) -> u8 {
+        self as u8 | if aye { 0b1000_0000 } else { 0 }
+    }
+
+    /// Conviction of its encoded index, as stored by the democracy and conviction voting pallets
+    pub fn from_index(index: u8) -> Option<Self> {
+        use clap::ValueEnum;
+        Self::value_variants().get(index as usize).copied()
+    }
+
+    /// Lock periods of the balance once the referendum is over, for a vote on the winning side
+    pub fn lock_periods(self) -> u32 {
+        match self {
+            Conviction::None => 0,
+            conviction => 1 << (conviction as u32 - 1),
+        }
+    }
+
+    /// Votes of a balance locked with this conviction
+    pub fn votes(self, balance: u128) -> u128 {
+        match self {
+            Conviction::None => balance / 10,
+            conviction => balance.saturating_mul(conviction as u128),
+        }
+    }
+}
+
+/// Hash of a preimage, call or proposal, 32 bytes in hex with or without the `0x` prefix
+pub fn parse_h256(s: &str) -> Result<H256, String> {
+    let s = s.trim();
+    let digits = s.strip_prefix("0x").unwrap_or(s);
+    let bytes = hex::decode(digits).map_err(|err| format!("invalid hash `{s}`: {err}"))?;
+    if bytes.len() != 32 {
+        return Err(format!(
+            "invalid hash `{s}`: expected 32 bytes (64 hex digits), got {}",
+            bytes.len()
+        ));
+    }
+    Ok(H256::from_slice(&bytes))
+}
+
+/// Account of a dev user name, e.g. `alice`, or of an SS58 address
+pub fn parse_account(s: &str) -> Result<AccountId32> {
+    use clap::ValueEnum;
+    let s = s.trim();
+    match User::from_str(s, true) {
+        Ok(user) => Ok(user.account_id()),
+        Err(_) => AccountId32::from_str(s).map_err(|err| anyhow!("invalid account `{s}`: {err}")),
+    }
+}
+
+/// Block number, accepting `_` separators and a leading `#`, e.g. `#1_000_000`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct BlockNumber(pub u32);
+
+impl FromStr for BlockNumber {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.trim()
+            .trim_start_matches('#')
+            .replace('_', "")
+            .parse()
+            .map(BlockNumber)
+            .map_err(|err| format!("invalid block number `{s}`: {err}"))
+    }
+}
+
+impl Display for BlockNumber {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+impl From<BlockNumber> for u32 {
+    fn from(block: BlockNumber) -> Self {
+        block.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hashes_parse_with_or_without_prefix() {
+        let hash = H256::repeat_byte(0xab);
+        let digits = "ab".repeat(32);
+        assert_eq!(parse_h256(&format!("0x{digits}")), Ok(hash));
+        assert_eq!(parse_h256(&digits), Ok(hash));
+        assert_eq!(parse_h256(&format!("  0x{digits}\n")), Ok(hash));
+    }
+
+    #[test]
+    fn hashes_must_be_32_bytes() {
+        let err = parse_h256(&format!("0x{}", "ab".repeat(31))).unwrap_err();
+        assert!(
+            err.ends_with("expected 32 bytes (64 hex digits), got 31"),
+            "{err}"
+        );
+        let err = parse_h256(&"ab".repeat(33)).unwrap_err();
+        assert!(err.ends_with("got 33"), "{err}");
+    }
+
+    #[test]
+    fn hashes_must_be_hex() {
+        let err = parse_h256(&format!("0x{}a", "ab".repeat(31))).unwrap_err();
+        assert!(err.contains("Odd number of digits"), "{err}");
+        let err = parse_h256(&format!("0x{}zz", "ab".repeat(31))).unwrap_err();
+        assert!(err.contains("Invalid character 'z'"), "{err}");
+    }
+
+    fn properties() -> ChainProperties {
+        ChainProperties {
+            ss58_prefix: 0,
+            decimals: 10,
+            symbol: "DOT".to_string(),
+        }
+    }
+
+    #[test]
+    fn balances_parse_as_planck_or_tokens() {
+        assert_eq!("1_000".parse(), Ok(Balance::Planck(1_000)));
+        let tokens = |amount: &str, symbol: Option<&str>| Balance::Tokens {
+            amount: amount.to_string(),
+            symbol: symbol.map(str::to_string),
+        };
+        assert_eq!("1.5".parse(), Ok(tokens("1.5", None)));
+        assert_eq!(" 2 DOT ".parse(), Ok(tokens("2", Some("DOT"))));
+        assert_eq!("0.25dot".parse(), Ok(tokens("0.25", Some("dot"))));
+
+        assert!("".parse::<Balance>().is_err());
+        assert!("1.2.3".parse::<Balance>().is_err());
+        assert!("1 D0T".parse::<Balance>().is_err());
+        assert!("340282366920938463463374607431768211456"
+            .parse::<Balance>()
+            .is_err());
+    }
+
+    #[test]
+    fn balances_convert_to_planck() {
+        let properties = properties();
+        let planck = |s: &str| s.parse::<Balance>().unwrap().planck(&properties);
+        assert_eq!(planck("7").unwrap(), 7);
+        assert_eq!(planck("1.5").unwrap(), 15_000_000_000);
+        assert_eq!(planck("2 dot").unwrap(), 20_000_000_000);
+        assert_eq!(planck("0.0000000001 DOT").unwrap(), 1);
+
+        let err = planck("1 KSM").unwrap_err();
+        assert_eq!(err.to_string(), "the chain token is DOT, not KSM");
+        let err = planck("0.00000000001").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "0.00000000001 has more than the 10 decimals of the token"
+        );
+        let err = planck("34028236692093846346337460743.1768211456").unwrap_err();
+        assert!(err.to_string().ends_with("is too large"), "{err}");
+    }
+
+    #[test]
+    fn block_numbers_accept_separators_and_hash() {
+        assert_eq!("#1_000_000".parse(), Ok(BlockNumber(1_000_000)));
+        assert_eq!(" 42 ".parse(), Ok(BlockNumber(42)));
+        assert!("#".parse::<BlockNumber>().is_err());
+        assert!("4_294_967_296".parse::<BlockNumber>().is_err());
+        assert!("-1".parse::<BlockNumber>().is_err());
+    }
+}
@@ -0,0 +1,272 @@
+//! Report of the voters of a referendum, ranked by their conviction-weighted votes, with their
+//! on-chain identity and the split between their own votes and the votes delegated to them.
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use codec::Encode;
+use serde_json::json;
+use subxt::ext::futures::TryStreamExt;
+use subxt::utils::AccountId32;
+
+use crate::chain::ChainConfig;
+use crate::kitchensink;
+use crate::kitchensink::runtime_types::pallet_conviction_voting::vote::{
+    AccountVote as CvAccountVote, Voting,
+};
+use crate::kitchensink::runtime_types::pallet_democracy::vote::{
+    AccountVote, Voting as DemocracyVoting,
+};
+use crate::kitchensink::runtime_types::pallet_identity::types::Data;
+use crate::output::Table;
+use crate::units::Conviction;
+use crate::{storage_key_account, storage_key_class, Governance, Program, STORAGE_PAGE_SIZE};
+
+// Voter of the referendum
+struct Voter {
+    account: AccountId32,
+    vote: String,
+    // votes of the voter's own balance
+    direct: u128,
+    // votes delegated to the voter, only counted for the standard votes
+    delegated: u128,
+    delegators: usize,
+}
+
+// Delegation, keyed by target and class, `None` with the democracy pallet
+type Delegations = BTreeMap<(AccountId32, Option<u16>), Vec<u128>>;
+
+/// Print the voters of a referendum, ranked by votes
+pub async fn run<C: ChainConfig>(program: &Program<C>, index: u32) -> Result<()> {
+    let mut voters = match program.governance()? {
+        Governance::OpenGov => open_gov_voters(program, index).await?,
+        Governance::Democracy => democracy_voters(program, index).await?,
+    };
+    if voters.is_empty() {
+        program
+            .output
+            .message(&format!("no vote on referendum {index}"));
+        return Ok(());
+    }
+    voters.sort_by_key(|voter| std::cmp::Reverse(voter.direct + voter.delegated));
+
+    let with_identity = program.api.metadata().pallet_by_name("Identity").is_some();
+    let mut table = Table::new(&[
+        "rank",
+        "voter",
+        "identity",
+        "vote",
+        "direct",
+        "delegated",
+        "delegators",
+        "total",
+    ]);
+    let (mut direct, mut delegated) = (0u128, 0u128);
+    for (rank, voter) in voters.iter().enumerate() {
+        let identity = if with_identity {
+            identity(program, &voter.account).await?
+        } else {
+            None
+        };
+        let total = voter.direct + voter.delegated;
+        direct += voter.direct;
+        delegated += voter.delegated;
+        program.output.record_json(
+            "voter",
+            json!({
+                "rank": rank + 1,
+                "voter": program.address(&voter.account),
+                "identity": identity,
+                "vote": voter.vote,
+                "direct": voter.direct,
+                "delegated": voter.delegated,
+                "delegators": voter.delegators,
+                "total": total,
+            }),
+        );
+        table.add_row(vec![
+            (rank + 1).to_string(),
+            program.address(&voter.account),
+            identity.unwrap_or_default(),
+            voter.vote.clone(),
+            program.balance(voter.direct),
+            program.balance(voter.delegated),
+            voter.delegators.to_string(),
+            program.balance(total),
+        ]);
+    }
+    program.output.table(&table)?;
+    program.output.record(
+        "voters",
+        json!({ "voters": voters.len(), "direct": direct, "delegated": delegated }),
+        format!(
+            "{} voters, {} direct and {} delegated votes",
+            voters.len(),
+            program.balance(direct),
+            program.balance(delegated)
+        ),
+    );
+    Ok(())
+}
+
+// Votes on the referendum of each class, finished referenda keep them until removed
+async fn open_gov_voters<C: ChainConfig>(program: &Program<C>, index: u32) -> Result<Vec<Voter>> {
+    let query = kitchensink::storage().conviction_voting().voting_for_iter();
+    let mut votes = Vec::new();
+    let mut delegations = Delegations::new();
+    // only the votes on the referendum and the delegations are kept as the entries arrive
+    let entries = program.stream_cached_storage(&query, STORAGE_PAGE_SIZE);
+    tokio::pin!(entries);
+    while let Some((key, voting)) = entries.try_next().await? {
+        // the account is followed by the hashed `u16` class
+        let account = storage_key_account(&key[..key.len() - 10]);
+        let class = storage_key_class(&key);
+        match voting {
+            Voting::Casting(casting) => {
+                if let Some((_, vote)) = casting.votes.0.into_iter().find(|(i, _)| *i == index) {
+                    votes.push((account, class, vote));
+                }
+            }
+            Voting::Delegating(delegating) => {
+                let conviction = delegating.conviction.encode()[0];
+                let conviction = Conviction::from_index(conviction).unwrap_or_default();
+                delegations
+                    .entry((delegating.target, Some(class)))
+                    .or_default()
+                    .push(conviction.votes(delegating.balance));
+            }
+            Voting::__Ignore(_) => {}
+        }
+    }
+
+    let voters = votes
+        .into_iter()
+        .map(|(account, class, vote)| {
+            let (vote, direct, standard) = match vote {
+                CvAccountVote::Standard { vote, balance } => standard_vote(vote.0, balance),
+                CvAccountVote::Split { aye, nay } => ("split".to_string(), (aye + nay) / 10, false),
+                CvAccountVote::SplitAbstain { aye, nay, abstain } => (
+                    "split abstain".to_string(),
+                    (aye + nay + abstain) / 10,
+                    false,
+                ),
+            };
+            voter(account, Some(class), vote, direct, standard, &delegations)
+        })
+        .collect();
+    Ok(voters)
+}
+
+// Direct votes on the referendum, the delegations apply to every referendum
+async fn democracy_voters<C: ChainConfig>(program: &Program<C>, index: u32) -> Result<Vec<Voter>> {
+    let query = kitchensink::storage().democracy().voting_of_iter();
+    let mut votes = Vec::new();
+    let mut delegations = Delegations::new();
+    let entries = program.stream_cached_storage(&query, STORAGE_PAGE_SIZE);
+    tokio::pin!(entries);
+    while let Some((key, voting)) = entries.try_next().await? {
+        let account = storage_key_account(&key);
+        match voting {
+            DemocracyVoting::Direct { votes: direct, .. } => {
+                if let Some((_, vote)) = direct.0.into_iter().find(|(i, _)| *i == index) {
+                    votes.push((account, vote));
+                }
+            }
+            DemocracyVoting::Delegating {
+                balance,
+                target,
+                conviction,
+                ..
+            } => {
+                let conviction = Conviction::from_index(conviction.encode()[0]).unwrap_or_default();
+                delegations
+                    .entry((target, None))
+                    .or_default()
+                    .push(conviction.votes(balance));
+            }
+        }
+    }
+
+    let voters = votes
+        .into_iter()
+        .map(|(account, vote)| {
+            let (vote, direct, standard) = match vote {
+                AccountVote::Standard { vote, balance } => standard_vote(vote.0, balance),
+                AccountVote::Split { aye, nay } => ("split".to_string(), (aye + nay) / 10, false),
+            };
+            voter(account, None, vote, direct, standard, &delegations)
+        })
+        .collect();
+    Ok(voters)
+}
+
+// Direction and votes of an encoded standard vote
+fn standard_vote(vote: u8, balance: u128) -> (String, u128, bool) {
+    let aye = vote & 0b1000_0000 != 0;
+    let conviction = Conviction::from_index(vote & 0b0111_1111).unwrap_or_default();
+    let direction = if aye { "aye" } else { "nay" };
+    (
+        format!("{direction} {conviction}"),
+        conviction.votes(balance),
+        true,
+    )
+}
+
+// Voter with the votes delegated to it in the class, split votes don't carry delegations
+fn voter(
+    account: AccountId32,
+    class: Option<u16>,
+    vote: String,
+    direct: u128,
+    standard: bool,
+    delegations: &Delegations,
+) -> Voter {
+    let delegated = match delegations.get(&(account.clone(), class)) {
+        Some(votes) if standard => votes.as_slice(),
+        _ => &[],
+    };
+    Voter {
+        account,
+        vote,
+        direct,
+        delegated: delegated.iter().sum(),
+        delegators: delegated.len(),
+    }
+}
+
+// Display name of an account, `parent/sub` for the sub-identities
+async fn identity<C: ChainConfig>(
+    program: &Program<C>,
+    account: &AccountId32,
+) -> Result<Option<String>> {
+    let api = program.storage().await?;
+    let query = kitchensink::storage().identity().identity_of(account);
+    if let Some(registration) = api.fetch(&query).await? {
+        return Ok(raw_data(&registration.info.display));
+    }
+
+    let query = kitchensink::storage().identity().super_of(account);
+    let Some((parent, sub)) = api.fetch(&query).await? else {
+        return Ok(None);
+    };
+    let query = kitchensink::storage().identity().identity_of(&parent);
+    let parent = match api.fetch(&query).await? {
+        Some(registration) => raw_data(&registration.info.display),
+        None => None,
+    }
+    .unwrap_or_else(|| program.address(&parent));
+    Ok(Some(format!(
+        "{parent}/{}",
+        raw_data(&sub).unwrap_or_default()
+    )))
+}
+
+// Text of an identity field, the `Raw` variants are indexed by their length plus one
+fn raw_data(data: &Data) -> Option<String> {
+    let encoded = data.encode();
+    match encoded.split_first() {
+        Some((&variant, raw)) if (1..=33).contains(&variant) && !raw.is_empty() => {
+            Some(String::from_utf8_lossy(raw).into_owned())
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,81 @@
+//! Runtime version of a wasm runtime blob, read from its `runtime_version` custom section without
+//! instantiating the blob, to check a runtime upgrade before proposing it.
+use anyhow::Result;
+use codec::Decode;
+
+// Prefix of the zstd compressed blobs, `sp_maybe_compressed_blob::ZSTD_PREFIX`
+const ZSTD_PREFIX: [u8; 8] = [82, 188, 83, 118, 70, 219, 142, 5];
+
+// Magic number and version 1 of the wasm binary format
+const WASM_HEADER: [u8; 8] = [0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00];
+
+/// Leading fields of `sp_version::RuntimeVersion`, as embedded by `sp_version::runtime_version`
+#[derive(Debug, Clone)]
+pub struct RuntimeVersion {
+    pub spec_name: String,
+    pub impl_name: String,
+    pub authoring_version: u32,
+    pub spec_version: u32,
+    pub impl_version: u32,
+}
+
+/// Whether the blob is zstd compressed, as the `.compact.compressed.wasm` build artifacts
+pub fn is_compressed(code: &[u8]) -> bool {
+    code.starts_with(&ZSTD_PREFIX)
+}
+
+/// Read the runtime version of an uncompressed wasm blob
+pub fn runtime_version(code: &[u8]) -> Result<RuntimeVersion> {
+    let mut rest = code
+        .strip_prefix(&WASM_HEADER)
+        .ok_or_else(|| anyhow::anyhow!("not a wasm blob"))?;
+
+    while let Some((&id, tail)) = rest.split_first() {
+        rest = tail;
+        let size = read_leb128(&mut rest)? as usize;
+        if size > rest.len() {
+            anyhow::bail!("truncated wasm section");
+        }
+        let (mut section, tail) = rest.split_at(size);
+        rest = tail;
+
+        // custom sections have id 0 and start with their name
+        if id != 0 {
+            continue;
+        }
+        let name_len = read_leb128(&mut section)? as usize;
+        if name_len > section.len() {
+            anyhow::bail!("truncated wasm custom section name");
+        }
+        let (name, mut payload) = section.split_at(name_len);
+        if name == b"runtime_version" {
+            let (spec_name, impl_name, authoring_version, spec_version, impl_version) =
+                <(String, String, u32, u32, u32)>::decode(&mut payload)?;
+            return Ok(RuntimeVersion {
+                spec_name,
+                impl_name,
+                authoring_version,
+                spec_version,
+                impl_version,
+            });
+        }
+    }
+
+    anyhow::bail!("no runtime_version section in the wasm blob")
+}
+
+// Read an unsigned LEB128 integer, the variable length encoding of the wasm sizes
+fn read_leb128(bytes: &mut &[u8]) -> Result<u32> {
+    let mut value = 0u32;
+    for shift in (0..35).step_by(7) {
+        let (&byte, rest) = bytes
+            .split_first()
+            .ok_or_else(|| anyhow::anyhow!("truncated wasm blob"))?;
+        *bytes = rest;
+        value |= ((byte & 0x7f) as u32) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    anyhow::bail!("invalid wasm integer")
+}
@@ -0,0 +1,117 @@
+//! Harness of the end to end tests, built with `--features integration`.
+//!
+//! The tests connect to the node at `DEMOCRACY_TEST_URL` when it is set, or else launch
+//! `substrate-node --dev --tmp`, the binary being overridden by `DEMOCRACY_TEST_NODE`. The node
+//! must run a runtime with short launch and voting periods for the referenda to pass, as the
+//! kitchensink runtime does when built for fast tests.
+use std::net::TcpListener;
+use std::process::{Child, Command, Stdio};
+use std::time::Duration;
+
+use democracy_cli::chain::Chain;
+use democracy_cli::output::Format;
+use democracy_cli::transport::Transport;
+use democracy_cli::{Connection, Options, Program, User};
+
+// Time to wait for a launched node to accept connections
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(60);
+
+// Default time to wait for an event, the test runtime periods are a few blocks long
+const DEFAULT_EVENT_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// Node the tests run against, killed when dropped if it was launched by the harness
+pub struct Node {
+    pub url: String,
+    child: Option<Child>,
+}
+
+impl Node {
+    /// Connect to `DEMOCRACY_TEST_URL`, or launch a dev node
+    pub async fn start() -> Self {
+        if let Ok(url) = std::env::var("DEMOCRACY_TEST_URL") {
+            return Self { url, child: None };
+        }
+
+        let binary =
+            std::env::var("DEMOCRACY_TEST_NODE").unwrap_or_else(|_| "substrate-node".to_string());
+        let port = free_port();
+        let child = Command::new(&binary)
+            .args(["--dev", "--tmp", "--rpc-port", &port.to_string()])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .unwrap_or_else(|err| panic!("failed to launch {binary}: {err}"));
+        let node = Self {
+            url: format!("ws://127.0.0.1:{port}"),
+            child: Some(child),
+        };
+
+        let started = tokio::time::timeout(STARTUP_TIMEOUT, async {
+            while tokio::net::TcpStream::connect(("127.0.0.1", port))
+                .await
+                .is_err()
+            {
+                tokio::time::sleep(Duration::from_millis(200)).await;
+            }
+        });
+        started
+            .await
+            .unwrap_or_else(|_| panic!("{binary} didn't open its RPC port {port}"));
+        node
+    }
+
+    /// Program signing as a dev user, confirming the transactions without asking
+    pub async fn program(&self, user: User) -> Program {
+        let timeout = std::env::var("DEMOCRACY_TEST_TIMEOUT")
+            .ok()
+            .and_then(|secs| secs.parse().ok())
+            .map_or(DEFAULT_EVENT_TIMEOUT, Duration::from_secs);
+        let connection = Connection::Rpc(vec![self.url.clone()], Transport::default());
+        let options = Options {
+            governance: None,
+            runtime: None,
+            assume_yes: true,
+            force: false,
+            dynamic: false,
+            strict: false,
+            ss58_prefix: None,
+            rps: None,
+            fork: false,
+            output: Format::Json,
+            columns: Vec::new(),
+            raw: true,
+            timeout: Some(timeout),
+            audit_log: None,
+            notify: Vec::new(),
+            #[cfg(feature = "cache")]
+            refresh: false,
+        };
+
+        Program::new(
+            connection,
+            user,
+            user.keypair(),
+            Chain::Local.properties(),
+            options,
+        )
+        .await
+        .expect("failed to connect to the test node")
+    }
+}
+
+impl Drop for Node {
+    fn drop(&mut self) {
+        if let Some(child) = &mut self.child {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+// A port no other process listens on
+fn free_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0")
+        .and_then(|listener| listener.local_addr())
+        .map(|addr| addr.port())
+        .expect("no free port")
+}
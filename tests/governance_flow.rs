@@ -0,0 +1,82 @@
+//! Democracy proposal from its preimage to its enactment: noting the preimage, proposing,
+//! seconding, voting on the referendum and waiting for it to pass.
+mod common;
+
+use codec::Encode;
+use democracy_cli::kitchensink;
+use democracy_cli::kitchensink::runtime_types::frame_support::traits::preimages::Bounded;
+use democracy_cli::units::Conviction;
+use democracy_cli::{create_vote, User, PROPOSAL_DEPOSIT};
+
+use common::Node;
+
+#[tokio::test]
+async fn preimage_propose_second_vote_pass() {
+    let node = Node::start().await;
+    let alice = node.program(User::Alice).await;
+    let bob = node.program(User::Bob).await;
+
+    // preimage
+    let remark = format!("democracy-cli integration test {}", std::process::id());
+    let image = kitchensink::Call::System(
+        kitchensink::runtime_types::frame_system::pallet::Call::remark {
+            remark: remark.into_bytes(),
+        },
+    )
+    .encode();
+    let (hash, len) = alice.note_preimage(image.clone()).await.unwrap();
+    alice.verify_preimage(hash, &image).await.unwrap();
+
+    // proposal
+    let tx = kitchensink::tx()
+        .democracy()
+        .propose(Bounded::Lookup { hash, len }, PROPOSAL_DEPOSIT);
+    let events = alice.submit_and_watch(&tx).await.unwrap();
+    let proposed = events
+        .find_first::<kitchensink::democracy::events::Proposed>()
+        .unwrap()
+        .expect("no Democracy::Proposed event");
+
+    // second
+    let tx = kitchensink::tx()
+        .democracy()
+        .second(proposed.proposal_index);
+    let events = bob.submit_and_watch(&tx).await.unwrap();
+    let seconded = events
+        .find_first::<kitchensink::democracy::events::Seconded>()
+        .unwrap()
+        .expect("no Democracy::Seconded event");
+    assert_eq!(seconded.prop_index, proposed.proposal_index);
+    assert_eq!(seconded.seconder, User::Bob.account_id());
+
+    // the proposal is tabled as a referendum at the end of the launch period
+    let started = alice
+        .wait_for_event::<kitchensink::democracy::events::Started>()
+        .await
+        .unwrap();
+
+    // vote
+    let tx = create_vote(
+        started.ref_index,
+        true,
+        Conviction::Locked1x,
+        PROPOSAL_DEPOSIT,
+    );
+    let events = alice.submit_and_watch(&tx).await.unwrap();
+    let voted = events
+        .find_first::<kitchensink::democracy::events::Voted>()
+        .unwrap()
+        .expect("no Democracy::Voted event");
+    assert_eq!(voted.ref_index, started.ref_index);
+    assert_eq!(voted.voter, User::Alice.account_id());
+
+    // the referendum passes at the end of the voting period
+    let ref_index = started.ref_index;
+    let (_, passed) = alice
+        .wait_for_event_where::<kitchensink::democracy::events::Passed>(|ev| {
+            ev.ref_index == ref_index
+        })
+        .await
+        .unwrap();
+    assert_eq!(passed.ref_index, ref_index);
+}
@@ -0,0 +1,105 @@
+//! Records of the governance events of a block, decoded with the bundled kitchensink metadata and
+//! served by a mock node instead of a real one.
+use codec::{Compact, Encode};
+use democracy_cli::cache::Record;
+use democracy_cli::indexer::records;
+use democracy_cli::kitchensink;
+use democracy_cli::kitchensink::runtime_types::pallet_democracy::vote_threshold::VoteThreshold;
+use democracy_cli::runtime::Runtime;
+use democracy_cli::Governance;
+use subxt::backend::rpc::{RawRpcFuture, RawRpcSubscription, RawValue, RpcClient, RpcClientT};
+use subxt::backend::RuntimeVersion;
+use subxt::error::RpcError;
+use subxt::events::{Events, Phase};
+use subxt::utils::H256;
+use subxt::{Metadata, OnlineClient, SubstrateConfig};
+
+// Node answering the `System::Events` storage queries with the same bytes
+struct EventsNode(Vec<u8>);
+
+impl RpcClientT for EventsNode {
+    fn request_raw<'a>(
+        &'a self,
+        method: &'a str,
+        _params: Option<Box<RawValue>>,
+    ) -> RawRpcFuture<'a, Box<RawValue>> {
+        Box::pin(async move {
+            if method != "state_getStorage" {
+                return Err(RpcError::RequestRejected(method.to_string()));
+            }
+            let bytes = serde_json::to_string(&format!("0x{}", hex::encode(&self.0))).unwrap();
+            Ok(RawValue::from_string(bytes).unwrap())
+        })
+    }
+
+    fn subscribe_raw<'a>(
+        &'a self,
+        sub: &'a str,
+        _params: Option<Box<RawValue>>,
+        _unsub: &'a str,
+    ) -> RawRpcFuture<'a, RawRpcSubscription> {
+        Box::pin(async move { Err(RpcError::RequestRejected(sub.to_string())) })
+    }
+}
+
+// Events of a block, as stored in `System::Events`
+async fn events(metadata: &Metadata, records: &[Vec<u8>]) -> Events<SubstrateConfig> {
+    let mut bytes = Compact(records.len() as u32).encode();
+    for record in records {
+        bytes.extend(record);
+    }
+    let runtime_version = RuntimeVersion {
+        spec_version: 0,
+        transaction_version: 0,
+    };
+    let rpc = RpcClient::new(EventsNode(bytes));
+    let api =
+        OnlineClient::from_rpc_client_with(H256::zero(), runtime_version, metadata.clone(), rpc)
+            .unwrap();
+    Events::new_from_client(metadata.clone(), H256::zero(), api)
+        .await
+        .unwrap()
+}
+
+// Event record of the democracy pallet with its encoded fields
+fn democracy_record(metadata: &Metadata, variant: &str, fields: impl Encode) -> Vec<u8> {
+    let pallet = metadata.pallet_by_name("Democracy").unwrap();
+    let index = pallet
+        .event_variants()
+        .unwrap()
+        .iter()
+        .find(|event| event.name == variant)
+        .unwrap()
+        .index;
+
+    let mut record = Phase::ApplyExtrinsic(1).encode();
+    record.extend([pallet.index(), index]);
+    record.extend(fields.encode());
+    record.extend(Vec::<H256>::new().encode());
+    record
+}
+
+#[tokio::test]
+async fn undecodable_event_is_skipped() {
+    let metadata = Runtime::Kitchensink.metadata().unwrap();
+    let started = kitchensink::democracy::events::Started {
+        ref_index: 3,
+        threshold: VoteThreshold::SimpleMajority,
+    };
+    let started = democracy_record(&metadata, "Started", started);
+    // no pallet has the index 255, the bytes can't be decoded
+    let mut unknown = Phase::Finalization.encode();
+    unknown.extend([255, 0, 1, 2, 3]);
+
+    let events = events(&metadata, &[started.clone(), unknown, started]).await;
+    let (records, governance) = records(42, &events);
+    assert!(governance);
+    assert_eq!(
+        records,
+        vec![Record::Started {
+            governance: Governance::Democracy,
+            index: 3,
+            track: None,
+        }]
+    );
+}
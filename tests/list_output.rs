@@ -0,0 +1,208 @@
+//! Snapshots of the text, JSON and CSV rendering of the list commands, fed with storage fixtures
+//! of the bundled kitchensink runtime instead of a node.
+//!
+//! Review the changes with `cargo insta review`, or accept them with `INSTA_UPDATE=always`.
+use democracy_cli::chain::Chain;
+use democracy_cli::chain_api::MockChain;
+use democracy_cli::commands::preimage::print_preimages;
+use democracy_cli::commands::referenda::print_referenda_by_track;
+use democracy_cli::kitchensink;
+use democracy_cli::kitchensink::runtime_types::frame_support::dispatch::RawOrigin;
+use democracy_cli::kitchensink::runtime_types::frame_support::traits::preimages::Bounded;
+use democracy_cli::kitchensink::runtime_types::frame_support::traits::schedule::DispatchTime;
+use democracy_cli::kitchensink::runtime_types::frame_support::traits::tokens::fungible::HoldConsideration;
+use democracy_cli::kitchensink::runtime_types::kitchensink_runtime::OriginCaller;
+use democracy_cli::kitchensink::runtime_types::pallet_conviction_voting::types::Tally;
+use democracy_cli::kitchensink::runtime_types::pallet_preimage::{OldRequestStatus, RequestStatus};
+use democracy_cli::kitchensink::runtime_types::pallet_referenda::types::{
+    DecidingStatus, Deposit, ReferendumInfo,
+};
+use democracy_cli::output::{Format, Output};
+use democracy_cli::runtime::Runtime;
+use democracy_cli::{ReferendumStatus, User};
+use serde_json::Value as Json;
+use subxt::utils::{AccountId32, H256};
+
+const UNIT: u128 = 1_000_000_000_000;
+
+// Chain holding a legacy and two current preimages
+fn preimages_chain() -> MockChain {
+    let mut chain = MockChain::new(Runtime::Kitchensink.metadata().unwrap());
+    let storage = kitchensink::storage().preimage();
+    chain
+        .insert(
+            &storage.status_for(H256::repeat_byte(1)),
+            OldRequestStatus::<_, u128>::Unrequested {
+                deposit: (User::Alice.account_id(), 3 * UNIT / 2),
+                len: 42,
+            },
+        )
+        .unwrap();
+    chain
+        .insert(
+            &storage.request_status_for(H256::repeat_byte(2)),
+            RequestStatus::<AccountId32, HoldConsideration>::Unrequested {
+                ticket: (User::Bob.account_id(), HoldConsideration(2 * UNIT)),
+                len: 1024,
+            },
+        )
+        .unwrap();
+    chain
+        .insert(
+            &storage.request_status_for(H256::repeat_byte(3)),
+            RequestStatus::<AccountId32, HoldConsideration>::Requested {
+                maybe_ticket: None,
+                count: 2,
+                maybe_len: None,
+            },
+        )
+        .unwrap();
+    chain
+}
+
+// Chain holding a preparing, a deciding and a confirming referendum on the root track, the only
+// track of kitchensink, and a finished one
+fn referenda_chain() -> MockChain {
+    let mut chain = MockChain::new(Runtime::Kitchensink.metadata().unwrap());
+    let storage = kitchensink::storage().referenda();
+    let status = |track, deciding, ayes| ReferendumStatus {
+        track,
+        origin: OriginCaller::system(RawOrigin::Root),
+        proposal: Bounded::Lookup {
+            hash: H256::repeat_byte(track as u8),
+            len: 64,
+        },
+        enactment: DispatchTime::After(10),
+        submitted: 100,
+        submission_deposit: Deposit {
+            who: User::Alice.account_id(),
+            amount: UNIT,
+        },
+        decision_deposit: None,
+        deciding,
+        tally: Tally {
+            ayes,
+            nays: UNIT / 4,
+            support: ayes / 2,
+        },
+        in_queue: false,
+        alarm: None,
+    };
+
+    let referenda = [
+        (0, status(0, None, 0)),
+        (
+            1,
+            status(
+                0,
+                Some(DecidingStatus {
+                    since: 120,
+                    confirming: None,
+                }),
+                5 * UNIT,
+            ),
+        ),
+        (
+            2,
+            status(
+                0,
+                Some(DecidingStatus {
+                    since: 110,
+                    confirming: Some(150),
+                }),
+                12 * UNIT,
+            ),
+        ),
+    ];
+    for (index, status) in referenda {
+        chain
+            .insert(
+                &storage.referendum_info_for(index),
+                ReferendumInfo::Ongoing(status),
+            )
+            .unwrap();
+    }
+    chain
+        .insert(
+            &storage.referendum_info_for(3),
+            ReferendumInfo::<u16, OriginCaller, u32, (), u128, Tally<u128>, AccountId32, (u32, u32)>::Rejected(
+                90, None, None,
+            ),
+        )
+        .unwrap();
+    chain
+}
+
+// Lines written by a list command rendered in a format
+async fn render<F, Fut>(format: Format, list: F) -> Vec<String>
+where
+    F: FnOnce(Output) -> Fut,
+    Fut: std::future::Future<Output = Output>,
+{
+    let output = Output::capture(format, User::Alice, Vec::new());
+    list(output).await.captured()
+}
+
+// JSON records of the captured lines
+fn records(lines: Vec<String>) -> Vec<Json> {
+    lines
+        .iter()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn list_preimages() {
+    let chain = preimages_chain();
+    let properties = Chain::Local.properties();
+    let list = |output: Output| async {
+        print_preimages(&chain, &output, &properties, None)
+            .await
+            .unwrap();
+        output
+    };
+
+    insta::assert_snapshot!(
+        "preimages_text",
+        render(Format::Text, list).await.join("\n")
+    );
+    insta::assert_snapshot!("preimages_csv", render(Format::Csv, list).await.join("\n"));
+    insta::assert_json_snapshot!("preimages_json", records(render(Format::Json, list).await));
+}
+
+#[tokio::test]
+async fn list_own_preimages() {
+    let chain = preimages_chain();
+    let properties = Chain::Local.properties();
+    let bob = User::Bob.account_id();
+    let list = |output: Output| async {
+        print_preimages(&chain, &output, &properties, Some(&bob))
+            .await
+            .unwrap();
+        output
+    };
+
+    insta::assert_snapshot!(
+        "own_preimages_text",
+        render(Format::Text, list).await.join("\n")
+    );
+}
+
+#[tokio::test]
+async fn list_referenda_by_track() {
+    let chain = referenda_chain();
+    let properties = Chain::Local.properties();
+    let list = |output: Output| async {
+        print_referenda_by_track(&chain, &output, &properties)
+            .await
+            .unwrap();
+        output
+    };
+
+    insta::assert_snapshot!(
+        "referenda_text",
+        render(Format::Text, list).await.join("\n")
+    );
+    insta::assert_snapshot!("referenda_csv", render(Format::Csv, list).await.join("\n"));
+    insta::assert_json_snapshot!("referenda_json", records(render(Format::Json, list).await));
+}
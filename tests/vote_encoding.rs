@@ -0,0 +1,67 @@
+//! Byte encoding of the standard votes, the conviction in the low bits and the aye flag in the
+//! high bit, as expected by the democracy and conviction voting pallets.
+use democracy_cli::kitchensink::runtime_types::pallet_conviction_voting::vote::{
+    AccountVote as CvAccountVote, Vote as CvVote,
+};
+use democracy_cli::kitchensink::runtime_types::pallet_democracy::vote::{AccountVote, Vote};
+use democracy_cli::units::Conviction;
+use democracy_cli::{create_cv_vote, create_vote};
+
+// Every conviction with its index and the encoded aye and nay votes
+const VOTES: [(Conviction, u8, u8, u8); 7] = [
+    (Conviction::None, 0, 0x80, 0x00),
+    (Conviction::Locked1x, 1, 0x81, 0x01),
+    (Conviction::Locked2x, 2, 0x82, 0x02),
+    (Conviction::Locked3x, 3, 0x83, 0x03),
+    (Conviction::Locked4x, 4, 0x84, 0x04),
+    (Conviction::Locked5x, 5, 0x85, 0x05),
+    (Conviction::Locked6x, 6, 0x86, 0x06),
+];
+
+#[test]
+fn vote_bytes() {
+    for (conviction, index, aye, nay) in VOTES {
+        assert_eq!(conviction.vote(true), aye, "{conviction} aye");
+        assert_eq!(conviction.vote(false), nay, "{conviction} nay");
+        assert_eq!(Conviction::from_index(index), Some(conviction));
+    }
+    assert_eq!(Conviction::from_index(7), None);
+}
+
+#[test]
+fn vote_calls() {
+    for (conviction, _, aye, nay) in VOTES {
+        for (is_aye, byte) in [(true, aye), (false, nay)] {
+            let democracy = create_vote(3, is_aye, conviction, 10);
+            let data = democracy.call_data();
+            assert_eq!(data.ref_index, 3);
+            assert!(matches!(
+                data.vote,
+                AccountVote::Standard { vote: Vote(vote), balance: 10 } if vote == byte
+            ));
+
+            let conviction_voting = create_cv_vote(5, is_aye, conviction, 10);
+            let data = conviction_voting.call_data();
+            assert_eq!(data.poll_index, 5);
+            assert!(matches!(
+                data.vote,
+                CvAccountVote::Standard { vote: CvVote(vote), balance: 10 } if vote == byte
+            ));
+        }
+    }
+}
+
+#[test]
+fn parse_conviction() {
+    for (conviction, index, _, _) in VOTES {
+        assert_eq!(Conviction::parse(&index.to_string()), Ok(conviction));
+        assert_eq!(Conviction::parse(&conviction.to_string()), Ok(conviction));
+    }
+    assert_eq!(Conviction::parse(" 3X "), Ok(Conviction::Locked3x));
+
+    let err = Conviction::parse("9").unwrap_err();
+    assert!(err.contains("between 0 and 6"), "{err}");
+    let err = Conviction::parse("7x").unwrap_err();
+    assert!(err.contains("expected none, 1x"), "{err}");
+    assert!(Conviction::parse("-1").is_err());
+}